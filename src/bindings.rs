@@ -0,0 +1,664 @@
+//! What the user can do with the keyboard and mouse, and which keys and buttons do it.
+//!
+//! Every action is bound to any number of chords: a key or mouse button, pressed while
+//! some modifiers are held. The defaults can be overridden in the `[bindings]` table of
+//! the user config, one entry per action, e.g. `undo = "Ctrl+Z"` or
+//! `pan = ["MiddleMouse", "Shift+MiddleMouse"]`.
+
+use std::{collections::HashMap, fmt, path::Path};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use winit::event::{MouseButton, VirtualKeyCode};
+
+use crate::config::BindingConfig;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// Rotate the viewport camera while the button is held.
+    Orbit,
+    /// Click to select, or drag out a box or a gizmo handle.
+    Select,
+    /// Move the viewport camera sideways while the button is held.
+    Pan,
+    Undo,
+    Redo,
+    OpenRenderWindow,
+    ViewFront,
+    ViewBack,
+    ViewRight,
+    ViewLeft,
+    ViewTop,
+    ViewBottom,
+    /// Switch between perspective and orthographic projection.
+    ToggleProjection,
+    FrameSelected,
+    FrameAll,
+    ToggleFlying,
+    StopFlying,
+    FlyForward,
+    FlyBackward,
+    FlyLeft,
+    FlyRight,
+    FlyUp,
+    FlyDown,
+    /// Index into the scene's bookmarks.
+    GoToBookmark(usize),
+}
+
+impl Action {
+    pub const ALL: [Self; 32] = [
+        Self::Orbit,
+        Self::Select,
+        Self::Pan,
+        Self::Undo,
+        Self::Redo,
+        Self::OpenRenderWindow,
+        Self::ViewFront,
+        Self::ViewBack,
+        Self::ViewRight,
+        Self::ViewLeft,
+        Self::ViewTop,
+        Self::ViewBottom,
+        Self::ToggleProjection,
+        Self::FrameSelected,
+        Self::FrameAll,
+        Self::ToggleFlying,
+        Self::StopFlying,
+        Self::FlyForward,
+        Self::FlyBackward,
+        Self::FlyLeft,
+        Self::FlyRight,
+        Self::FlyUp,
+        Self::FlyDown,
+        Self::GoToBookmark(0),
+        Self::GoToBookmark(1),
+        Self::GoToBookmark(2),
+        Self::GoToBookmark(3),
+        Self::GoToBookmark(4),
+        Self::GoToBookmark(5),
+        Self::GoToBookmark(6),
+        Self::GoToBookmark(7),
+        Self::GoToBookmark(8),
+    ];
+
+    pub fn name(self) -> String {
+        match self {
+            Self::Orbit => "Orbit".to_string(),
+            Self::Select => "Select".to_string(),
+            Self::Pan => "Pan".to_string(),
+            Self::Undo => "Undo".to_string(),
+            Self::Redo => "Redo".to_string(),
+            Self::OpenRenderWindow => "Open render window".to_string(),
+            Self::ViewFront => "Front view".to_string(),
+            Self::ViewBack => "Back view".to_string(),
+            Self::ViewRight => "Right view".to_string(),
+            Self::ViewLeft => "Left view".to_string(),
+            Self::ViewTop => "Top view".to_string(),
+            Self::ViewBottom => "Bottom view".to_string(),
+            Self::ToggleProjection => "Toggle projection".to_string(),
+            Self::FrameSelected => "Frame selected".to_string(),
+            Self::FrameAll => "Frame all".to_string(),
+            Self::ToggleFlying => "Toggle fly mode".to_string(),
+            Self::StopFlying => "Stop flying".to_string(),
+            Self::FlyForward => "Fly forward".to_string(),
+            Self::FlyBackward => "Fly backward".to_string(),
+            Self::FlyLeft => "Fly left".to_string(),
+            Self::FlyRight => "Fly right".to_string(),
+            Self::FlyUp => "Fly up".to_string(),
+            Self::FlyDown => "Fly down".to_string(),
+            Self::GoToBookmark(index) => format!("Go to bookmark {}", index + 1),
+        }
+    }
+
+    /// The key of the action in the `[bindings]` table of the user config.
+    pub fn config_name(self) -> String {
+        match self {
+            Self::GoToBookmark(index) => format!("go_to_bookmark_{}", index + 1),
+            _ => self.name().to_lowercase().replace(' ', "_"),
+        }
+    }
+
+    /// Whether the action lasts for as long as a mouse button is held, rather than
+    /// happening once when its chord is pressed. These can only be bound to mouse
+    /// buttons.
+    pub fn is_drag(self) -> bool {
+        matches!(self, Self::Orbit | Self::Select | Self::Pan)
+    }
+
+    fn default_chords(self) -> &'static [&'static str] {
+        match self {
+            Self::Orbit => &["Shift+LeftMouse"],
+            // Ctrl adds to or removes from the selection rather than replacing it.
+            Self::Select => &["LeftMouse", "Ctrl+LeftMouse"],
+            Self::Pan => &["MiddleMouse", "Shift+MiddleMouse"],
+            Self::Undo => &["Ctrl+Z"],
+            Self::Redo => &["Ctrl+Shift+Z"],
+            Self::OpenRenderWindow => &["R"],
+            Self::ViewFront => &["Numpad1"],
+            Self::ViewBack => &["Ctrl+Numpad1"],
+            Self::ViewRight => &["Numpad3"],
+            Self::ViewLeft => &["Ctrl+Numpad3"],
+            Self::ViewTop => &["Numpad7"],
+            Self::ViewBottom => &["Ctrl+Numpad7"],
+            Self::ToggleProjection => &["Numpad5"],
+            Self::FrameSelected => &["F", "NumpadDecimal"],
+            Self::FrameAll => &["Home"],
+            Self::ToggleFlying => &["Shift+F"],
+            Self::StopFlying => &["Escape"],
+            Self::FlyForward => &["W"],
+            Self::FlyBackward => &["S"],
+            Self::FlyLeft => &["A"],
+            Self::FlyRight => &["D"],
+            Self::FlyUp => &["E"],
+            Self::FlyDown => &["Q"],
+            Self::GoToBookmark(index) => {
+                static DIGITS: [&str; 9] = ["1", "2", "3", "4", "5", "6", "7", "8", "9"];
+                DIGITS.get(index).map_or(&[], std::slice::from_ref)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    /// The super key, i.e. the Windows key, or command on macOS.
+    pub logo: bool,
+}
+
+/// Written the same way as in chords, e.g. "Z" or "LeftMouse", when serialized.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum Trigger {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+}
+
+impl Trigger {
+    /// Parses keys and mouse buttons written like "Z" or "LeftMouse". Case doesn't
+    /// matter.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        parse_trigger(text)
+            .ok_or_else(|| format!("'{}' is not a key or mouse button that can be bound", text))
+    }
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Key(key) => write!(f, "{}", key_name(*key)),
+            Self::Mouse(MouseButton::Left) => write!(f, "LeftMouse"),
+            Self::Mouse(MouseButton::Middle) => write!(f, "MiddleMouse"),
+            Self::Mouse(MouseButton::Right) => write!(f, "RightMouse"),
+            Self::Mouse(MouseButton::Other(button)) => write!(f, "Mouse{}", button),
+        }
+    }
+}
+
+impl From<Trigger> for String {
+    fn from(trigger: Trigger) -> Self {
+        trigger.to_string()
+    }
+}
+
+impl TryFrom<String> for Trigger {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, String> {
+        Self::parse(&text)
+    }
+}
+
+/// A key or mouse button, pressed while exactly `modifiers` are held.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Chord {
+    pub modifiers: Modifiers,
+    pub trigger: Trigger,
+}
+
+impl Chord {
+    /// Parses chords written like "Ctrl+Shift+Z" or "Alt+LeftMouse". Case doesn't
+    /// matter.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        let trigger = Trigger::parse(parts.pop().unwrap_or_default())?;
+
+        let mut modifiers = Modifiers::default();
+        for part in parts {
+            let modifier = match part.to_lowercase().as_str() {
+                "shift" => &mut modifiers.shift,
+                "ctrl" | "control" => &mut modifiers.ctrl,
+                "alt" => &mut modifiers.alt,
+                "super" | "cmd" => &mut modifiers.logo,
+                _ => return Err(format!("'{}' is not a modifier", part)),
+            };
+            *modifier = true;
+        }
+
+        Ok(Self { modifiers, trigger })
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.shift {
+            write!(f, "Shift+")?;
+        }
+        if self.modifiers.alt {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.logo {
+            write!(f, "Super+")?;
+        }
+        write!(f, "{}", self.trigger)
+    }
+}
+
+fn parse_trigger(text: &str) -> Option<Trigger> {
+    let lowercase = text.to_lowercase();
+    let button = match lowercase.as_str() {
+        "leftmouse" => Some(MouseButton::Left),
+        "middlemouse" => Some(MouseButton::Middle),
+        "rightmouse" => Some(MouseButton::Right),
+        _ => lowercase
+            .strip_prefix("mouse")
+            .and_then(|number| number.parse().ok())
+            .map(MouseButton::Other),
+    };
+    if let Some(button) = button {
+        return Some(Trigger::Mouse(button));
+    }
+
+    BINDABLE_KEYS
+        .iter()
+        .find(|key| key_name(**key).eq_ignore_ascii_case(text))
+        .map(|key| Trigger::Key(*key))
+}
+
+/// The name of `key` in chords: the number row goes by its digits, and every other key
+/// by its winit name.
+fn key_name(key: VirtualKeyCode) -> String {
+    use VirtualKeyCode::*;
+    let digit = [Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9]
+        .iter()
+        .position(|digit_key| *digit_key == key);
+    match digit {
+        Some(digit) => digit.to_string(),
+        None => format!("{:?}", key),
+    }
+}
+
+const BINDABLE_KEYS: &[VirtualKeyCode] = {
+    use VirtualKeyCode::*;
+    &[
+        A,
+        B,
+        C,
+        D,
+        E,
+        F,
+        G,
+        H,
+        I,
+        J,
+        K,
+        L,
+        M,
+        N,
+        O,
+        P,
+        Q,
+        R,
+        S,
+        T,
+        U,
+        V,
+        W,
+        X,
+        Y,
+        Z,
+        Key0,
+        Key1,
+        Key2,
+        Key3,
+        Key4,
+        Key5,
+        Key6,
+        Key7,
+        Key8,
+        Key9,
+        Numpad0,
+        Numpad1,
+        Numpad2,
+        Numpad3,
+        Numpad4,
+        Numpad5,
+        Numpad6,
+        Numpad7,
+        Numpad8,
+        Numpad9,
+        NumpadAdd,
+        NumpadSubtract,
+        NumpadMultiply,
+        NumpadDivide,
+        NumpadDecimal,
+        NumpadEnter,
+        F1,
+        F2,
+        F3,
+        F4,
+        F5,
+        F6,
+        F7,
+        F8,
+        F9,
+        F10,
+        F11,
+        F12,
+        Escape,
+        Tab,
+        Space,
+        Return,
+        Back,
+        Delete,
+        Insert,
+        Home,
+        End,
+        PageUp,
+        PageDown,
+        Left,
+        Right,
+        Up,
+        Down,
+        Grave,
+        Minus,
+        Equals,
+        LBracket,
+        RBracket,
+        Semicolon,
+        Apostrophe,
+        Comma,
+        Period,
+        Slash,
+        Backslash,
+    ]
+};
+
+/// The chords bound to every action.
+#[derive(Clone, PartialEq)]
+pub struct ActionMap {
+    chords: HashMap<Action, Vec<Chord>>,
+}
+
+impl Default for ActionMap {
+    fn default() -> Self {
+        let chords = Action::ALL
+            .iter()
+            .map(|action| {
+                let chords = action
+                    .default_chords()
+                    .iter()
+                    .map(|chord| Chord::parse(chord).expect("invalid default binding"))
+                    .collect();
+                (*action, chords)
+            })
+            .collect();
+        Self { chords }
+    }
+}
+
+impl ActionMap {
+    /// The default bindings, with those given in the user config replacing them.
+    /// Invalid bindings are ignored, and conflicts are reported, with a warning.
+    pub fn from_config(config: Option<&HashMap<String, BindingConfig>>) -> Self {
+        let mut map = Self::default();
+        for (name, binding) in config.into_iter().flatten() {
+            let Some(action) = Action::ALL
+                .into_iter()
+                .find(|action| action.config_name() == *name)
+            else {
+                log::warn!("Unknown action '{}' in user config bindings.", name);
+                continue;
+            };
+            let chords: Result<Vec<Chord>, String> = binding
+                .chords()
+                .iter()
+                .map(|chord| Chord::parse(chord))
+                .collect();
+            match chords {
+                Ok(chords) => map.set_chords(action, chords),
+                Err(e) => log::warn!("Invalid binding for '{}' in user config: {}.", name, e),
+            }
+        }
+
+        for problem in map.problems() {
+            log::warn!("{}", problem);
+        }
+        map
+    }
+
+    pub fn chords(&self, action: Action) -> &[Chord] {
+        self.chords.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn set_chords(&mut self, action: Action, chords: Vec<Chord>) {
+        self.chords.insert(action, chords);
+    }
+
+    /// The action `trigger` does while exactly `modifiers` are held. If several are
+    /// bound to the same chord, the first in `Action::ALL` wins.
+    pub fn action_for(&self, trigger: Trigger, modifiers: Modifiers) -> Option<Action> {
+        let chord = Chord { modifiers, trigger };
+        Action::ALL
+            .into_iter()
+            .find(|action| self.chords(*action).contains(&chord))
+    }
+
+    /// The actions bound to `trigger` with any modifiers. Used when it is released,
+    /// since the modifiers may have changed since it was pressed.
+    pub fn actions_with_trigger(&self, trigger: Trigger) -> impl Iterator<Item = Action> + '_ {
+        Action::ALL.into_iter().filter(move |action| {
+            self.chords(*action)
+                .iter()
+                .any(|chord| chord.trigger == trigger)
+        })
+    }
+
+    /// How to trigger `action`, for showing in menus. Empty if it isn't bound.
+    pub fn shortcut_text(&self, action: Action) -> String {
+        self.chords(action)
+            .first()
+            .map_or_else(String::new, Chord::to_string)
+    }
+
+    /// Chords bound to more than one action, and drag actions bound to keys.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut bound_to: HashMap<Chord, Action> = HashMap::new();
+        for action in Action::ALL {
+            for chord in self.chords(action) {
+                if let Some(other) = bound_to.get(chord) {
+                    problems.push(format!(
+                        "{} is bound to both '{}' and '{}'.",
+                        chord,
+                        other.name(),
+                        action.name()
+                    ));
+                } else {
+                    bound_to.insert(*chord, action);
+                }
+                if action.is_drag() && matches!(chord.trigger, Trigger::Key(_)) {
+                    problems.push(format!(
+                        "'{}' can only be bound to mouse buttons, not {}.",
+                        action.name(),
+                        chord
+                    ));
+                }
+            }
+        }
+        problems
+    }
+
+    /// Writes the bindings which differ from the defaults into the `[bindings]` table
+    /// of the user config at `path`. The rest of the config, comments included, is
+    /// left as it is.
+    pub fn save_to_config(&self, path: &Path) -> anyhow::Result<()> {
+        let mut config: toml_edit::Document = match std::fs::read_to_string(path) {
+            Ok(contents) => contents
+                .parse()
+                .with_context(|| format!("{} is not a valid config file", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => toml_edit::Document::new(),
+            Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+        };
+
+        let defaults = Self::default();
+        let changed: Vec<Action> = Action::ALL
+            .into_iter()
+            .filter(|action| self.chords(*action) != defaults.chords(*action))
+            .collect();
+        // An existing table is kept where it is, along with the comments before it.
+        if !config.contains_key("bindings") {
+            if changed.is_empty() {
+                return Ok(());
+            }
+            config.insert("bindings", toml_edit::table());
+        }
+        let Some(bindings) = config["bindings"].as_table_mut() else {
+            anyhow::bail!("bindings in {} should be a table", path.display());
+        };
+        bindings.clear();
+        for action in changed {
+            let chords: Vec<String> = self.chords(action).iter().map(Chord::to_string).collect();
+            let value = match chords.as_slice() {
+                [chord] => toml_edit::value(chord),
+                _ => toml_edit::value(chords.into_iter().collect::<toml_edit::Array>()),
+            };
+            bindings.insert(&action.config_name(), value);
+        }
+
+        std::fs::write(path, config.to_string())
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(text: &str) -> Chord {
+        Chord::parse(text).unwrap()
+    }
+
+    #[test]
+    fn chords_round_trip_through_text() {
+        let triggers = BINDABLE_KEYS.iter().map(|key| Trigger::Key(*key)).chain(
+            [
+                MouseButton::Left,
+                MouseButton::Middle,
+                MouseButton::Right,
+                MouseButton::Other(4),
+            ]
+            .map(Trigger::Mouse),
+        );
+        for trigger in triggers {
+            for bits in 0..16 {
+                let modifiers = Modifiers {
+                    shift: bits & 1 != 0,
+                    ctrl: bits & 2 != 0,
+                    alt: bits & 4 != 0,
+                    logo: bits & 8 != 0,
+                };
+                let chord = Chord { modifiers, trigger };
+                assert_eq!(Chord::parse(&chord.to_string()), Ok(chord));
+            }
+        }
+
+        assert_eq!(chord("shift+ctrl+z"), chord("Ctrl+Shift+Z"));
+        assert_eq!(chord("Control + Cmd + 1"), chord("Ctrl+Super+1"));
+        for invalid in ["", "Ctrl+", "Hyper+Z", "Ctrl+Shift", "Mouse"] {
+            assert!(Chord::parse(invalid).is_err(), "{:?} parsed", invalid);
+        }
+    }
+
+    #[test]
+    fn problems_reports_conflicts() {
+        let mut bindings = ActionMap::default();
+        assert_eq!(bindings.problems(), Vec::<String>::new());
+
+        bindings.set_chords(Action::Undo, vec![chord("Ctrl+Shift+Z")]);
+        assert_eq!(
+            bindings.problems(),
+            vec!["Ctrl+Shift+Z is bound to both 'Undo' and 'Redo'.".to_string()]
+        );
+    }
+
+    #[test]
+    fn problems_reports_drag_actions_bound_to_keys() {
+        let mut bindings = ActionMap::default();
+        bindings.set_chords(Action::Orbit, vec![chord("Alt+LeftMouse"), chord("O")]);
+        assert_eq!(
+            bindings.problems(),
+            vec!["'Orbit' can only be bound to mouse buttons, not O.".to_string()]
+        );
+    }
+
+    #[test]
+    fn action_for_requires_the_exact_modifiers() {
+        let bindings = ActionMap::default();
+        let action_for = |text: &str| {
+            let chord = chord(text);
+            bindings.action_for(chord.trigger, chord.modifiers)
+        };
+        assert_eq!(action_for("Ctrl+Z"), Some(Action::Undo));
+        assert_eq!(action_for("Ctrl+Shift+Z"), Some(Action::Redo));
+        assert_eq!(action_for("Z"), None);
+        assert_eq!(action_for("Ctrl+Alt+Z"), None);
+        assert_eq!(action_for("LeftMouse"), Some(Action::Select));
+        assert_eq!(action_for("Shift+LeftMouse"), Some(Action::Orbit));
+        assert_eq!(action_for("Alt+LeftMouse"), None);
+    }
+
+    #[test]
+    fn save_to_config_only_writes_changed_bindings() {
+        let directory =
+            std::env::temp_dir().join(format!("ekki-bindings-test-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("config.toml");
+        let original =
+            "# How much to log.\nlog_level = \"warn\"\n\n[viewport]\nzoom_to_cursor = true\n";
+        std::fs::write(&path, original).unwrap();
+
+        let read_bindings = || {
+            let config: toml::Table = std::fs::read_to_string(&path).unwrap().parse().unwrap();
+            let bindings: HashMap<String, BindingConfig> =
+                config["bindings"].clone().try_into().unwrap();
+            bindings
+        };
+
+        let mut bindings = ActionMap::default();
+        bindings.set_chords(Action::Undo, vec![chord("Ctrl+U")]);
+        bindings.set_chords(Action::Pan, vec![chord("RightMouse"), chord("MiddleMouse")]);
+        bindings.set_chords(Action::FrameAll, vec![]);
+        bindings.save_to_config(&path).unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.starts_with(original), "{}", contents);
+        let saved = read_bindings();
+        let mut names: Vec<&str> = saved.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        assert_eq!(names, ["frame_all", "pan", "undo"]);
+        assert!(ActionMap::from_config(Some(&saved)) == bindings);
+
+        // Going back to the defaults empties the table, which stays where it was.
+        ActionMap::default().save_to_config(&path).unwrap();
+        assert!(read_bindings().is_empty());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -25,7 +25,11 @@ pub struct Camera {
 impl Camera {
     pub fn initialize(screen_width: f32, screen_height: f32) -> Self {
         let view_info = ViewInfo::initialize();
-        let projection_info = ProjectionInfo::initialize(screen_width, screen_height);
+        let projection_info = ProjectionInfo::initialize(
+            screen_width,
+            screen_height,
+            view_info.z_offset.to_glam_vec3().length(),
+        );
         let view_projection_matrix = OPENGL_TO_WGPU_MATRIX
             * projection_info.get_projection_matrix()
             * view_info.get_view_matrix();
@@ -59,12 +63,80 @@ impl Camera {
         self.get_view_projection_matrix().invert().unwrap()
     }
 
+    /// world -> camera space
+    pub fn get_view_matrix(&self) -> glam::Mat4 {
+        self.view_info.get_view_matrix().to_glam_mat4()
+    }
+
+    /// The ray through the point `ndc` on the screen, where x and y go from -1 at the
+    /// bottom left to 1 at the top right. Returns the world space point where the ray
+    /// crosses the near plane, and its normalized direction.
+    pub fn get_ray_through(&self, ndc: glam::Vec2) -> (glam::Vec3, glam::Vec3) {
+        let inverse = self.get_view_projection_matrix_inverse().to_glam_mat4();
+        // `OPENGL_TO_WGPU_MATRIX` maps the near plane to a depth of 0.5, and depth
+        // increases away from the camera.
+        let near = inverse.project_point3(ndc.extend(0.5));
+        let further = inverse.project_point3(ndc.extend(0.75));
+        (near, (further - near).normalize())
+    }
+
+    /// Where `point` ends up on the screen, in the same coordinates as for
+    /// `get_ray_through()`. Returns `None` for points behind the camera.
+    pub fn project_point(&self, point: glam::Vec3) -> Option<glam::Vec2> {
+        let clip = self.get_view_projection_matrix().to_glam_mat4() * point.extend(1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+        Some(clip.truncate().truncate() / clip.w)
+    }
+
+    /// How long something `pixels` long on screen is in the world, at the depth of
+    /// `point`.
+    pub fn world_size_of_pixels(
+        &self,
+        point: glam::Vec3,
+        pixels: f32,
+        window_size: glam::Vec2,
+    ) -> f32 {
+        let view_projection = self.get_view_projection_matrix().to_glam_mat4();
+        let projected = view_projection.project_point3(point);
+        let offset = glam::Vec3::new(0.0, 2.0 * pixels / window_size.y, 0.0);
+        view_projection
+            .inverse()
+            .project_point3(projected + offset)
+            .distance(point)
+    }
+
     pub fn to_rend3_camera(&self) -> rend3::types::Camera {
-        rend3::types::Camera {
-            projection: rend3::types::CameraProjection::Perspective {
-                vfov: self.projection_info.vertical_fov,
-                near: self.projection_info.z_near,
+        let lens = &self.projection_info.lens;
+        let projection = match self.projection_info.kind {
+            ProjectionKind::Perspective => match lens.z_far {
+                None => rend3::types::CameraProjection::Perspective {
+                    vfov: lens.vertical_fov,
+                    near: lens.z_near,
+                },
+                // rend3 only builds projections with an infinite far plane. Like those,
+                // this one maps the near plane to a depth of 1 and the far plane to 0.
+                Some(z_far) => rend3::types::CameraProjection::Raw(glam::Mat4::perspective_lh(
+                    lens.vertical_fov.to_radians(),
+                    self.projection_info.aspect_ratio,
+                    z_far,
+                    lens.z_near,
+                )),
             },
+            ProjectionKind::Orthographic => {
+                let height = self.projection_info.orthographic_height();
+                rend3::types::CameraProjection::Orthographic {
+                    size: glam::Vec3A::new(
+                        height * self.projection_info.aspect_ratio,
+                        height,
+                        ORTHOGRAPHIC_DEPTH,
+                    ),
+                }
+            }
+        };
+        rend3::types::Camera {
+            projection,
             view: self.view_info.get_view_matrix().to_glam_mat4(),
         }
     }
@@ -89,12 +161,155 @@ impl Camera {
         self.rebuild_view_projection_matrix();
     }
 
-    pub fn get_z_near(&self) -> f32 {
-        self.projection_info.z_near
+    pub fn get_projection(&self) -> ProjectionKind {
+        self.projection_info.kind
+    }
+
+    pub fn set_projection(&mut self, kind: ProjectionKind) {
+        self.projection_info.kind = kind;
+        self.projection_info.build_projection_matrix();
+        self.rebuild_view_projection_matrix();
+    }
+
+    pub fn lens(&self) -> LensSettings {
+        self.projection_info.lens
+    }
+
+    /// How many units orthographic views show vertically.
+    pub fn orthographic_height(&self) -> f32 {
+        self.projection_info.orthographic_height()
+    }
+
+    pub fn set_lens(&mut self, lens: LensSettings) {
+        self.projection_info.lens = lens;
+        self.projection_info.build_projection_matrix();
+        self.rebuild_view_projection_matrix();
+    }
+
+    /// Captures everything needed to reconstruct the current view. Any rotation in
+    /// progress (the rotation modifier) is folded into the saved rotation.
+    pub fn get_state(&self) -> CameraState {
+        CameraState {
+            look_at: self.view_info.look_at.to_glam_vec3(),
+            z_offset: self.view_info.z_offset.to_glam_vec3(),
+            rotation: (self.view_info.current_rotation * self.view_info.rotation_modifier)
+                .normalize()
+                .to_glam_quat(),
+            lens: self.projection_info.lens,
+            projection: self.projection_info.kind,
+        }
+    }
+
+    /// Restores a view captured with `get_state()`. The aspect ratio is kept, since it
+    /// belongs to the window rather than to the camera.
+    pub fn set_state(&mut self, state: &CameraState) {
+        self.view_info.look_at = Point3::from(state.look_at);
+        self.view_info.z_offset = Point3::from(state.z_offset);
+        self.view_info.current_rotation = Quaternion::from(state.rotation).normalize();
+        self.view_info.rotation_modifier = Quaternion::identity();
+        self.view_info.rebuild_view_matrix();
+
+        self.projection_info.lens = state.lens;
+        self.projection_info.kind = state.projection;
+        self.projection_info.focus_distance = state.z_offset.length();
+        self.projection_info.build_projection_matrix();
+
+        self.rebuild_view_projection_matrix();
+        self.solidify_view_info();
+    }
+}
+
+/// Converts from window pixels, measured from the top left corner, to normalized device
+/// coordinates, which go from -1 at the bottom left to 1 at the top right.
+pub fn window_to_ndc(position: glam::Vec2, window_size: glam::Vec2) -> glam::Vec2 {
+    glam::Vec2::new(
+        2.0 * position.x / window_size.x - 1.0,
+        1.0 - 2.0 * position.y / window_size.y,
+    )
+}
+
+/// The inverse of `window_to_ndc()`.
+pub fn ndc_to_window(ndc: glam::Vec2, window_size: glam::Vec2) -> glam::Vec2 {
+    glam::Vec2::new(
+        (ndc.x + 1.0) / 2.0 * window_size.x,
+        (1.0 - ndc.y) / 2.0 * window_size.y,
+    )
+}
+
+/// Serializable snapshot of the camera, used when saving and loading projects.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CameraState {
+    pub look_at: glam::Vec3,
+    pub z_offset: glam::Vec3,
+    pub rotation: glam::Quat,
+    pub lens: LensSettings,
+    pub projection: ProjectionKind,
+}
+
+/// The optics of the camera. Everything that projects through the camera, from the
+/// viewport's renderer to the grid and picking, gets them from here.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct LensSettings {
+    /// in degrees
+    pub vertical_fov: f32,
+    /// Width and height of the film or sensor the lens projects onto, in millimeters.
+    /// Only used to convert between the field of view and the focal length.
+    pub sensor_size: glam::Vec2,
+    /// distance to near clipping plane
+    pub z_near: f32,
+    /// distance to far clipping plane, or `None` to see infinitely far. Orthographic
+    /// views ignore both clipping planes.
+    pub z_far: Option<f32>,
+}
+
+impl Default for LensSettings {
+    fn default() -> Self {
+        Self {
+            vertical_fov: 60.0,
+            sensor_size: default_sensor_size(),
+            z_near: 0.1,
+            z_far: None,
+        }
+    }
+}
+
+/// Full frame 35mm film.
+fn default_sensor_size() -> glam::Vec2 {
+    glam::Vec2::new(36.0, 24.0)
+}
+
+impl LensSettings {
+    /// in millimeters
+    pub fn focal_length(&self) -> f32 {
+        self.sensor_size.y / (2.0 * (self.vertical_fov.to_radians() / 2.0).tan())
+    }
+
+    /// Changes the field of view to what a lens of `focal_length` millimeters has on
+    /// this sensor.
+    pub fn set_focal_length(&mut self, focal_length: f32) {
+        self.vertical_fov = (2.0 * (self.sensor_size.y / (2.0 * focal_length)).atan()).to_degrees();
     }
+}
+
+/// How dragging the mouse rotates the viewport camera.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RotationStyle {
+    /// Horizontal movement spins the view about the world's up axis and vertical
+    /// movement tilts it, so the horizon stays level.
+    Turntable,
+    /// The cursor drags a ball around the point being looked at, which can roll the
+    /// view in any direction.
+    Trackball,
+}
+
+impl RotationStyle {
+    pub const ALL: [Self; 2] = [Self::Turntable, Self::Trackball];
 
-    pub fn get_z_far(&self) -> Option<f32> {
-        self.projection_info.z_far
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Turntable => "Turntable",
+            Self::Trackball => "Trackball",
+        }
     }
 }
 
@@ -102,6 +317,27 @@ impl Camera {
 // kinds we want to target are "turntable" and "trackball". The Blender
 // implementation is in `source/blender/editors/space_view3d/view3d_navigate_rotate.c`.
 impl Camera {
+    /// Rotates the view as if the cursor had grabbed a ball in the middle of the
+    /// window at `cursor_start` and dragged it to `cursor`, both in window pixels. Like
+    /// `turntable_rotate()`, this only sets the rotation modifier, so
+    /// `solidify_view_info()` still has to be called once the drag is over.
+    pub fn trackball_rotate(
+        &mut self,
+        cursor_start: glam::Vec2,
+        cursor: glam::Vec2,
+        window_size: glam::Vec2,
+    ) {
+        let start = trackball_point(cursor_start, window_size);
+        let end = trackball_point(cursor, window_size);
+        // The rotation of the ball is in camera space, so it is applied after the
+        // current rotation. The modifier is applied before it, so it has to be
+        // conjugated.
+        let view_rotation = glam::Quat::from_rotation_arc(start, end);
+        let current_rotation = self.view_info.current_rotation.to_glam_quat();
+        let rotation_modifier = current_rotation.inverse() * view_rotation * current_rotation;
+        self.set_rotation_modifier(Quaternion::from(rotation_modifier).normalize());
+    }
+
     pub fn turntable_rotate(&mut self, delta_mouse: Vector2, window_size: (f32, f32)) {
         let x_angle_scale_factor = 2.0 * (consts::PI as f32) / window_size.0;
         let y_angle_scale_factor = consts::PI as f32 / window_size.1;
@@ -143,6 +379,424 @@ impl Camera {
     }
 }
 
+/// Views straight along one of the world axes, as on Blender's numpad.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ViewPreset {
+    /// Looking along +z.
+    Front,
+    Back,
+    /// Looking along +x.
+    Left,
+    Right,
+    /// Looking down.
+    Top,
+    Bottom,
+}
+
+impl ViewPreset {
+    pub const ALL: [Self; 6] = [
+        Self::Front,
+        Self::Back,
+        Self::Left,
+        Self::Right,
+        Self::Top,
+        Self::Bottom,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Front => "Front",
+            Self::Back => "Back",
+            Self::Left => "Left",
+            Self::Right => "Right",
+            Self::Top => "Top",
+            Self::Bottom => "Bottom",
+        }
+    }
+
+    /// The world to camera rotation, as in `CameraState::rotation`. The camera looks
+    /// down its +z axis.
+    fn rotation(self) -> glam::Quat {
+        use std::f32::consts::{FRAC_PI_2, PI};
+        match self {
+            Self::Front => glam::Quat::IDENTITY,
+            Self::Back => glam::Quat::from_rotation_y(PI),
+            Self::Left => glam::Quat::from_rotation_y(-FRAC_PI_2),
+            Self::Right => glam::Quat::from_rotation_y(FRAC_PI_2),
+            Self::Top => glam::Quat::from_rotation_x(-FRAC_PI_2),
+            Self::Bottom => glam::Quat::from_rotation_x(FRAC_PI_2),
+        }
+    }
+}
+
+impl Camera {
+    /// The camera looking at the same point from the same distance, from the side
+    /// given by `preset`.
+    pub fn preset_state(&self, preset: ViewPreset) -> CameraState {
+        CameraState {
+            rotation: preset.rotation(),
+            ..self.get_state()
+        }
+    }
+
+    /// The camera looking the same way at the middle of the box from `min` to `max`,
+    /// from just far enough away that all of the box fits in the view.
+    pub fn framing_state(&self, min: glam::Vec3, max: glam::Vec3) -> CameraState {
+        // Fitting the sphere around the box rather than the box itself makes the
+        // distance the same from every side.
+        let radius = (max - min).length() / 2.0;
+        let info = &self.projection_info;
+        let half_fov_tan = (info.lens.vertical_fov.to_radians() / 2.0).tan();
+        // The sphere has to fit along the narrower side of the view.
+        let narrow_half_fov_tan = half_fov_tan * info.aspect_ratio.min(1.0);
+        let distance = match info.kind {
+            ProjectionKind::Perspective => radius / narrow_half_fov_tan.atan().sin(),
+            // Orthographic views show as much as perspective ones do at the focus
+            // distance, see `ProjectionInfo::orthographic_height()`.
+            ProjectionKind::Orthographic => radius / narrow_half_fov_tan,
+        };
+
+        CameraState {
+            look_at: (min + max) / 2.0,
+            z_offset: glam::Vec3::new(
+                0.0,
+                0.0,
+                -distance.clamp(MIN_DOLLY_DISTANCE, MAX_DOLLY_DISTANCE),
+            ),
+            ..self.get_state()
+        }
+    }
+}
+
+/// A smooth transition of the camera from one state to another, which starts slowly
+/// and ends slowly.
+pub struct CameraAnimation {
+    from: CameraState,
+    to: CameraState,
+    start: std::time::Instant,
+    duration: std::time::Duration,
+}
+
+impl CameraAnimation {
+    pub fn new(from: CameraState, to: CameraState, duration: std::time::Duration) -> Self {
+        Self {
+            from,
+            to,
+            start: std::time::Instant::now(),
+            duration,
+        }
+    }
+
+    /// Where the animation ends up.
+    pub fn target(&self) -> &CameraState {
+        &self.to
+    }
+
+    /// Puts `camera` where it should be by now. Returns false once the animation is
+    /// over, with the camera at the target.
+    pub fn update(&self, camera: &mut Camera) -> bool {
+        let progress = if self.duration.is_zero() {
+            1.0
+        } else {
+            (self.start.elapsed().as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+        };
+        let t = progress * progress * (3.0 - 2.0 * progress);
+
+        let (from, to) = (&self.from, &self.to);
+        camera.set_state(&CameraState {
+            look_at: from.look_at.lerp(to.look_at, t),
+            z_offset: from.z_offset.lerp(to.z_offset, t),
+            rotation: from.rotation.slerp(to.rotation, t),
+            lens: LensSettings {
+                vertical_fov: from.lens.vertical_fov
+                    + (to.lens.vertical_fov - from.lens.vertical_fov) * t,
+                ..to.lens
+            },
+            ..to.clone()
+        });
+        progress < 1.0
+    }
+}
+
+/// Where the cursor, given in window pixels, touches the virtual trackball, in camera
+/// space. The ball fills the shorter side of the window. Outside of it, the cursor
+/// touches a hyperbolic sheet instead, which meets the ball smoothly so that the
+/// rotation doesn't jump as the cursor leaves the ball (Bell's trackball).
+fn trackball_point(cursor: glam::Vec2, window_size: glam::Vec2) -> glam::Vec3 {
+    let radius = window_size.min_element() / 2.0;
+    let x = (cursor.x - window_size.x / 2.0) / radius;
+    let y = (window_size.y / 2.0 - cursor.y) / radius;
+    let distance_squared = x * x + y * y;
+    let depth = if distance_squared <= 0.5 {
+        (1.0 - distance_squared).sqrt()
+    } else {
+        0.5 / distance_squared.sqrt()
+    };
+    // The camera looks down +z, so the side of the ball facing it is at negative z.
+    glam::Vec3::new(x, y, -depth).normalize()
+}
+
+/// Each scroll step brings the camera this many times closer to what it is looking at,
+/// so that zooming feels the same at any distance.
+const DOLLY_FACTOR: f32 = 1.2;
+const MIN_DOLLY_DISTANCE: f32 = 0.01;
+const MAX_DOLLY_DISTANCE: f32 = 10_000.0;
+
+// Moving the camera without rotating it: panning moves the point it orbits around
+// sideways, and dollying moves the camera towards or away from that point.
+impl Camera {
+    /// Where the camera is, in world space.
+    pub fn get_position(&self) -> glam::Vec3 {
+        let rotation = (self.view_info.current_rotation * self.view_info.rotation_modifier)
+            .normalize()
+            .to_glam_quat();
+        self.view_info.look_at.to_glam_vec3()
+            + rotation.inverse() * self.view_info.z_offset.to_glam_vec3()
+    }
+
+    /// Moves the view so that what is under the cursor follows it, for a cursor that
+    /// moved `delta_pixels`, with y going down.
+    pub fn pan(&mut self, delta_pixels: glam::Vec2, window_size: glam::Vec2) {
+        let rotation = (self.view_info.current_rotation * self.view_info.rotation_modifier)
+            .normalize()
+            .to_glam_quat()
+            .inverse();
+        let look_at = self.view_info.look_at.to_glam_vec3();
+        // How far a pixel is in the plane of the point being orbited.
+        let scale = self.world_size_of_pixels(look_at, 1.0, window_size);
+        let right = rotation * glam::Vec3::X;
+        let up = rotation * glam::Vec3::Y;
+
+        self.view_info.look_at =
+            Point3::from(look_at + (up * delta_pixels.y - right * delta_pixels.x) * scale);
+        self.rebuild_view();
+    }
+
+    /// Moves the camera `steps` scroll steps towards what it is looking at, or away
+    /// from it for negative `steps`. With `toward`, a point on the screen in normalized
+    /// device coordinates, the view also shifts so that what is under that point stays
+    /// there.
+    pub fn dolly(&mut self, steps: f32, toward: Option<glam::Vec2>) {
+        let distance = self.view_info.z_offset.to_glam_vec3().length();
+        let new_distance =
+            (distance / DOLLY_FACTOR.powf(steps)).clamp(MIN_DOLLY_DISTANCE, MAX_DOLLY_DISTANCE);
+        let mut look_at = self.view_info.look_at.to_glam_vec3();
+
+        if let Some(ndc) = toward {
+            // Find what is under the cursor at the depth of `look_at`. Scaling the view
+            // about that point keeps it under the cursor.
+            let forward = (look_at - self.get_position()).normalize_or_zero();
+            let (origin, direction) = self.get_ray_through(ndc);
+            let along_forward = direction.dot(forward);
+            if along_forward > f32::EPSILON {
+                let target = origin + direction * ((look_at - origin).dot(forward) / along_forward);
+                look_at = target + (look_at - target) * (new_distance / distance);
+            }
+        }
+
+        self.view_info.z_offset = Point3::new(0.0, 0.0, -new_distance);
+        self.view_info.look_at = Point3::from(look_at);
+        // Orthographic views get closer by showing less.
+        self.projection_info.focus_distance = new_distance;
+        self.projection_info.build_projection_matrix();
+        self.rebuild_view();
+    }
+
+    fn rebuild_view(&mut self) {
+        self.view_info.rebuild_view_matrix();
+        self.rebuild_view_projection_matrix();
+    }
+}
+
+/// How far the camera turns, in radians, per unit of mouse movement while flying.
+const FLY_LOOK_SPEED: f32 = 0.003;
+/// How close to straight up or down the camera can look while flying, in radians, so
+/// that it doesn't flip over.
+const MAX_FLY_PITCH: f32 = 1.55;
+/// Each scroll step while flying makes the camera this many times faster.
+const FLY_SPEED_FACTOR: f32 = 1.2;
+const MIN_FLY_SPEED: f32 = 0.01;
+const MAX_FLY_SPEED: f32 = 1000.0;
+/// How long the camera takes to get most of the way to full speed, or to a stop, in
+/// seconds.
+const FLY_ACCELERATION_TIME: f32 = 0.15;
+
+// Moving the camera like in a first person game, where it turns about itself rather
+// than about the point it looks at. That point is carried along, so the camera can go
+// back to orbiting at any time without the view changing.
+impl Camera {
+    /// Turns the camera in place for a mouse that moved `delta`, with y going down.
+    /// Horizontal movement turns about the world's up axis, so the horizon stays level.
+    pub fn fly_look(&mut self, delta: glam::Vec2) {
+        let position = self.get_position();
+        let rotation = (self.view_info.current_rotation * self.view_info.rotation_modifier)
+            .normalize()
+            .to_glam_quat();
+
+        let forward = rotation.inverse() * glam::Vec3::Z;
+        let pitch = forward.y.clamp(-1.0, 1.0).asin();
+        // Positive angles about the camera's x axis tilt it down.
+        let tilt = (delta.y * FLY_LOOK_SPEED).clamp(pitch - MAX_FLY_PITCH, pitch + MAX_FLY_PITCH);
+        let turn = delta.x * FLY_LOOK_SPEED;
+        // The rotation goes from world to camera space, so turning the camera by some
+        // rotation means applying its inverse.
+        let rotation =
+            (glam::Quat::from_rotation_x(-tilt) * rotation * glam::Quat::from_rotation_y(-turn))
+                .normalize();
+
+        self.view_info.current_rotation = Quaternion::from(rotation);
+        self.view_info.rotation_modifier = Quaternion::identity();
+        self.view_info.look_at =
+            Point3::from(position - rotation.inverse() * self.view_info.z_offset.to_glam_vec3());
+        self.rebuild_view();
+        self.solidify_view_info();
+    }
+
+    /// Moves the camera by `offset`, where x is to the camera's right, z is forward
+    /// and y is up in the world.
+    pub fn fly_move(&mut self, offset: glam::Vec3) {
+        let rotation = (self.view_info.current_rotation * self.view_info.rotation_modifier)
+            .normalize()
+            .to_glam_quat()
+            .inverse();
+        let offset = rotation * glam::Vec3::new(offset.x, 0.0, offset.z) + glam::Vec3::Y * offset.y;
+        self.view_info.look_at = Point3::from(self.view_info.look_at.to_glam_vec3() + offset);
+        self.rebuild_view();
+    }
+}
+
+/// Flies the camera around, speeding up and slowing down smoothly.
+pub struct FlyControl {
+    /// Top speed, in units per second.
+    pub speed: f32,
+    /// In the same coordinates as the offset given to `Camera::fly_move()`.
+    velocity: glam::Vec3,
+}
+
+impl FlyControl {
+    pub fn new(speed: f32) -> Self {
+        Self {
+            speed,
+            velocity: glam::Vec3::ZERO,
+        }
+    }
+
+    /// Makes flying faster by `steps` scroll steps, or slower for negative `steps`.
+    pub fn change_speed(&mut self, steps: f32) {
+        self.speed =
+            (self.speed * FLY_SPEED_FACTOR.powf(steps)).clamp(MIN_FLY_SPEED, MAX_FLY_SPEED);
+    }
+
+    pub fn stop(&mut self) {
+        self.velocity = glam::Vec3::ZERO;
+    }
+
+    /// Moves `camera` for `elapsed` time, accelerating towards top speed in
+    /// `direction`, given like the offset to `Camera::fly_move()`. With no direction,
+    /// the camera slows down to a stop.
+    pub fn update(
+        &mut self,
+        camera: &mut Camera,
+        direction: glam::Vec3,
+        elapsed: std::time::Duration,
+    ) {
+        let elapsed = elapsed.as_secs_f32();
+        let target_velocity = direction.normalize_or_zero() * self.speed;
+        // The velocity closes the same fraction of the gap to the target velocity every
+        // so often. The distance covered is integrated exactly, rather than taken as
+        // velocity times time, so that it doesn't depend on the frame rate either.
+        let gap = self.velocity - target_velocity;
+        let remaining = (-elapsed / FLY_ACCELERATION_TIME).exp();
+        let offset = target_velocity * elapsed + gap * FLY_ACCELERATION_TIME * (1.0 - remaining);
+        self.velocity = target_velocity + gap * remaining;
+        if target_velocity == glam::Vec3::ZERO && self.velocity.length() < self.speed * 1e-3 {
+            self.velocity = glam::Vec3::ZERO;
+        }
+
+        if offset != glam::Vec3::ZERO {
+            camera.fly_move(offset);
+        }
+    }
+}
+
+/// Moves a camera the way dragging and scrolling in a 3D viewer do. Cursor positions
+/// are in window pixels. Kept apart from the viewer so that recorded input can be
+/// replayed onto a camera without a window.
+pub struct CameraNavigation {
+    pub rotation_style: RotationStyle,
+    /// Whether scrolling zooms towards the cursor rather than the center of the view.
+    pub zoom_to_cursor: bool,
+    /// Where the cursor was when the orbit in progress started.
+    orbit_cursor_start: Option<glam::Vec2>,
+    /// Where the cursor was the last time the view was panned during the pan in
+    /// progress.
+    pan_cursor: Option<glam::Vec2>,
+}
+
+impl CameraNavigation {
+    pub fn new(rotation_style: RotationStyle, zoom_to_cursor: bool) -> Self {
+        Self {
+            rotation_style,
+            zoom_to_cursor,
+            orbit_cursor_start: None,
+            pan_cursor: None,
+        }
+    }
+
+    /// Rotates the view for the cursor having moved to `cursor` since the orbit
+    /// started. The first call starts the orbit.
+    pub fn orbit(&mut self, camera: &mut Camera, cursor: glam::Vec2, window_size: glam::Vec2) {
+        let start = *self.orbit_cursor_start.get_or_insert(cursor);
+        match self.rotation_style {
+            RotationStyle::Turntable => camera.turntable_rotate(
+                Vector2::new(start.x - cursor.x, start.y - cursor.y),
+                (window_size.x, window_size.y),
+            ),
+            RotationStyle::Trackball => camera.trackball_rotate(start, cursor, window_size),
+        }
+    }
+
+    pub fn finish_orbit(&mut self, camera: &mut Camera) {
+        self.orbit_cursor_start = None;
+        camera.solidify_view_info();
+    }
+
+    /// Moves the view sideways, following the cursor. The first call starts the pan.
+    /// Returns whether the camera moved.
+    pub fn pan(
+        &mut self,
+        camera: &mut Camera,
+        cursor: glam::Vec2,
+        window_size: glam::Vec2,
+    ) -> bool {
+        match self.pan_cursor.replace(cursor) {
+            Some(last_cursor) => {
+                camera.pan(cursor - last_cursor, window_size);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn finish_pan(&mut self) {
+        self.pan_cursor = None;
+    }
+
+    /// Zooms in for `steps` scroll steps up, or out for negative `steps`.
+    pub fn zoom(
+        &self,
+        camera: &mut Camera,
+        steps: f32,
+        cursor: glam::Vec2,
+        window_size: glam::Vec2,
+    ) {
+        let toward = self
+            .zoom_to_cursor
+            .then(|| window_to_ndc(cursor, window_size));
+        camera.dolly(steps, toward);
+    }
+}
+
 /// The motivation for this abstraction was to couple the view
 /// matrix to the data that it is defined by. Ideally, we would
 /// like to avoid recomputing the matrix every frame (given that
@@ -154,7 +808,8 @@ struct ViewInfo {
     /// How far the camera is from the look_at point, without accounting for
     /// rotation.
     z_offset: Point3,
-    /// The point the camera is pointing at / the center point.
+    /// The point the camera is pointing at / the center point, in world space. The
+    /// camera orbits around it.
     look_at: Point3,
     /// Represents the rotation needed to get to the last set camera rotation.
     /// A camera rotation is set, for example, after releasing the keybind that
@@ -178,10 +833,7 @@ impl ViewInfo {
         let current_rotation =
             Quaternion::rotation_from_axis_angle(Vector3::unit_x(), Radians(-0.5));
         let rotation_modifier = Quaternion::identity();
-        let view_matrix = Self::build_view_matrix_from_rotation_and_offset(
-            current_rotation,
-            Vector3::from(z_offset) + Vector3::from(look_at),
-        );
+        let view_matrix = Self::build_view_matrix(current_rotation, z_offset, look_at);
 
         Self {
             z_offset,
@@ -193,20 +845,21 @@ impl ViewInfo {
         }
     }
 
-    fn build_view_matrix_from_rotation_and_offset(
-        rotation: Quaternion,
-        offset: Vector3,
-    ) -> Matrix4 {
+    /// The camera orbits `look_at`, so the world is moved to put `look_at` at the
+    /// origin before it is rotated, and then pushed away from the camera by `z_offset`.
+    fn build_view_matrix(rotation: Quaternion, z_offset: Point3, look_at: Point3) -> Matrix4 {
+        let negative_look_at_matrix = Matrix4::from_translation(-Vector3::from(look_at));
         let rotation_matrix = Matrix4::from(rotation);
-        let negative_offset_matrix = Matrix4::from_translation(-offset);
+        let negative_offset_matrix = Matrix4::from_translation(-Vector3::from(z_offset));
 
-        negative_offset_matrix * rotation_matrix
+        negative_offset_matrix * rotation_matrix * negative_look_at_matrix
     }
 
     fn rebuild_view_matrix(&mut self) {
-        self.view_matrix = Self::build_view_matrix_from_rotation_and_offset(
+        self.view_matrix = Self::build_view_matrix(
             self.current_rotation * self.rotation_modifier,
-            Vector3::from(self.z_offset) + Vector3::from(self.look_at),
+            self.z_offset,
+            self.look_at,
         );
     }
 
@@ -220,44 +873,80 @@ impl ViewInfo {
     }
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ProjectionKind {
+    #[default]
+    Perspective,
+    /// Parallel lines stay parallel, and things don't get smaller with distance.
+    Orthographic,
+}
+
+impl ProjectionKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Perspective => "Perspective",
+            Self::Orthographic => "Orthographic",
+        }
+    }
+}
+
+/// How much orthographic views show along the view direction, centered on the camera.
+/// They have no vanishing point, so unlike in perspective views, what is behind the
+/// camera can be seen as well.
+const ORTHOGRAPHIC_DEPTH: f32 = 10_000.0;
+
 /// Encapsulates information needed to build the projection
 /// matrix. Justification is described in `ViewInfo`.
 struct ProjectionInfo {
-    /// in degrees
-    vertical_fov: f32,
+    kind: ProjectionKind,
+    lens: LensSettings,
     aspect_ratio: f32,
-    /// distance to near clipping plane
-    z_near: f32,
-    /// distance to far clipping plane    
-    z_far: Option<f32>,
+    /// How far the point the camera orbits around is. Orthographic views show as much
+    /// as perspective views do at this distance, so switching between them keeps
+    /// that point's surroundings the same size.
+    focus_distance: f32,
     projection_matrix: Matrix4,
 }
 
 impl ProjectionInfo {
-    fn initialize(screen_width: f32, screen_height: f32) -> Self {
-        let vertical_fov = 60.0;
+    fn initialize(screen_width: f32, screen_height: f32, focus_distance: f32) -> Self {
+        let lens = LensSettings::default();
         let aspect_ratio = screen_width / screen_height;
-        let z_near = 0.1;
-        let z_far = None;
-        let projection_matrix =
-            Matrix4::create_perspective(Degrees(vertical_fov), aspect_ratio, z_near, z_far);
+        let projection_matrix = Matrix4::create_perspective(
+            Degrees(lens.vertical_fov),
+            aspect_ratio,
+            lens.z_near,
+            lens.z_far,
+        );
 
         Self {
-            vertical_fov,
+            kind: ProjectionKind::Perspective,
+            lens,
             aspect_ratio,
-            z_near,
-            z_far,
+            focus_distance,
             projection_matrix,
         }
     }
 
     fn build_projection_matrix(&mut self) {
-        self.projection_matrix = Matrix4::create_perspective(
-            Degrees(self.vertical_fov),
-            self.aspect_ratio,
-            self.z_near,
-            self.z_far,
-        );
+        self.projection_matrix = match self.kind {
+            ProjectionKind::Perspective => Matrix4::create_perspective(
+                Degrees(self.lens.vertical_fov),
+                self.aspect_ratio,
+                self.lens.z_near,
+                self.lens.z_far,
+            ),
+            ProjectionKind::Orthographic => Matrix4::create_orthographic(
+                self.orthographic_height(),
+                self.aspect_ratio,
+                ORTHOGRAPHIC_DEPTH,
+            ),
+        };
+    }
+
+    /// How many units orthographic views show vertically.
+    fn orthographic_height(&self) -> f32 {
+        2.0 * self.focus_distance * (self.lens.vertical_fov.to_radians() / 2.0).tan()
     }
 
     fn set_aspect_ratio(&mut self, arg: f32) {
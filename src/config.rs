@@ -1,10 +1,21 @@
+use std::collections::HashMap;
+
 use serde::Deserialize;
 
+use crate::camera::RotationStyle;
+
+/// Where the user config is read from, relative to the working directory.
+pub const USER_CONFIG_PATH: &str = "ekki_config.toml";
+
 #[derive(Deserialize, Clone)]
 pub struct UserConfig {
     pub log_level: Option<String>,
     pub render: Option<RenderUserConfig>,
     pub startup: Option<UserStartupConfig>,
+    pub viewport: Option<ViewportUserConfig>,
+    /// Chords bound to actions, by the actions' config names. Actions which aren't
+    /// listed keep their default bindings.
+    pub bindings: Option<HashMap<String, BindingConfig>>,
 }
 
 impl UserConfig {
@@ -33,6 +44,8 @@ impl Default for UserConfig {
             log_level: Some("warn".to_string()),
             render: None,
             startup: None,
+            viewport: None,
+            bindings: None,
         }
     }
 }
@@ -43,6 +56,49 @@ pub struct RenderUserConfig {
     pub update_frequency: Option<u32>,
 }
 
+/// Settings for the 3D viewers. Each viewer starts out with these, and can change them
+/// for itself from its "View" menu.
+#[derive(Deserialize, Clone)]
+pub struct ViewportUserConfig {
+    /// Whether scrolling zooms towards the cursor rather than the center of the view.
+    pub zoom_to_cursor: Option<bool>,
+    /// "turntable" or "trackball".
+    pub rotation_style: Option<String>,
+}
+
+impl ViewportUserConfig {
+    pub fn get_rotation_style(&self) -> RotationStyle {
+        if let Some(s) = &self.rotation_style {
+            match s.to_lowercase().as_str() {
+                "turntable" => return RotationStyle::Turntable,
+                "trackball" => return RotationStyle::Trackball,
+                _ => {
+                    log::warn!("Invalid rotation style '{}' specified in user config.", s);
+                }
+            }
+        }
+
+        RotationStyle::Turntable
+    }
+}
+
+/// Either a single chord, like `"Ctrl+Z"`, or a list of them.
+#[derive(Deserialize, Clone)]
+#[serde(untagged)]
+pub enum BindingConfig {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl BindingConfig {
+    pub fn chords(&self) -> &[String] {
+        match self {
+            Self::One(chord) => std::slice::from_ref(chord),
+            Self::Many(chords) => chords,
+        }
+    }
+}
+
 #[derive(Deserialize, Clone)]
 pub struct UserStartupConfig {
     pub startup_window: Option<String>,
@@ -0,0 +1,984 @@
+//! Handles for moving, rotating and scaling the selected nodes in the 3D viewer.
+//!
+//! `Gizmo` works out where the handles are, which one is under the cursor and what
+//! dragging it does to the scene graph. `GizmoRenderRoutine` draws the handles, along
+//! with the other overlays in `GizmoGeometry`, on top of everything else in the
+//! viewport.
+
+use std::sync::Arc;
+
+use encase::private::AsRefMatrixParts;
+use wgpu::util::DeviceExt;
+
+use crate::{
+    camera::{ndc_to_window, window_to_ndc, Camera},
+    scene::{
+        graph::{NodeId, Transform},
+        SceneData,
+    },
+};
+
+/// Length of the axis handles on screen, in pixels.
+const GIZMO_SIZE: f32 = 96.0;
+/// How close the cursor has to be to a handle, in pixels, to grab it.
+const GRAB_DISTANCE: f32 = 8.0;
+/// Axes which look shorter than this fraction of their length point almost straight at
+/// the camera. Dragging along them isn't well defined, so they are hidden.
+const MIN_VISIBLE_AXIS_LENGTH: f32 = 0.2;
+/// Scaling something down to nothing can't be undone by scaling it back up.
+const MIN_SCALE_FACTOR: f32 = 0.001;
+
+const RING_SEGMENTS: usize = 64;
+const CONE_SEGMENTS: usize = 12;
+
+const AXIS_COLORS: [glam::Vec4; 3] = [
+    glam::Vec4::new(0.9, 0.2, 0.2, 1.0),
+    glam::Vec4::new(0.3, 0.8, 0.2, 1.0),
+    glam::Vec4::new(0.2, 0.4, 0.9, 1.0),
+];
+const CENTER_COLOR: glam::Vec4 = glam::Vec4::new(0.9, 0.9, 0.9, 1.0);
+/// Color of the handle under the cursor or being dragged.
+const ACTIVE_COLOR: glam::Vec4 = glam::Vec4::new(1.0, 0.85, 0.2, 1.0);
+/// Opacity of the plane handles, which would otherwise hide what they're moving.
+const PLANE_ALPHA: f32 = 0.4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GizmoMode {
+    Translate,
+    Rotate,
+    Scale,
+}
+
+impl GizmoMode {
+    pub const ALL: [Self; 3] = [Self::Translate, Self::Rotate, Self::Scale];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Translate => "Move",
+            Self::Rotate => "Rotate",
+            Self::Scale => "Scale",
+        }
+    }
+}
+
+/// Which axes the handles follow.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GizmoSpace {
+    World,
+    /// The axes of the active node.
+    Local,
+}
+
+impl GizmoSpace {
+    pub const ALL: [Self; 2] = [Self::World, Self::Local];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::World => "World",
+            Self::Local => "Local",
+        }
+    }
+}
+
+/// Increments that drags are rounded to while snapping.
+#[derive(Clone, Copy, Debug)]
+pub struct SnapSettings {
+    pub enabled: bool,
+    /// In world units, along the gizmo axes.
+    pub translation: f32,
+    pub rotation_degrees: f32,
+    /// Of the scale factor.
+    pub scale: f32,
+}
+
+impl Default for SnapSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            translation: 0.5,
+            rotation_degrees: 15.0,
+            scale: 0.1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Handle {
+    /// Moves or scales along, or rotates about, one of the gizmo axes.
+    Axis(usize),
+    /// Moves in the plane perpendicular to one of the gizmo axes.
+    Plane(usize),
+    /// Scales uniformly.
+    Center,
+}
+
+/// Where the gizmo is drawn.
+struct GizmoFrame {
+    /// Average position of the nodes being transformed.
+    pivot: glam::Vec3,
+    /// Unit axes, as columns.
+    axes: glam::Mat3,
+    /// World space length of the axis handles.
+    size: f32,
+}
+
+struct DraggedNode {
+    id: NodeId,
+    start_transform: Transform,
+    parent_world_transform: glam::Mat4,
+}
+
+struct Drag {
+    handle: Handle,
+    pivot: glam::Vec3,
+    axes: glam::Mat3,
+    /// Where the cursor ray met the line or plane the handle moves along when the drag
+    /// started.
+    start_point: glam::Vec3,
+    start_cursor: glam::Vec2,
+    /// For rotations: the point on the plane of rotation under the cursor the last time
+    /// it moved, and the angle turned since the drag started. The angle is accumulated
+    /// so that turning past half a revolution works.
+    last_point: glam::Vec3,
+    angle: f32,
+    nodes: Vec<DraggedNode>,
+}
+
+pub struct Gizmo {
+    pub mode: GizmoMode,
+    pub space: GizmoSpace,
+    pub snap: SnapSettings,
+    hovered: Option<Handle>,
+    drag: Option<Drag>,
+}
+
+impl Default for Gizmo {
+    fn default() -> Self {
+        Self {
+            mode: GizmoMode::Translate,
+            space: GizmoSpace::World,
+            snap: SnapSettings::default(),
+            hovered: None,
+            drag: None,
+        }
+    }
+}
+
+impl Gizmo {
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// Highlights the handle under `cursor`, given in window pixels. `None` means the
+    /// cursor isn't over the viewport.
+    pub fn update_hover(
+        &mut self,
+        scene: &SceneData,
+        cursor: Option<glam::Vec2>,
+        window_size: glam::Vec2,
+    ) {
+        self.hovered = cursor.and_then(|cursor| self.handle_at(scene, cursor, window_size));
+    }
+
+    /// Starts dragging the handle under `cursor`. Returns false if there is no handle
+    /// there, in which case the click is left for selecting.
+    pub fn begin_drag(
+        &mut self,
+        scene: &SceneData,
+        cursor: glam::Vec2,
+        window_size: glam::Vec2,
+    ) -> bool {
+        let Some(handle) = self.handle_at(scene, cursor, window_size) else {
+            return false;
+        };
+        let Some(frame) = self.frame(scene, window_size) else {
+            return false;
+        };
+        let ray = scene
+            .camera
+            .get_ray_through(window_to_ndc(cursor, window_size));
+        let Some(start_point) = constrained_point(self.mode, handle, frame.pivot, frame.axes, ray)
+        else {
+            return false;
+        };
+
+        let graph = scene.graph();
+        let nodes = outermost_selected(scene)
+            .into_iter()
+            .filter_map(|id| {
+                let node = graph.get(id)?;
+                let parent_world_transform = node
+                    .parent()
+                    .and_then(|parent| graph.get(parent))
+                    .map_or(glam::Mat4::IDENTITY, |parent| parent.world_transform());
+                Some(DraggedNode {
+                    id,
+                    start_transform: *node.transform(),
+                    parent_world_transform,
+                })
+            })
+            .collect();
+
+        self.drag = Some(Drag {
+            handle,
+            pivot: frame.pivot,
+            axes: frame.axes,
+            start_point,
+            start_cursor: cursor,
+            last_point: start_point,
+            angle: 0.0,
+            nodes,
+        });
+        true
+    }
+
+    /// Transforms the dragged nodes to follow `cursor`. Snapping is turned on or off
+    /// for this movement, as opposed to what the settings say, with `invert_snapping`.
+    pub fn drag(
+        &mut self,
+        scene: &mut SceneData,
+        cursor: glam::Vec2,
+        window_size: glam::Vec2,
+        invert_snapping: bool,
+        rend3_renderer: &Arc<rend3::Renderer>,
+    ) {
+        let mode = self.mode;
+        let snap = self.snap;
+        let snapping = snap.enabled != invert_snapping;
+        let Some(drag) = &mut self.drag else {
+            return;
+        };
+
+        let ray = scene
+            .camera
+            .get_ray_through(window_to_ndc(cursor, window_size));
+        let Some(point) = constrained_point(mode, drag.handle, drag.pivot, drag.axes, ray) else {
+            return;
+        };
+        let to_pivot = glam::Mat4::from_translation(drag.pivot);
+        let from_pivot = glam::Mat4::from_translation(-drag.pivot);
+
+        let world_change = match (mode, drag.handle) {
+            (GizmoMode::Translate, _) => {
+                let mut offset = drag.axes.transpose() * (point - drag.start_point);
+                if snapping {
+                    offset = glam::Vec3::from_array(
+                        offset.to_array().map(|x| round_to(x, snap.translation)),
+                    );
+                }
+                glam::Mat4::from_translation(drag.axes * offset)
+            }
+
+            (GizmoMode::Rotate, Handle::Axis(axis)) => {
+                let axis = drag.axes.col(axis);
+                let from = drag.last_point - drag.pivot;
+                let to = point - drag.pivot;
+                drag.angle += axis.dot(from.cross(to)).atan2(from.dot(to));
+                drag.last_point = point;
+
+                let mut angle = drag.angle;
+                if snapping {
+                    angle = round_to(angle, snap.rotation_degrees.to_radians());
+                }
+                to_pivot * glam::Mat4::from_axis_angle(axis, angle) * from_pivot
+            }
+            (GizmoMode::Rotate, _) => return,
+
+            (GizmoMode::Scale, handle) => {
+                let mut factor = match handle {
+                    Handle::Axis(axis) => {
+                        let axis = drag.axes.col(axis);
+                        let start = (drag.start_point - drag.pivot).dot(axis);
+                        if start.abs() < f32::EPSILON {
+                            return;
+                        }
+                        (point - drag.pivot).dot(axis) / start
+                    }
+                    _ => {
+                        let Some(center) = scene.camera.project_point(drag.pivot) else {
+                            return;
+                        };
+                        let center = ndc_to_window(center, window_size);
+                        let start = drag.start_cursor.distance(center);
+                        if start < f32::EPSILON {
+                            return;
+                        }
+                        cursor.distance(center) / start
+                    }
+                };
+                if snapping {
+                    factor = round_to(factor, snap.scale);
+                }
+                if factor.abs() < MIN_SCALE_FACTOR {
+                    factor = MIN_SCALE_FACTOR.copysign(factor);
+                }
+
+                let scale = match handle {
+                    Handle::Axis(axis) => {
+                        let mut scale = glam::Vec3::ONE;
+                        scale[axis] = factor;
+                        scale
+                    }
+                    _ => glam::Vec3::splat(factor),
+                };
+                to_pivot
+                    * glam::Mat4::from_mat3(drag.axes)
+                    * glam::Mat4::from_scale(scale)
+                    * glam::Mat4::from_mat3(drag.axes.transpose())
+                    * from_pivot
+            }
+        };
+
+        for node in &drag.nodes {
+            let parent_inverse = node.parent_world_transform.inverse();
+            let transform = if mode == GizmoMode::Translate {
+                // Moving only changes the translation, so keep the rest exactly as it
+                // was rather than going through a decomposition.
+                Transform {
+                    translation: node.start_transform.translation
+                        + parent_inverse.transform_vector3(world_change.w_axis.truncate()),
+                    ..node.start_transform
+                }
+            } else {
+                Transform::from_matrix(
+                    parent_inverse
+                        * world_change
+                        * node.parent_world_transform
+                        * node.start_transform.to_matrix(),
+                )
+            };
+            scene.set_transform(node.id, transform, rend3_renderer);
+        }
+    }
+
+    /// Leaves the dragged nodes where they are. Returns the nodes that were dragged,
+    /// along with their transforms from before the drag.
+    pub fn end_drag(&mut self) -> Vec<(NodeId, Transform)> {
+        self.drag
+            .take()
+            .map(|drag| {
+                drag.nodes
+                    .into_iter()
+                    .map(|node| (node.id, node.start_transform))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The handles to draw this frame, if anything is selected.
+    pub fn geometry(&self, scene: &SceneData, window_size: glam::Vec2) -> GizmoGeometry {
+        let mut geometry = GizmoGeometry::default();
+        let Some(frame) = self.frame(scene, window_size) else {
+            return geometry;
+        };
+        let active = self.drag.as_ref().map(|drag| drag.handle).or(self.hovered);
+        let color = |handle: Handle, color: glam::Vec4| {
+            if active == Some(handle) {
+                ACTIVE_COLOR
+            } else {
+                color
+            }
+        };
+
+        for axis in 0..3 {
+            let direction = frame.axes.col(axis) * frame.size;
+            let (u, v) = other_axes(frame.axes, axis);
+            let (u, v) = (u * frame.size, v * frame.size);
+            let axis_color = color(Handle::Axis(axis), AXIS_COLORS[axis]);
+            let visible = axis_is_visible(&scene.camera, &frame, axis, window_size);
+
+            match self.mode {
+                GizmoMode::Translate => {
+                    if visible {
+                        let tip = frame.pivot + direction * 0.8;
+                        geometry.line(frame.pivot, tip, axis_color);
+                        geometry.cone(tip, direction * 0.2, u * 0.06, v * 0.06, axis_color);
+                    }
+
+                    let plane_color = color(Handle::Plane(axis), AXIS_COLORS[axis]);
+                    geometry.quad(
+                        plane_corners(&frame, axis),
+                        plane_color.truncate().extend(PLANE_ALPHA),
+                    );
+                }
+                GizmoMode::Rotate => {
+                    let points = ring_points(&frame, axis);
+                    for (a, b) in points.iter().zip(points.iter().cycle().skip(1)) {
+                        geometry.line(*a, *b, axis_color);
+                    }
+                }
+                GizmoMode::Scale => {
+                    if visible {
+                        geometry.line(frame.pivot, frame.pivot + direction * 0.9, axis_color);
+                        geometry.cube(
+                            frame.pivot + direction * 0.95,
+                            frame.axes * (frame.size * 0.05),
+                            axis_color,
+                        );
+                    }
+                }
+            }
+        }
+
+        if self.mode == GizmoMode::Scale {
+            geometry.cube(
+                frame.pivot,
+                frame.axes * (frame.size * 0.07),
+                color(Handle::Center, CENTER_COLOR),
+            );
+        }
+
+        geometry
+    }
+
+    /// Where the gizmo is, if anything is selected.
+    fn frame(&self, scene: &SceneData, window_size: glam::Vec2) -> Option<GizmoFrame> {
+        let graph = scene.graph();
+        let positions: Vec<glam::Vec3> = outermost_selected(scene)
+            .into_iter()
+            .filter_map(|id| graph.get(id))
+            .map(|node| node.world_transform().w_axis.truncate())
+            .collect();
+        if positions.is_empty() {
+            return None;
+        }
+        let pivot = positions.iter().sum::<glam::Vec3>() / positions.len() as f32;
+
+        // The axes are kept fixed while dragging, so that rotating in local space
+        // doesn't change the axis being rotated about.
+        let axes = match (&self.drag, self.space) {
+            (Some(drag), _) => drag.axes,
+            (None, GizmoSpace::World) => glam::Mat3::IDENTITY,
+            (None, GizmoSpace::Local) => scene
+                .selection()
+                .last()
+                .and_then(|id| graph.get(*id))
+                .map_or(glam::Mat3::IDENTITY, |node| {
+                    let (_, rotation, _) = node.world_transform().to_scale_rotation_translation();
+                    glam::Mat3::from_quat(rotation)
+                }),
+        };
+
+        let size = scene
+            .camera
+            .world_size_of_pixels(pivot, GIZMO_SIZE, window_size);
+
+        Some(GizmoFrame { pivot, axes, size })
+    }
+
+    /// The handle closest to `cursor`, if any are close enough to grab.
+    fn handle_at(
+        &self,
+        scene: &SceneData,
+        cursor: glam::Vec2,
+        window_size: glam::Vec2,
+    ) -> Option<Handle> {
+        let frame = self.frame(scene, window_size)?;
+        let project = |point: glam::Vec3| {
+            scene
+                .camera
+                .project_point(point)
+                .map(|ndc| ndc_to_window(ndc, window_size))
+        };
+        let center = project(frame.pivot)?;
+
+        // The small handles near the center take priority over the axes running
+        // through them.
+        match self.mode {
+            GizmoMode::Translate => {
+                for axis in 0..3 {
+                    let corners = plane_corners(&frame, axis).map(project);
+                    if let [Some(a), Some(b), Some(c), Some(d)] = corners {
+                        if quad_contains([a, b, c, d], cursor) {
+                            return Some(Handle::Plane(axis));
+                        }
+                    }
+                }
+            }
+            GizmoMode::Scale => {
+                if center.distance(cursor) < GRAB_DISTANCE * 1.5 {
+                    return Some(Handle::Center);
+                }
+            }
+            GizmoMode::Rotate => {}
+        }
+
+        let mut closest = None;
+        let mut closest_distance = GRAB_DISTANCE;
+        for axis in 0..3 {
+            let distance = if self.mode == GizmoMode::Rotate {
+                let points: Vec<_> = ring_points(&frame, axis)
+                    .into_iter()
+                    .filter_map(project)
+                    .collect();
+                points
+                    .iter()
+                    .zip(points.iter().cycle().skip(1))
+                    .map(|(a, b)| distance_to_segment(cursor, *a, *b))
+                    .fold(f32::INFINITY, f32::min)
+            } else if axis_is_visible(&scene.camera, &frame, axis, window_size) {
+                match project(frame.pivot + frame.axes.col(axis) * frame.size) {
+                    Some(tip) => distance_to_segment(cursor, center, tip),
+                    None => continue,
+                }
+            } else {
+                continue;
+            };
+
+            if distance < closest_distance {
+                closest = Some(Handle::Axis(axis));
+                closest_distance = distance;
+            }
+        }
+        closest
+    }
+}
+
+/// The selected nodes, leaving out those with a selected ancestor, which are moved
+/// along with it.
+fn outermost_selected(scene: &SceneData) -> Vec<NodeId> {
+    let graph = scene.graph();
+    scene
+        .selection()
+        .iter()
+        .copied()
+        .filter(|id| {
+            let mut ancestor = graph.get(*id).and_then(|node| node.parent());
+            while let Some(id) = ancestor {
+                if scene.is_selected(id) {
+                    return false;
+                }
+                ancestor = graph.get(id).and_then(|node| node.parent());
+            }
+            true
+        })
+        .collect()
+}
+
+/// Where the cursor `ray` meets the line or plane that `handle` moves along. The center
+/// handle works in screen space, so this is just the pivot for it.
+fn constrained_point(
+    mode: GizmoMode,
+    handle: Handle,
+    pivot: glam::Vec3,
+    axes: glam::Mat3,
+    (origin, direction): (glam::Vec3, glam::Vec3),
+) -> Option<glam::Vec3> {
+    match (mode, handle) {
+        (GizmoMode::Rotate, Handle::Axis(axis)) | (_, Handle::Plane(axis)) => {
+            let normal = axes.col(axis);
+            let denominator = direction.dot(normal);
+            if denominator.abs() < 1e-6 {
+                return None;
+            }
+            let t = (pivot - origin).dot(normal) / denominator;
+            (t > 0.0).then(|| origin + direction * t)
+        }
+        (_, Handle::Axis(axis)) => {
+            // The point on the axis closest to the ray. Both directions are unit length.
+            let axis = axes.col(axis);
+            let cos = axis.dot(direction);
+            let denominator = 1.0 - cos * cos;
+            if denominator < 1e-6 {
+                return None;
+            }
+            let to_pivot = pivot - origin;
+            let s = (cos * direction.dot(to_pivot) - axis.dot(to_pivot)) / denominator;
+            Some(pivot + axis * s)
+        }
+        (_, Handle::Center) => Some(pivot),
+    }
+}
+
+fn axis_is_visible(
+    camera: &Camera,
+    frame: &GizmoFrame,
+    axis: usize,
+    window_size: glam::Vec2,
+) -> bool {
+    let project = |point: glam::Vec3| {
+        camera
+            .project_point(point)
+            .map(|ndc| ndc_to_window(ndc, window_size))
+    };
+    match (
+        project(frame.pivot),
+        project(frame.pivot + frame.axes.col(axis) * frame.size),
+    ) {
+        (Some(center), Some(tip)) => center.distance(tip) >= GIZMO_SIZE * MIN_VISIBLE_AXIS_LENGTH,
+        _ => false,
+    }
+}
+
+/// The two axes other than `axis`, in an order that keeps them right handed.
+fn other_axes(axes: glam::Mat3, axis: usize) -> (glam::Vec3, glam::Vec3) {
+    (axes.col((axis + 1) % 3), axes.col((axis + 2) % 3))
+}
+
+/// The square handle for moving in the plane perpendicular to `axis`.
+fn plane_corners(frame: &GizmoFrame, axis: usize) -> [glam::Vec3; 4] {
+    let (u, v) = other_axes(frame.axes, axis);
+    let (near, far) = (frame.size * 0.25, frame.size * 0.45);
+    [
+        frame.pivot + u * near + v * near,
+        frame.pivot + u * far + v * near,
+        frame.pivot + u * far + v * far,
+        frame.pivot + u * near + v * far,
+    ]
+}
+
+/// The circle for rotating about `axis`.
+fn ring_points(frame: &GizmoFrame, axis: usize) -> Vec<glam::Vec3> {
+    let (u, v) = other_axes(frame.axes, axis);
+    (0..RING_SEGMENTS)
+        .map(|segment| {
+            let angle = segment as f32 / RING_SEGMENTS as f32 * std::f32::consts::TAU;
+            frame.pivot + (u * angle.cos() + v * angle.sin()) * frame.size
+        })
+        .collect()
+}
+
+fn distance_to_segment(point: glam::Vec2, a: glam::Vec2, b: glam::Vec2) -> f32 {
+    let ab = b - a;
+    let t = if ab.length_squared() > 0.0 {
+        ((point - a).dot(ab) / ab.length_squared()).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    point.distance(a + ab * t)
+}
+
+/// Whether the convex quadrilateral with the given corners, in either winding order,
+/// contains `point`.
+fn quad_contains(corners: [glam::Vec2; 4], point: glam::Vec2) -> bool {
+    let sides = [0, 1, 2, 3].map(|i| {
+        let (a, b) = (corners[i], corners[(i + 1) % 4]);
+        (b - a).perp_dot(point - a)
+    });
+    sides.iter().all(|side| *side >= 0.0) || sides.iter().all(|side| *side <= 0.0)
+}
+
+fn round_to(value: f32, increment: f32) -> f32 {
+    if increment > 0.0 {
+        (value / increment).round() * increment
+    } else {
+        value
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct GizmoVertex {
+    position: [f32; 3],
+    color: [f32; 4],
+}
+
+impl GizmoVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4];
+}
+
+/// World space shapes making up the gizmo and other overlays, like light icons, drawn
+/// by `GizmoRenderRoutine`.
+#[derive(Default)]
+pub struct GizmoGeometry {
+    /// Pairs of vertices.
+    lines: Vec<GizmoVertex>,
+    triangles: Vec<GizmoVertex>,
+}
+
+impl GizmoGeometry {
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty() && self.triangles.is_empty()
+    }
+
+    fn vertex(position: glam::Vec3, color: glam::Vec4) -> GizmoVertex {
+        GizmoVertex {
+            position: position.to_array(),
+            color: color.to_array(),
+        }
+    }
+
+    pub fn line(&mut self, a: glam::Vec3, b: glam::Vec3, color: glam::Vec4) {
+        self.lines
+            .extend([Self::vertex(a, color), Self::vertex(b, color)]);
+    }
+
+    fn triangle(&mut self, a: glam::Vec3, b: glam::Vec3, c: glam::Vec3, color: glam::Vec4) {
+        self.triangles.extend([
+            Self::vertex(a, color),
+            Self::vertex(b, color),
+            Self::vertex(c, color),
+        ]);
+    }
+
+    fn quad(&mut self, [a, b, c, d]: [glam::Vec3; 4], color: glam::Vec4) {
+        self.triangle(a, b, c, color);
+        self.triangle(a, c, d, color);
+    }
+
+    /// A closed cone from `base` to `base + height`, with `u` and `v` spanning the base.
+    fn cone(
+        &mut self,
+        base: glam::Vec3,
+        height: glam::Vec3,
+        u: glam::Vec3,
+        v: glam::Vec3,
+        color: glam::Vec4,
+    ) {
+        let apex = base + height;
+        let rim: Vec<glam::Vec3> = (0..CONE_SEGMENTS)
+            .map(|segment| {
+                let angle = segment as f32 / CONE_SEGMENTS as f32 * std::f32::consts::TAU;
+                base + u * angle.cos() + v * angle.sin()
+            })
+            .collect();
+        for (a, b) in rim.iter().zip(rim.iter().cycle().skip(1)) {
+            self.triangle(apex, *a, *b, color);
+            self.triangle(base, *b, *a, color);
+        }
+    }
+
+    /// A box around `center` whose half extents are the columns of `half_axes`.
+    fn cube(&mut self, center: glam::Vec3, half_axes: glam::Mat3, color: glam::Vec4) {
+        for axis in 0..3 {
+            let (u, v) = other_axes(half_axes, axis);
+            for side in [-1.0, 1.0] {
+                let face = center + half_axes.col(axis) * side;
+                self.quad(
+                    [face - u - v, face + u - v, face + u + v, face - u + v],
+                    color,
+                );
+            }
+        }
+    }
+}
+
+pub struct GizmoRenderRoutine {
+    line_pipeline: wgpu::RenderPipeline,
+    triangle_pipeline: wgpu::RenderPipeline,
+    uniform_bind_group_layout: wgpu::BindGroupLayout,
+}
+
+/// A frame's `GizmoGeometry`, uploaded by `GizmoRenderRoutine::create_buffers()`.
+struct GizmoBuffers {
+    uniform_bind_group: wgpu::BindGroup,
+    /// The triangles followed by the lines.
+    vertex_buffer: wgpu::Buffer,
+}
+
+impl GizmoRenderRoutine {
+    pub fn new(renderer: &rend3::Renderer, surface_format: wgpu::TextureFormat) -> Self {
+        let shader_module = renderer
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("gizmo shader"),
+                source: wgpu::ShaderSource::Wgsl(include_str!("gizmo.wgsl").into()),
+            });
+
+        let uniform_bind_group_layout =
+            renderer
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    }],
+                    label: Some("gizmo bind group layout"),
+                });
+
+        let render_pipeline_layout =
+            renderer
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("gizmo render pipeline layout"),
+                    bind_group_layouts: &[&uniform_bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+
+        let create_pipeline = |label, topology| {
+            renderer
+                .device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some(label),
+                    layout: Some(&render_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader_module,
+                        entry_point: "vs_main",
+                        buffers: &[wgpu::VertexBufferLayout {
+                            array_stride: std::mem::size_of::<GizmoVertex>() as wgpu::BufferAddress,
+                            step_mode: wgpu::VertexStepMode::Vertex,
+                            attributes: &GizmoVertex::ATTRIBUTES,
+                        }],
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader_module,
+                        entry_point: "fs_main",
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format: surface_format,
+                            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                    }),
+                    // The handles are seen from both sides, and are drawn over the
+                    // scene regardless of depth so they can't get lost inside objects.
+                    primitive: wgpu::PrimitiveState {
+                        topology,
+                        strip_index_format: None,
+                        front_face: wgpu::FrontFace::Ccw,
+                        cull_mode: None,
+                        polygon_mode: wgpu::PolygonMode::Fill,
+                        unclipped_depth: false,
+                        conservative: false,
+                    },
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview: None,
+                })
+        };
+        let line_pipeline = create_pipeline(
+            "gizmo line render pipeline",
+            wgpu::PrimitiveTopology::LineList,
+        );
+        let triangle_pipeline = create_pipeline(
+            "gizmo triangle render pipeline",
+            wgpu::PrimitiveTopology::TriangleList,
+        );
+
+        Self {
+            line_pipeline,
+            triangle_pipeline,
+            uniform_bind_group_layout,
+        }
+    }
+
+    /// Draws `geometry` over whatever is already in `output`.
+    pub fn add_to_graph<'node>(
+        &'node self,
+        graph: &mut rend3::graph::RenderGraph<'node>,
+        output: rend3::graph::RenderTargetHandle,
+        geometry: GizmoGeometry,
+    ) {
+        if geometry.is_empty() {
+            return;
+        }
+
+        let triangle_count = geometry.triangles.len() as u32;
+        let line_count = geometry.lines.len() as u32;
+        let buffers = graph.add_data::<GizmoBuffers>();
+        self.create_buffers(graph, buffers, geometry);
+        self.render(graph, output, buffers, triangle_count, line_count);
+    }
+
+    /// Adds a node to the render graph which uploads the vertices, along with the
+    /// view-projection matrix from the camera manager attached to the graph.
+    fn create_buffers<'node>(
+        &'node self,
+        graph: &mut rend3::graph::RenderGraph<'node>,
+        buffers: rend3::graph::DataHandle<GizmoBuffers>,
+        geometry: GizmoGeometry,
+    ) {
+        let mut builder = graph.add_node("build gizmo buffers");
+
+        let output_handle = builder.add_data(buffers, rend3::graph::NodeResourceUsage::Output);
+        builder.build(move |ctx| {
+            let uniform = GizmoUniform {
+                view_projection_matrix: *ctx.data_core.camera_manager.view_proj().as_ref_parts(),
+            };
+            let uniform_buffer =
+                ctx.renderer
+                    .device
+                    .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("gizmo uniform buffer"),
+                        contents: bytemuck::cast_slice(&[uniform]),
+                        usage: wgpu::BufferUsages::UNIFORM,
+                    });
+            let uniform_bind_group =
+                ctx.renderer
+                    .device
+                    .create_bind_group(&wgpu::BindGroupDescriptor {
+                        layout: &self.uniform_bind_group_layout,
+                        entries: &[wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: uniform_buffer.as_entire_binding(),
+                        }],
+                        label: Some("gizmo bind group"),
+                    });
+
+            let vertices: Vec<GizmoVertex> = geometry
+                .triangles
+                .into_iter()
+                .chain(geometry.lines)
+                .collect();
+            let vertex_buffer =
+                ctx.renderer
+                    .device
+                    .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("gizmo vertex buffer"),
+                        contents: bytemuck::cast_slice(&vertices),
+                        usage: wgpu::BufferUsages::VERTEX,
+                    });
+
+            ctx.graph_data.set_data(
+                output_handle,
+                Some(GizmoBuffers {
+                    uniform_bind_group,
+                    vertex_buffer,
+                }),
+            );
+        });
+    }
+
+    /// Adds a node to the render graph which draws the gizmo from the buffers filled
+    /// by `create_buffers()`. It has no depth target, so the gizmo ends up in front of
+    /// everything drawn before it.
+    fn render<'node>(
+        &'node self,
+        graph: &mut rend3::graph::RenderGraph<'node>,
+        output: rend3::graph::RenderTargetHandle,
+        buffers: rend3::graph::DataHandle<GizmoBuffers>,
+        triangle_count: u32,
+        line_count: u32,
+    ) {
+        let mut builder = graph.add_node("gizmo");
+
+        let output_handle =
+            builder.add_render_target(output, rend3::graph::NodeResourceUsage::InputOutput);
+
+        let rpass_handle = builder.add_renderpass(rend3::graph::RenderPassTargets {
+            targets: vec![rend3::graph::RenderPassTarget {
+                color: output_handle,
+                clear: rend3::types::Color::BLACK,
+                resolve: None,
+            }],
+            depth_stencil: None,
+        });
+
+        let buffers_handle = builder.add_data(buffers, rend3::graph::NodeResourceUsage::Input);
+
+        builder.build(move |mut ctx| {
+            let rpass = ctx.encoder_or_pass.take_rpass(rpass_handle);
+            let buffers = ctx.graph_data.get_data(ctx.temps, buffers_handle).unwrap();
+
+            rpass.set_bind_group(0, &buffers.uniform_bind_group, &[]);
+            rpass.set_vertex_buffer(0, buffers.vertex_buffer.slice(..));
+            rpass.set_pipeline(&self.triangle_pipeline);
+            rpass.draw(0..triangle_count, 0..1);
+            rpass.set_pipeline(&self.line_pipeline);
+            rpass.draw(triangle_count..triangle_count + line_count, 0..1);
+        });
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct GizmoUniform {
+    // projection_matrix * view_matrix (column vectors)
+    view_projection_matrix: [[f32; 4]; 4],
+}
@@ -0,0 +1,33 @@
+// Vertex shader
+
+struct GizmoUniform {
+    view_projection_matrix: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> gizmo_uniform: GizmoUniform;
+
+struct VertexIn {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+struct VertexOut {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(in: VertexIn) -> VertexOut {
+    var out: VertexOut;
+    out.position = gizmo_uniform.view_projection_matrix * vec4<f32>(in.position, 1.0);
+    out.color = in.color;
+    return out;
+}
+
+// Fragment shader
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+    return in.color;
+}
@@ -1,6 +1,11 @@
 use encase::private::AsRefMatrixParts;
 use wgpu::util::DeviceExt;
 
+use crate::camera::LensSettings;
+
+/// How far away the grid fades out when the camera has no far plane.
+const INFINITE_GRID_FADE_DISTANCE: f32 = 250.0;
+
 
 pub struct GridRenderRoutine {
     pipeline: wgpu::RenderPipeline,
@@ -96,26 +101,29 @@ impl GridRenderRoutine {
         graph: &mut rend3::graph::RenderGraph<'node>,
         depth_target: rend3::graph::RenderTargetHandle,
         output: rend3::graph::RenderTargetHandle,
+        lens: LensSettings,
     ) {
         let grid_uniform_bg = graph.add_data::<wgpu::BindGroup>();
-        self.create_bind_groups(graph, grid_uniform_bg);
+        self.create_bind_groups(graph, grid_uniform_bg, lens);
         self.render(graph, depth_target, output, grid_uniform_bg);
     }
 
     /// Adds a node to the render graph which is responsible for filling the grid uniform bind
     /// group resource. To do this, it reconstructs the grid uniform using the view/projection
-    /// matrices from the camera manager attached to the graph.
+    /// matrices from the camera manager attached to the graph, and the clipping planes from
+    /// `lens`, which should be the lens of the same camera.
     fn create_bind_groups<'node>(
         &'node self,
         graph: &mut rend3::graph::RenderGraph<'node>,
         grid_uniform_bg: rend3::graph::DataHandle<wgpu::BindGroup>,
+        lens: LensSettings,
     ) {
         let mut builder = graph.add_node("build grid uniforms");
 
         let output_handle = builder.add_data(grid_uniform_bg, rend3::graph::NodeResourceUsage::Output);
         builder.build(
             move |ctx| {
-                let uniform = GridUniform::new(&ctx.data_core.camera_manager);
+                let uniform = GridUniform::new(&ctx.data_core.camera_manager, &lens);
                 let uniform_buffer = ctx.renderer 
                     .device
                     .create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -197,18 +205,18 @@ pub struct GridUniform {
 }
 
 impl GridUniform {
-    /// - `camera`: the rend3 camera from which we get the view-projection matrix and the near
-    /// plane.  TODO
-    /// - `z_far`: the far plane, since rend3 assumes an infinite far plane.
-    pub fn new(camera_manager: &rend3::managers::CameraManager) -> Self {
+    /// - `camera_manager`: the rend3 camera from which we get the view-projection matrix.
+    /// - `lens`: the lens of the same camera, from which we get the clipping planes. The grid
+    /// fades out towards the far plane.
+    pub fn new(camera_manager: &rend3::managers::CameraManager, lens: &LensSettings) -> Self {
         let view_projection_matrix = camera_manager.view_proj();
         let view_projection_matrix_inverse = view_projection_matrix.inverse();
 
         Self {
             view_projection_matrix: *view_projection_matrix.as_ref_parts(),
             view_projection_matrix_inverse: *view_projection_matrix_inverse.as_ref_parts(),
-            z_near: 0.001, // TODO
-            z_far: 100.,
+            z_near: lens.z_near,
+            z_far: lens.z_far.unwrap_or(INFINITE_GRID_FADE_DISTANCE),
             _padding: [0; 2],
         }
     }
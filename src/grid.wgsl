@@ -71,17 +71,28 @@ fn grid(frag_pos_3d: vec3<f32>, scale: f32) -> vec4<f32> {
     return color;
 }
 
+// Orthographic projections don't divide by the distance to the camera, so the bottom
+// row of their matrix is (0, 0, 0, 1).
+fn is_orthographic() -> bool {
+    let m = unproject_uniform.view_projection_matrix;
+    return m[0][3] == 0.0 && m[1][3] == 0.0 && m[2][3] == 0.0;
+}
+
 fn compute_depth(frag_pos_3d: vec3<f32>) -> f32 {
     let clip_space_pos = unproject_uniform.view_projection_matrix * vec4<f32>(frag_pos_3d, 1.0);
     return (clip_space_pos.z / clip_space_pos.w);
 }
 
-fn fading(frag_pos_3d: vec3<f32>, depth: f32) -> f32 {
-    let znear = 0.001;
-    // If you're using far plane at infinity as described here, then linearized depth is simply znear / depth.
-    // From: https://www.reddit.com/r/GraphicsProgramming/comments/f9zwin/linearising_reverse_depth_buffer/
-    let linear_depth = znear / depth;
-    return max(0.0, 2.5 - linear_depth);
+fn fading(frag_pos_3d: vec3<f32>) -> f32 {
+    // Orthographic views have no horizon for the grid to fade into.
+    if (is_orthographic()) {
+        return 1.0;
+    }
+    // For perspective projections, w is the distance from the camera along the view
+    // direction, whether the far plane is at infinity or not.
+    let view_depth = (unproject_uniform.view_projection_matrix * vec4<f32>(frag_pos_3d, 1.0)).w;
+    // Fully visible up to 60% of the way to the far plane.
+    return clamp(2.5 * (1.0 - view_depth / unproject_uniform.z_far), 0.0, 1.0);
 }
 
 struct FragmentShaderOutput {
@@ -97,9 +108,11 @@ fn fs_main(in: VertexOut) -> FragmentShaderOutput {
     let depth = compute_depth(frag_pos_3d);
 
     var out: FragmentShaderOutput;
-    out.color = grid(frag_pos_3d, 2.0) * f32(t < 0.0);
+    // Only draw the grid where the ray hits the ground within the visible depth range,
+    // which also leaves out hits behind a perspective camera.
+    out.color = grid(frag_pos_3d, 2.0) * f32(depth > 0.0 && depth <= 1.0);
     out.fragDepth = depth;
-    out.color.a = out.color.a * fading(frag_pos_3d, depth);
+    out.color.a = out.color.a * fading(frag_pos_3d);
 
     return out;
 }
@@ -0,0 +1,274 @@
+//! Undo and redo.
+//!
+//! Edits are recorded as commands which know how to reverse themselves, once they have
+//! been made. Commands are grouped into entries, one per user action, so that everything
+//! a single action did (say, a whole drag) is undone in one step. Once the history takes
+//! up more than its memory limit, the oldest entries are forgotten.
+
+use std::collections::VecDeque;
+
+/// How much memory a history may use by default, in bytes.
+pub const DEFAULT_MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+
+pub trait Command {
+    /// What the command edits. It may borrow, so that things which are needed together,
+    /// like a scene and the renderer drawing it, can be passed as one.
+    type Target<'a>;
+
+    /// Reverses the command. `target` is in the state the command left it in.
+    fn undo(&mut self, target: &mut Self::Target<'_>);
+
+    /// Makes the command again, after it has been undone.
+    fn redo(&mut self, target: &mut Self::Target<'_>);
+
+    /// Roughly how many bytes the command keeps alive.
+    fn memory_size(&self) -> usize;
+
+    /// Folds `next`, which was made right after this command, into this one, so that
+    /// they are undone together. Returns `next` if they can't be merged.
+    fn merge(&mut self, next: Self) -> Result<(), Self>
+    where
+        Self: Sized,
+    {
+        Err(next)
+    }
+}
+
+/// Commands that are undone and redone together.
+struct Entry<C> {
+    name: String,
+    commands: Vec<C>,
+}
+
+impl<C: Command> Entry<C> {
+    fn memory_size(&self) -> usize {
+        self.name.len() + self.commands.iter().map(C::memory_size).sum::<usize>()
+    }
+}
+
+pub struct History<C> {
+    /// Oldest first.
+    done: VecDeque<Entry<C>>,
+    /// The most recently undone last.
+    undone: Vec<Entry<C>>,
+    /// In bytes. The most recent entry is always kept, even if it is larger than this.
+    pub memory_limit: usize,
+}
+
+impl<C> Default for History<C> {
+    fn default() -> Self {
+        Self {
+            done: VecDeque::new(),
+            undone: Vec::new(),
+            memory_limit: DEFAULT_MEMORY_LIMIT,
+        }
+    }
+}
+
+impl<C: Command> History<C> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a command which has already been made. Anything that was undone can't be
+    /// redone anymore. If the most recent entry is a single command of the same name
+    /// which can be merged with this one, the command is added to it instead.
+    pub fn push(&mut self, name: impl Into<String>, command: C) {
+        let name = name.into();
+        let command = match self.done.back_mut() {
+            Some(last) if last.name == name && last.commands.len() == 1 => {
+                match last.commands[0].merge(command) {
+                    Ok(()) => {
+                        self.undone.clear();
+                        self.enforce_memory_limit();
+                        return;
+                    }
+                    Err(command) => command,
+                }
+            }
+            _ => command,
+        };
+        self.push_group(name, vec![command]);
+    }
+
+    /// Records several commands which have already been made, in the order they were
+    /// made, as a single entry.
+    pub fn push_group(&mut self, name: impl Into<String>, commands: Vec<C>) {
+        if commands.is_empty() {
+            return;
+        }
+
+        self.undone.clear();
+        self.done.push_back(Entry {
+            name: name.into(),
+            commands,
+        });
+        self.enforce_memory_limit();
+    }
+
+    /// Undoes the most recent entry. Returns false if there was nothing to undo.
+    pub fn undo(&mut self, target: &mut C::Target<'_>) -> bool {
+        let Some(mut entry) = self.done.pop_back() else {
+            return false;
+        };
+        for command in entry.commands.iter_mut().rev() {
+            command.undo(target);
+        }
+        log::info!("undid {}", entry.name);
+        self.undone.push(entry);
+        // Undoing can make commands hold on to more, e.g. to the nodes they removed.
+        self.enforce_memory_limit();
+        true
+    }
+
+    /// Redoes the most recently undone entry. Returns false if there was nothing to redo.
+    pub fn redo(&mut self, target: &mut C::Target<'_>) -> bool {
+        let Some(mut entry) = self.undone.pop() else {
+            return false;
+        };
+        for command in &mut entry.commands {
+            command.redo(target);
+        }
+        log::info!("redid {}", entry.name);
+        self.done.push_back(entry);
+        self.enforce_memory_limit();
+        true
+    }
+
+    /// Undoes or redoes until `position` entries are done.
+    pub fn go_to(&mut self, position: usize, target: &mut C::Target<'_>) {
+        while self.done.len() > position && self.undo(target) {}
+        while self.done.len() < position && self.redo(target) {}
+    }
+
+    /// Forgets every entry, e.g. because what they edited has been replaced.
+    pub fn clear(&mut self) {
+        self.done.clear();
+        self.undone.clear();
+    }
+
+    pub fn memory_used(&self) -> usize {
+        self.done
+            .iter()
+            .chain(&self.undone)
+            .map(Entry::memory_size)
+            .sum()
+    }
+
+    fn enforce_memory_limit(&mut self) {
+        let mut memory_used = self.memory_used();
+        while memory_used > self.memory_limit {
+            // Forget what is furthest away from the current state first: the entries
+            // furthest in the past, or if everything left is undone, the furthest in
+            // the future.
+            let forgotten = if self.done.len() > 1 {
+                self.done.pop_front()
+            } else if !self.undone.is_empty() {
+                Some(self.undone.remove(0))
+            } else {
+                None
+            };
+            match forgotten {
+                Some(entry) => memory_used -= entry.memory_size(),
+                None => break,
+            }
+        }
+    }
+}
+
+/// Lists the entries of `history`, oldest first, with undone ones greyed out. Returns
+/// how many entries should be done to get to the one that was clicked, for
+/// `History::go_to()`.
+pub fn draw_egui_history<C: Command>(ui: &mut egui::Ui, history: &History<C>) -> Option<usize> {
+    let mut clicked = None;
+
+    ui.label(format!(
+        "Using {:.1} of {:.0} MB",
+        history.memory_used() as f64 / 1e6,
+        history.memory_limit as f64 / 1e6
+    ));
+    ui.separator();
+
+    egui::ScrollArea::vertical()
+        .auto_shrink([false, true])
+        .stick_to_bottom(true)
+        .show(ui, |ui| {
+            let current = history.done.len();
+            if ui
+                .selectable_label(current == 0, "Start of history")
+                .clicked()
+            {
+                clicked = Some(0);
+            }
+
+            let entries = history.done.iter().chain(history.undone.iter().rev());
+            for (index, entry) in entries.enumerate() {
+                let position = index + 1;
+                let text = if position > current {
+                    egui::RichText::new(&entry.name).weak()
+                } else {
+                    egui::RichText::new(&entry.name)
+                };
+                if ui.selectable_label(position == current, text).clicked() {
+                    clicked = Some(position);
+                }
+            }
+        });
+
+    clicked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sets a number, from the first value to the second.
+    struct Set(i32, i32);
+
+    impl Command for Set {
+        type Target<'a> = i32;
+
+        fn undo(&mut self, target: &mut i32) {
+            *target = self.0;
+        }
+
+        fn redo(&mut self, target: &mut i32) {
+            *target = self.1;
+        }
+
+        fn memory_size(&self) -> usize {
+            0
+        }
+
+        fn merge(&mut self, next: Self) -> Result<(), Self> {
+            self.1 = next.1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn push_merges_into_the_last_entry_of_the_same_name() {
+        let mut history = History::new();
+        history.push("Set", Set(0, 1));
+        history.push("Set", Set(1, 2));
+        history.push("Other", Set(2, 3));
+        history.push("Set", Set(3, 4));
+        assert_eq!(history.done.len(), 3);
+
+        let mut value = 4;
+        history.undo(&mut value);
+        assert_eq!(value, 3);
+        history.undo(&mut value);
+        assert_eq!(value, 2);
+        history.undo(&mut value);
+        assert_eq!(value, 0);
+        assert!(!history.undo(&mut value));
+
+        // Undone entries are forgotten rather than merged into.
+        history.push("Set", Set(0, 5));
+        assert_eq!(history.done.len(), 1);
+        assert!(history.undone.is_empty());
+        history.undo(&mut value);
+        assert_eq!(value, 0);
+    }
+}
@@ -1,15 +1,107 @@
-use crate::math::vector::Vector2;
+use std::{collections::HashSet, hash::Hash};
 
+use serde::{Deserialize, Serialize};
+use winit::{
+    event::{ElementState, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent},
+    window::WindowId,
+};
+
+use crate::{
+    bindings::{Action, ActionMap, Modifiers, Trigger},
+    math::vector::Vector2,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum InputEvent {
     /// Rotate viewport about some pivot point, e.g. turntable rotation.
     DoViewportOrbit,
-    /// The keys for doing the viewport orbit have just been released.
+    /// The button for doing the viewport orbit has just been released.
     FinishViewportOrbit,
+    /// The selection button is held down. Whether this is a click, a box being dragged
+    /// out or a gizmo handle being dragged is only known once it is released, except
+    /// for the gizmo, which is decided by where the button was pressed.
+    DoSelectionDrag,
+    /// The selection button has just been released.
+    FinishSelection,
+    /// Move the viewport camera sideways, following the cursor.
+    DoViewportPan,
+    /// The button for panning has just been released.
+    FinishViewportPan,
+    /// The scroll wheel moved, which moves the viewport camera closer or further away.
+    ViewportZoom,
+    /// The mouse moved, which turns the viewport camera while it is flying.
+    ViewportLook,
+}
+
+/// How many pixels of touchpad scrolling count as one line of scroll wheel scrolling.
+const PIXELS_PER_SCROLL_LINE: f32 = 40.0;
+
+/// The input a window gets which the input state keeps track of. Unlike winit's events,
+/// these can be recorded to a file and replayed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum WindowInput {
+    Focused(bool),
+    CursorEntered,
+    CursorLeft,
+    /// In physical pixels from the top left corner of the window.
+    CursorMoved(glam::Vec2),
+    Button {
+        trigger: Trigger,
+        pressed: bool,
+    },
+    /// In lines, like `MouseState::scroll_delta`.
+    Scroll(glam::Vec2),
+}
+
+impl WindowInput {
+    pub fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        match event {
+            WindowEvent::Focused(focused) => Some(Self::Focused(*focused)),
+            WindowEvent::CursorEntered { .. } => Some(Self::CursorEntered),
+            WindowEvent::CursorLeft { .. } => Some(Self::CursorLeft),
+            WindowEvent::CursorMoved { position, .. } => Some(Self::CursorMoved(glam::Vec2::new(
+                position.x as f32,
+                position.y as f32,
+            ))),
+            WindowEvent::KeyboardInput { input, .. } => Some(Self::Button {
+                trigger: Trigger::Key(input.virtual_keycode?),
+                pressed: input.state == ElementState::Pressed,
+            }),
+            WindowEvent::MouseInput { state, button, .. } => Some(Self::Button {
+                trigger: Trigger::Mouse(*button),
+                pressed: *state == ElementState::Pressed,
+            }),
+            WindowEvent::MouseWheel { delta, .. } => Some(Self::Scroll(match delta {
+                MouseScrollDelta::LineDelta(x, y) => glam::Vec2::new(*x, *y),
+                // Touchpads scroll in pixels.
+                MouseScrollDelta::PixelDelta(pixels) => {
+                    glam::Vec2::new(pixels.x as f32, pixels.y as f32) / PIXELS_PER_SCROLL_LINE
+                }
+            })),
+            _ => None,
+        }
+    }
+
+    /// Whether the input is kept track of even when egui takes it, so that keys and
+    /// buttons aren't left held down.
+    pub fn tracked_under_egui(&self) -> bool {
+        matches!(
+            self,
+            Self::Focused(_)
+                | Self::CursorEntered
+                | Self::CursorLeft
+                | Self::Button { pressed: false, .. }
+        )
+    }
 }
 
 pub struct InputState {
     pub mouse: MouseState,
     pub keyboard: KeyboardState,
+    /// The window keyboard input goes to, if any of ours has focus.
+    pub focused_window: Option<WindowId>,
+    /// Which keys and mouse buttons do what.
+    pub bindings: ActionMap,
 }
 
 impl Default for InputState {
@@ -17,76 +109,342 @@ impl Default for InputState {
         Self {
             mouse: MouseState::default(),
             keyboard: KeyboardState::default(),
+            focused_window: None,
+            bindings: ActionMap::default(),
         }
     }
 }
 
 impl InputState {
-    pub fn get_input_events(&self) -> Vec<InputEvent> {
+    /// The input events for `window`. Drags go to the window they were started in,
+    /// scrolling to the window under the cursor, and mouse movement to the focused
+    /// window.
+    pub fn get_input_events(&self, window: WindowId) -> Vec<InputEvent> {
         let mut input_events = Vec::new();
 
-        // DoViewportOrbit
-        if self.mouse.lmb_pressed && self.keyboard.shift_pressed {
-            input_events.push(InputEvent::DoViewportOrbit);
+        let in_window = |drag: Option<Drag>| {
+            drag.filter(|drag| drag.window == window)
+                .map(|drag| drag.action)
+        };
+
+        match in_window(self.mouse.drag) {
+            Some(Action::Orbit) => input_events.push(InputEvent::DoViewportOrbit),
+            Some(Action::Select) => input_events.push(InputEvent::DoSelectionDrag),
+            Some(Action::Pan) => input_events.push(InputEvent::DoViewportPan),
+            _ => {}
+        }
+
+        match in_window(self.mouse.finished_drag) {
+            Some(Action::Orbit) => input_events.push(InputEvent::FinishViewportOrbit),
+            Some(Action::Select) => input_events.push(InputEvent::FinishSelection),
+            Some(Action::Pan) => input_events.push(InputEvent::FinishViewportPan),
+            _ => {}
         }
 
-        // FinishViewportOrbit TODO
-        if self.mouse.lmb_released || self.keyboard.shift_released {
-            input_events.push(InputEvent::FinishViewportOrbit);
+        // ViewportZoom
+        if self.mouse.scroll_delta.y() != 0.0 && self.mouse.cursor_window == Some(window) {
+            input_events.push(InputEvent::ViewportZoom);
+        }
+
+        // ViewportLook
+        if self.mouse.motion_delta.to_glam_vec2() != glam::Vec2::ZERO
+            && self.focused_window == Some(window)
+        {
+            input_events.push(InputEvent::ViewportLook);
         }
 
         input_events
     }
 
+    /// Updates the state for `input` having happened in `window`. Returns the actions
+    /// whose keys or buttons were pressed or released, for the window to do. Drag
+    /// actions are left out, since they are passed on as input events.
+    pub fn handle_window_input(
+        &mut self,
+        window: WindowId,
+        input: &WindowInput,
+    ) -> Vec<(Action, ElementState)> {
+        let mut actions = Vec::new();
+        match *input {
+            WindowInput::Focused(focused) => self.handle_focus(window, focused),
+            WindowInput::CursorEntered => self.mouse.cursor_window = Some(window),
+            WindowInput::CursorLeft => {
+                if self.mouse.cursor_window == Some(window) {
+                    self.mouse.cursor_window = None;
+                }
+            }
+            WindowInput::CursorMoved(position) => {
+                self.mouse.window_cursor_pos = Vector2::new(position.x, position.y);
+                self.mouse.cursor_window = Some(window);
+            }
+            WindowInput::Scroll(delta) => {
+                self.mouse.scroll_delta += Vector2::new(delta.x, delta.y);
+            }
+            WindowInput::Button {
+                trigger,
+                pressed: true,
+            } => {
+                let action = match trigger {
+                    Trigger::Key(key) => {
+                        self.keyboard.keys.update(key, ElementState::Pressed);
+                        // Holding a key down doesn't repeat its action.
+                        if self.keyboard.keys.just_pressed(key) {
+                            self.bindings.action_for(trigger, self.keyboard.modifiers())
+                        } else {
+                            None
+                        }
+                    }
+                    Trigger::Mouse(button) => self.press_mouse_button(window, button),
+                };
+                actions.extend(action.map(|action| (action, ElementState::Pressed)));
+            }
+            WindowInput::Button {
+                trigger,
+                pressed: false,
+            } => {
+                let released = match trigger {
+                    Trigger::Key(key) => {
+                        self.keyboard.keys.update(key, ElementState::Released);
+                        self.keyboard.keys.just_released(key)
+                    }
+                    Trigger::Mouse(button) => {
+                        self.release_mouse_button(button);
+                        self.mouse.buttons.just_released(button)
+                    }
+                };
+                if released {
+                    actions.extend(
+                        self.bindings
+                            .actions_with_trigger(trigger)
+                            .filter(|action| !action.is_drag())
+                            .map(|action| (action, ElementState::Released)),
+                    );
+                }
+            }
+        }
+        actions
+    }
+
+    /// Counts mouse movement which doesn't belong to any window, like
+    /// `MouseState::motion_delta`.
+    pub fn handle_mouse_motion(&mut self, delta: glam::Vec2) {
+        self.mouse.motion_delta += Vector2::new(delta.x, delta.y);
+    }
+
+    /// Drags are decided by the modifiers held when the button is pressed, and last
+    /// until it is released. Returns the action to do if the button is bound to one
+    /// which isn't a drag.
+    fn press_mouse_button(&mut self, window: WindowId, button: MouseButton) -> Option<Action> {
+        self.mouse.buttons.update(button, ElementState::Pressed);
+        let action = self
+            .bindings
+            .action_for(Trigger::Mouse(button), self.keyboard.modifiers())?;
+        if !action.is_drag() {
+            return Some(action);
+        }
+        if self.mouse.drag.is_none() {
+            self.mouse.drag = Some(Drag {
+                action,
+                button,
+                window,
+            });
+        }
+        None
+    }
+
+    fn release_mouse_button(&mut self, button: MouseButton) {
+        self.mouse.buttons.update(button, ElementState::Released);
+        if let Some(drag) = self.mouse.drag {
+            if drag.button == button {
+                self.mouse.drag = None;
+                self.mouse.finished_drag = Some(drag);
+            }
+        }
+    }
+
+    /// A window losing focus won't hear about keys or mouse buttons being let go, so
+    /// they are released, and a drag started in the window is finished.
+    fn handle_focus(&mut self, window: WindowId, focused: bool) {
+        if focused {
+            self.focused_window = Some(window);
+            return;
+        }
+
+        if self.focused_window == Some(window) {
+            self.focused_window = None;
+            self.keyboard.keys.release_all();
+        }
+        if self.mouse.drag.is_some_and(|drag| drag.window == window) {
+            self.mouse.finished_drag = self.mouse.drag.take();
+            self.mouse.buttons.release_all();
+        }
+    }
+
+    /// Forgets what happened during this pass through the event loop: which keys and
+    /// buttons were just pressed or released, scrolling and mouse movement.
     pub fn reset_release_events(&mut self) {
         self.mouse.reset_release_events();
-        self.keyboard.reset_release_events();
+        self.keyboard.keys.reset_release_events();
     }
 }
 
+/// Which of a set of keys or buttons are held down, and which were pressed or released
+/// during this pass through the event loop.
+pub struct ButtonState<T> {
+    pressed: HashSet<T>,
+    just_pressed: HashSet<T>,
+    just_released: HashSet<T>,
+}
+
+impl<T> Default for ButtonState<T> {
+    fn default() -> Self {
+        Self {
+            pressed: HashSet::new(),
+            just_pressed: HashSet::new(),
+            just_released: HashSet::new(),
+        }
+    }
+}
+
+impl<T: Copy + Eq + Hash> ButtonState<T> {
+    /// Key repeats don't count as being pressed again.
+    pub fn update(&mut self, button: T, state: ElementState) {
+        match state {
+            ElementState::Pressed => {
+                if self.pressed.insert(button) {
+                    self.just_pressed.insert(button);
+                }
+            }
+            ElementState::Released => {
+                if self.pressed.remove(&button) {
+                    self.just_released.insert(button);
+                }
+            }
+        }
+    }
+
+    pub fn is_pressed(&self, button: T) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    pub fn just_pressed(&self, button: T) -> bool {
+        self.just_pressed.contains(&button)
+    }
+
+    pub fn just_released(&self, button: T) -> bool {
+        self.just_released.contains(&button)
+    }
+
+    fn release_all(&mut self) {
+        self.just_released.extend(self.pressed.drain());
+    }
+
+    fn reset_release_events(&mut self) {
+        self.just_pressed.clear();
+        self.just_released.clear();
+    }
+}
+
+/// A drag action being done with a mouse button held down.
+#[derive(Clone, Copy)]
+pub struct Drag {
+    pub action: Action,
+    pub button: MouseButton,
+    /// The window the button was pressed in, which gets the drag's events until the
+    /// button is released, even if the cursor leaves it.
+    pub window: WindowId,
+}
+
 pub struct MouseState {
-    pub lmb_pressed: bool,
-    /// True if the button has just been released. This should only be true for one pass
-    /// through the event loop-- as soon as the released is processed and handled, it is
-    /// set back to false.
-    pub lmb_released: bool,
-    pub cursor_pos_on_pressed: Option<Vector2>,
-    pub curr_cursor_pos: Vector2,
+    pub buttons: ButtonState<MouseButton>,
+    pub drag: Option<Drag>,
+    /// The drag whose button has just been released. This should only be set for one
+    /// pass through the event loop-- as soon as the release is processed and handled,
+    /// it is set back to `None`.
+    pub finished_drag: Option<Drag>,
+    /// How far the scroll wheel turned during this pass through the event loop, in
+    /// lines. y is positive when scrolling up, away from the user, and x when scrolling
+    /// right.
+    pub scroll_delta: Vector2,
+    /// Position of the cursor in the window it was last over, in physical pixels from
+    /// the top left corner.
+    pub window_cursor_pos: Vector2,
+    /// The window the cursor is over, if any.
+    pub cursor_window: Option<winit::window::WindowId>,
+    /// How far the mouse moved during this pass through the event loop, with y going
+    /// down. This is raw mouse movement, so it keeps counting when the cursor can't
+    /// move, e.g. because it is at the edge of the screen.
+    pub motion_delta: Vector2,
 }
 
 impl Default for MouseState {
     fn default() -> Self {
         Self {
-            lmb_pressed: false,
-            lmb_released: false,
-            cursor_pos_on_pressed: None,
-            curr_cursor_pos: Vector2::new(0., 0.),
+            buttons: ButtonState::default(),
+            drag: None,
+            finished_drag: None,
+            scroll_delta: Vector2::new(0., 0.),
+            window_cursor_pos: Vector2::new(0., 0.),
+            cursor_window: None,
+            motion_delta: Vector2::new(0., 0.),
         }
     }
 }
 
 impl MouseState {
+    /// Also resets the scrolling and mouse movement, which are only counted for one pass
+    /// through the event loop too.
     fn reset_release_events(&mut self) {
-        self.lmb_released = false;
+        self.buttons.reset_release_events();
+        self.finished_drag = None;
+        self.scroll_delta = Vector2::new(0., 0.);
+        self.motion_delta = Vector2::new(0., 0.);
     }
 }
 
 pub struct KeyboardState {
-    pub shift_pressed: bool,
-    pub shift_released: bool,
+    pub keys: ButtonState<VirtualKeyCode>,
 }
 
 impl Default for KeyboardState {
     fn default() -> Self {
         Self {
-            shift_pressed: false,
-            shift_released: false,
+            keys: ButtonState::default(),
         }
     }
 }
 
 impl KeyboardState {
-    fn reset_release_events(&mut self) {
-        self.shift_released = false;
+    fn either_pressed(&self, left: VirtualKeyCode, right: VirtualKeyCode) -> bool {
+        self.keys.is_pressed(left) || self.keys.is_pressed(right)
+    }
+
+    /// Either shift key.
+    pub fn shift(&self) -> bool {
+        self.either_pressed(VirtualKeyCode::LShift, VirtualKeyCode::RShift)
+    }
+
+    /// Either control key.
+    pub fn ctrl(&self) -> bool {
+        self.either_pressed(VirtualKeyCode::LControl, VirtualKeyCode::RControl)
+    }
+
+    /// Either alt key.
+    pub fn alt(&self) -> bool {
+        self.either_pressed(VirtualKeyCode::LAlt, VirtualKeyCode::RAlt)
+    }
+
+    /// Either super key, i.e. the Windows key, or command on macOS.
+    pub fn logo(&self) -> bool {
+        self.either_pressed(VirtualKeyCode::LWin, VirtualKeyCode::RWin)
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        Modifiers {
+            shift: self.shift(),
+            ctrl: self.ctrl(),
+            alt: self.alt(),
+            logo: self.logo(),
+        }
     }
 }
@@ -0,0 +1,166 @@
+//! Icons showing where the lights of the scene are in the 3D viewer and which way they
+//! shine. They are added to the gizmo's geometry, so they stay visible through objects.
+
+use crate::{
+    camera::ndc_to_window,
+    gizmo::GizmoGeometry,
+    scene::{SceneData, SceneLight, SceneLightKind},
+};
+
+/// Radius of the icons on screen, in pixels.
+const ICON_RADIUS: f32 = 10.0;
+/// How close the cursor has to be to the center of an icon, in pixels, to click it.
+const CLICK_RADIUS: f32 = 14.0;
+/// Directional lights have no position, so their icon is drawn this far from the
+/// origin, on the side the light comes from.
+const DIRECTIONAL_ICON_DISTANCE: f32 = 5.0;
+/// Length of the lines showing which way a light shines, in icon radii.
+const DIRECTION_LENGTH: f32 = 4.0;
+/// Spot light cones wider than this are drawn at this angle, since the cone's base
+/// gets infinitely wide as the angle approaches 90 degrees.
+const MAX_DRAWN_CONE_ANGLE: f32 = 1.4;
+const CIRCLE_SEGMENTS: usize = 24;
+
+const ICON_COLOR: glam::Vec4 = glam::Vec4::new(0.9, 0.9, 0.9, 1.0);
+const SELECTED_COLOR: glam::Vec4 = glam::Vec4::new(1.0, 0.85, 0.2, 1.0);
+
+fn icon_position(light: &SceneLight) -> glam::Vec3 {
+    light.kind.position().unwrap_or_else(|| {
+        let direction = light.kind.direction().unwrap_or(glam::Vec3::NEG_Y);
+        -direction.normalize_or_zero() * DIRECTIONAL_ICON_DISTANCE
+    })
+}
+
+/// The light whose icon is closest to `cursor`, given in window pixels, if any are
+/// close enough to click.
+pub fn light_at(scene: &SceneData, cursor: glam::Vec2, window_size: glam::Vec2) -> Option<usize> {
+    scene
+        .lights
+        .iter()
+        .enumerate()
+        .filter_map(|(index, light)| {
+            let center = scene.camera.project_point(icon_position(light))?;
+            Some((index, ndc_to_window(center, window_size).distance(cursor)))
+        })
+        .filter(|(_, distance)| *distance <= CLICK_RADIUS)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(index, _)| index)
+}
+
+/// Adds an icon for every light in `scene` to `geometry`, highlighting the light at
+/// index `selected`.
+pub fn add_light_icons(
+    geometry: &mut GizmoGeometry,
+    scene: &SceneData,
+    selected: Option<usize>,
+    window_size: glam::Vec2,
+) {
+    let camera = &scene.camera;
+    for (index, light) in scene.lights.iter().enumerate() {
+        let center = icon_position(light);
+        let Some(ndc) = camera.project_point(center) else {
+            continue;
+        };
+        let color = if selected == Some(index) {
+            SELECTED_COLOR
+        } else {
+            ICON_COLOR
+        };
+
+        // The circle faces the camera.
+        let (eye, _) = camera.get_ray_through(ndc);
+        let toward_camera = (eye - center).normalize_or_zero();
+        if toward_camera == glam::Vec3::ZERO {
+            continue;
+        }
+        let (u, v) = toward_camera.any_orthonormal_pair();
+        let radius = camera.world_size_of_pixels(center, ICON_RADIUS, window_size);
+        circle(geometry, center, u * radius, v * radius, color);
+
+        let length = radius * DIRECTION_LENGTH;
+        match light.kind {
+            SceneLightKind::Directional { direction, .. } => {
+                // Parallel rays, like sunlight.
+                let direction = direction.normalize_or_zero();
+                let side = direction.cross(toward_camera).normalize_or_zero() * radius * 0.6;
+                for offset in [-side, glam::Vec3::ZERO, side] {
+                    let start = center + offset;
+                    geometry.line(start, start + direction * length, color);
+                }
+            }
+            SceneLightKind::Point { .. } => {
+                // Rays in every direction.
+                for ray in 0..8 {
+                    let angle = ray as f32 / 8.0 * std::f32::consts::TAU;
+                    let out = u * angle.cos() + v * angle.sin();
+                    geometry.line(
+                        center + out * radius * 1.4,
+                        center + out * radius * 2.0,
+                        color,
+                    );
+                }
+            }
+            SceneLightKind::Spot {
+                direction,
+                outer_cone_angle,
+                ..
+            } => {
+                let direction = direction.normalize_or_zero();
+                if direction == glam::Vec3::ZERO {
+                    continue;
+                }
+                let base = center + direction * length;
+                let base_radius = length * outer_cone_angle.min(MAX_DRAWN_CONE_ANGLE).tan();
+                let (a, b) = direction.any_orthonormal_pair();
+                circle(geometry, base, a * base_radius, b * base_radius, color);
+                for rim in [a, b, -a, -b] {
+                    geometry.line(center, base + rim * base_radius, color);
+                }
+            }
+            SceneLightKind::Area {
+                position,
+                direction,
+                width,
+                height,
+            } => {
+                let direction = direction.normalize_or_zero();
+                if direction == glam::Vec3::ZERO {
+                    continue;
+                }
+                // Like glTF lights, area lights face down their -z axis.
+                let rotation = glam::Quat::from_rotation_arc(glam::Vec3::NEG_Z, direction);
+                let half_width = rotation * glam::Vec3::X * width * 0.5;
+                let half_height = rotation * glam::Vec3::Y * height * 0.5;
+                let corners = [
+                    position - half_width - half_height,
+                    position + half_width - half_height,
+                    position + half_width + half_height,
+                    position - half_width + half_height,
+                ];
+                for (a, b) in corners.iter().zip(corners.iter().cycle().skip(1)) {
+                    geometry.line(*a, *b, color);
+                }
+                geometry.line(position, position + direction * length, color);
+            }
+        }
+    }
+}
+
+/// A circle around `center`, with `u` and `v` as the radii along its two axes.
+fn circle(
+    geometry: &mut GizmoGeometry,
+    center: glam::Vec3,
+    u: glam::Vec3,
+    v: glam::Vec3,
+    color: glam::Vec4,
+) {
+    let points: Vec<glam::Vec3> = (0..CIRCLE_SEGMENTS)
+        .map(|segment| {
+            let angle = segment as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+            center + u * angle.cos() + v * angle.sin()
+        })
+        .collect();
+    for (a, b) in points.iter().zip(points.iter().cycle().skip(1)) {
+        geometry.line(*a, *b, color);
+    }
+}
@@ -1,19 +1,29 @@
 use std::collections::HashMap;
 
+use bindings::{Action, ActionMap};
 use config::UserConfig;
-use math::vector::Vector2;
+use input::WindowInput;
+use project::Project;
+use recording::{InputRecorder, InputReplay, RecordedEvent, Recording};
 use ui::windows::{
     node_map::NodeMapWindow, render::RenderWindow, scene_viewer_3d::SceneViewer3D,
     startup::StartupWindow, WindowLike,
 };
+use winit::event::ElementState;
 
 mod base;
+mod bindings;
 mod camera;
 mod config;
+mod gizmo;
 mod grid;
+mod history;
 mod input;
+mod light_icons;
 mod math;
 mod plugins;
+mod project;
+mod recording;
 mod scene;
 mod ui;
 
@@ -55,6 +65,22 @@ pub enum WindowRedrawCallbackCommand {
     Create3DWindowAndClose,
     CreateNodeMapWindowAndClose,
     CreateRenderWindowAndClose,
+    /// Gather the session from every window and write it to the given path.
+    SaveProject(std::path::PathBuf),
+    /// Replace the session with the project at the given path.
+    OpenProject(std::path::PathBuf),
+    /// Add a primitive to the scene of the first window which has one.
+    AddPrimitive(scene::primitives::Primitive),
+    /// Use these key and mouse bindings from now on, in every window.
+    SetBindings(ActionMap),
+    /// Record all input from now on, to be written to the given path.
+    StartRecordingInput(std::path::PathBuf),
+    StopRecordingInput,
+    /// Replay the input recorded at the given path into the window asking for it.
+    ReplayInput(std::path::PathBuf),
+    /// Start a render in the window asking for it, of the scene of the first window
+    /// which has one, for an image `width` by `height` pixels.
+    BeginRender { width: u32, height: u32 },
 }
 
 pub enum WindowCloseCallbackCommand {
@@ -63,7 +89,7 @@ pub enum WindowCloseCallbackCommand {
 }
 
 fn parse_user_config() -> UserConfig {
-    let config_file = std::fs::read_to_string("ekki_config.toml");
+    let config_file = std::fs::read_to_string(config::USER_CONFIG_PATH);
     if config_file.is_err() {
         return UserConfig::default();
     }
@@ -78,6 +104,85 @@ fn parse_user_config() -> UserConfig {
     parsed_config.unwrap()
 }
 
+/// Adds a window to the set of windows driven by the event loop, giving it the
+/// chance to pick up its part of the currently open project.
+fn add_window(
+    windows: &mut HashMap<winit::window::WindowId, Box<dyn WindowLike>>,
+    project: &mut Project,
+    bindings: &ActionMap,
+    mut window: Box<dyn WindowLike>,
+) {
+    if let Err(e) = window.load_from_project(project) {
+        log::error!("failed to load project into new window: {:#}", e);
+    }
+    window.set_bindings(bindings);
+    windows.insert(window.get_window_id(), window);
+}
+
+/// Closes the window `id`. What it owned of the project is kept in the project, for
+/// other windows to claim.
+fn remove_window(
+    windows: &mut HashMap<winit::window::WindowId, Box<dyn WindowLike>>,
+    project: &mut Project,
+    id: winit::window::WindowId,
+) {
+    if let Some(window) = windows.remove(&id) {
+        window.save_to_project(project);
+        project.release(id);
+    }
+}
+
+/// Does the actions input in `window` resulted in. Returns whether the render window
+/// should be opened, which is left to the caller since it owns the windows.
+fn do_actions(
+    window: &mut dyn WindowLike,
+    actions: Vec<(Action, ElementState)>,
+    input_state: &input::InputState,
+) -> bool {
+    let mut open_render_window = false;
+    for (action, state) in actions {
+        match (action, state) {
+            (Action::Undo, ElementState::Pressed) => window.undo(),
+            (Action::Redo, ElementState::Pressed) => window.redo(),
+            (Action::OpenRenderWindow, ElementState::Pressed) => open_render_window = true,
+            (action, ElementState::Pressed) => window.action_pressed(action, input_state),
+            (action, ElementState::Released) => window.action_released(action, input_state),
+        }
+    }
+    open_render_window
+}
+
+/// Sends every window the input events for this pass through the event loop, recording
+/// them if a recorder is given.
+fn send_input_events(
+    windows: &mut HashMap<winit::window::WindowId, Box<dyn WindowLike>>,
+    input_state: &mut input::InputState,
+    mut recorder: Option<&mut InputRecorder>,
+) {
+    for (window_id, w) in windows.iter_mut() {
+        let input_events = input_state.get_input_events(*window_id);
+        if let Some(recorder) = recorder.as_deref_mut() {
+            if !input_events.is_empty() {
+                recorder.record(RecordedEvent::InputEvents {
+                    window: (*window_id).into(),
+                    events: input_events.clone(),
+                });
+            }
+        }
+        for input_event in input_events {
+            w.handle_input_event(input_state, input_event)
+        }
+    }
+    input_state.reset_release_events();
+}
+
+fn finish_recording(recorder: InputRecorder) {
+    match recorder.finish() {
+        Ok(path) => log::info!("saved input recording to {}", path.display()),
+        Err(e) => log::error!("{:#}", e),
+    }
+}
+
 fn main() {
     // State
     let mut render_window_active = false;
@@ -89,6 +194,8 @@ fn main() {
     // Create event loop and window
     let event_loop = winit::event_loop::EventLoop::new();
     let mut input_state = input::InputState::default();
+    input_state.bindings = ActionMap::from_config(user_config.bindings.as_ref());
+    let mut project = Project::default();
 
     let mut windows: HashMap<winit::window::WindowId, Box<dyn WindowLike>> = HashMap::new();
     {
@@ -99,20 +206,37 @@ fn main() {
         match startup_window_kind {
             config::StartupWindowOption::Startup => {
                 let startup_window = StartupWindow::create(&event_loop);
-                windows.insert(startup_window.get_window_id(), Box::new(startup_window));
+                add_window(
+                    &mut windows,
+                    &mut project,
+                    &input_state.bindings,
+                    Box::new(startup_window),
+                );
             }
             config::StartupWindowOption::Render => {
                 let startup_window = RenderWindow::create(&event_loop, &user_config.render);
-                windows.insert(startup_window.get_window_id(), Box::new(startup_window));
+                add_window(
+                    &mut windows,
+                    &mut project,
+                    &input_state.bindings,
+                    Box::new(startup_window),
+                );
             }
         }
     }
 
+    let mut input_recorder: Option<InputRecorder> = None;
+    // The replay, and the window its input goes to.
+    let mut input_replay: Option<(InputReplay, winit::window::WindowId)> = None;
+
     // TODO: never cleared
     let mut recently_closed_windows = Vec::new();
 
     // Do event loop.
     event_loop.run(move |event, window_target, control| {
+        let mut had_input = false;
+        let mut open_render_window = false;
+
         match event {
             winit::event::Event::WindowEvent { window_id, event } => {
                 if recently_closed_windows.contains(&window_id) {
@@ -121,8 +245,27 @@ fn main() {
 
                 let this_window = windows.get_mut(&window_id).unwrap();
 
-                // Pass the window events to the egui integration.
-                if this_window.egui_event_consumed(&event) {
+                // Pass the window events to the egui integration. Releases, focus and the
+                // cursor crossing windows are tracked even if egui takes them, so that keys
+                // and buttons aren't left held down.
+                let consumed = this_window.egui_event_consumed(&event);
+                let input = WindowInput::from_window_event(&event)
+                    .filter(|input| !consumed || input.tracked_under_egui());
+                if let Some(input) = input {
+                    if let Some(recorder) = &mut input_recorder {
+                        recorder.record(RecordedEvent::WindowInput {
+                            window: window_id.into(),
+                            input: input.clone(),
+                        });
+                    }
+                    // Key and mouse input only go to the window they happened in, so
+                    // actions happen in whichever window the user is working in.
+                    let actions = input_state.handle_window_input(window_id, &input);
+                    open_render_window |=
+                        do_actions(this_window.as_mut(), actions, &input_state);
+                    had_input = true;
+                }
+                if consumed {
                     return;
                 }
 
@@ -131,12 +274,15 @@ fn main() {
                     winit::event::WindowEvent::CloseRequested => {
                         match this_window.close_requested() {
                             WindowCloseCallbackCommand::Close => {
-                                windows.remove(&window_id);
+                                remove_window(&mut windows, &mut project, window_id);
                                 recently_closed_windows.push(window_id);
                                 return;
                             }
 
                             WindowCloseCallbackCommand::QuitProgram => {
+                                if let Some(recorder) = input_recorder.take() {
+                                    finish_recording(recorder);
+                                }
                                 *control = winit::event_loop::ControlFlow::Exit;
                             }
                         }
@@ -146,60 +292,6 @@ fn main() {
                         this_window.resize(physical_size);
                     }
 
-                    winit::event::WindowEvent::KeyboardInput {
-                        device_id: _,
-                        input,
-                        is_synthetic: _,
-                    } => {
-                        let state = input.state;
-                        let keycode = input.virtual_keycode;
-
-                        if keycode == Some(winit::event::VirtualKeyCode::LShift) {
-                            match state {
-                                winit::event::ElementState::Pressed => {
-                                    input_state.keyboard.shift_pressed = true;
-                                }
-
-                                winit::event::ElementState::Released => {
-                                    input_state.keyboard.shift_pressed = false;
-                                    input_state.keyboard.shift_released = true;
-                                }
-                            }
-                        }
-
-                        if keycode == Some(winit::event::VirtualKeyCode::R) && !render_window_active
-                        {
-                            let new_window =
-                                RenderWindow::create(window_target, &user_config.render);
-                            windows.insert(new_window.get_window_id(), Box::new(new_window));
-                            render_window_active = true;
-                        }
-                    }
-
-                    winit::event::WindowEvent::MouseInput {
-                        device_id: _,
-                        state,
-                        button,
-                        ..
-                    } => {
-                        if button == winit::event::MouseButton::Left {
-                            match state {
-                                winit::event::ElementState::Pressed => {
-                                    input_state.mouse.lmb_pressed = true;
-                                    if input_state.mouse.cursor_pos_on_pressed.is_none() {
-                                        input_state.mouse.cursor_pos_on_pressed =
-                                            Some(input_state.mouse.curr_cursor_pos.clone());
-                                    }
-                                }
-                                winit::event::ElementState::Released => {
-                                    input_state.mouse.lmb_pressed = false;
-                                    input_state.mouse.lmb_released = true;
-                                    input_state.mouse.cursor_pos_on_pressed = None;
-                                }
-                            }
-                        }
-                    }
-
                     _ => {}
                 }
             }
@@ -209,14 +301,44 @@ fn main() {
                 event,
             } => match event {
                 winit::event::DeviceEvent::MouseMotion { delta } => {
-                    input_state.mouse.curr_cursor_pos +=
-                        Vector2::new(-delta.0 as f32, -delta.1 as f32);
+                    let delta = glam::Vec2::new(delta.0 as f32, delta.1 as f32);
+                    if let Some(recorder) = &mut input_recorder {
+                        recorder.record(RecordedEvent::MouseMotion(delta));
+                    }
+                    input_state.handle_mouse_motion(delta);
+                    had_input = true;
                 }
 
                 _ => {}
             },
 
             winit::event::Event::MainEventsCleared => {
+                // Replayed input goes to the window the replay was started from, and is
+                // handled a piece at a time like the real thing.
+                if let Some((replay, replay_window)) = &mut input_replay {
+                    for event in replay.due_events() {
+                        match event {
+                            RecordedEvent::WindowInput { input, .. } => {
+                                let actions =
+                                    input_state.handle_window_input(*replay_window, &input);
+                                if let Some(w) = windows.get_mut(replay_window) {
+                                    open_render_window |=
+                                        do_actions(w.as_mut(), actions, &input_state);
+                                }
+                            }
+                            RecordedEvent::MouseMotion(delta) => {
+                                input_state.handle_mouse_motion(delta)
+                            }
+                            RecordedEvent::InputEvents { .. } => {}
+                        }
+                        send_input_events(&mut windows, &mut input_state, None);
+                    }
+                    if replay.is_finished() || !windows.contains_key(replay_window) {
+                        log::info!("finished replaying input");
+                        input_replay = None;
+                    }
+                }
+
                 for w in windows.values_mut() {
                     w.request_redraw();
                 }
@@ -233,30 +355,141 @@ fn main() {
                     for callback in calls {
                         match callback {
                             WindowRedrawCallbackCommand::Create3DWindow => {
-                                let new_window = SceneViewer3D::create(window_target);
-                                windows.insert(new_window.get_window_id(), Box::new(new_window));
+                                let new_window =
+                                    SceneViewer3D::create(window_target, &user_config.viewport);
+                                add_window(
+                                    &mut windows,
+                                    &mut project,
+                                    &input_state.bindings,
+                                    Box::new(new_window),
+                                );
                             }
 
                             WindowRedrawCallbackCommand::Create3DWindowAndClose => {
-                                windows.remove(&id);
+                                remove_window(&mut windows, &mut project, id);
                                 recently_closed_windows.push(id);
-                                let new_window = SceneViewer3D::create(window_target);
-                                windows.insert(new_window.get_window_id(), Box::new(new_window));
+                                let new_window =
+                                    SceneViewer3D::create(window_target, &user_config.viewport);
+                                add_window(
+                                    &mut windows,
+                                    &mut project,
+                                    &input_state.bindings,
+                                    Box::new(new_window),
+                                );
                             }
 
                             WindowRedrawCallbackCommand::CreateNodeMapWindowAndClose => {
-                                windows.remove(&id);
+                                remove_window(&mut windows, &mut project, id);
                                 recently_closed_windows.push(id);
                                 let new_window = NodeMapWindow::create(window_target);
-                                windows.insert(new_window.get_window_id(), Box::new(new_window));
+                                add_window(
+                                    &mut windows,
+                                    &mut project,
+                                    &input_state.bindings,
+                                    Box::new(new_window),
+                                );
                             }
 
                             WindowRedrawCallbackCommand::CreateRenderWindowAndClose => {
-                                windows.remove(&id);
+                                remove_window(&mut windows, &mut project, id);
                                 recently_closed_windows.push(id);
                                 let new_window =
                                     RenderWindow::create(window_target, &user_config.render);
-                                windows.insert(new_window.get_window_id(), Box::new(new_window));
+                                add_window(
+                                    &mut windows,
+                                    &mut project,
+                                    &input_state.bindings,
+                                    Box::new(new_window),
+                                );
+                            }
+
+                            WindowRedrawCallbackCommand::SaveProject(path) => {
+                                for w in windows.values() {
+                                    w.save_to_project(&mut project);
+                                }
+
+                                match project.save(&path) {
+                                    Ok(()) => log::info!("saved project to {}", path.display()),
+                                    Err(e) => log::error!("{:#}", e),
+                                }
+                            }
+
+                            WindowRedrawCallbackCommand::OpenProject(path) => {
+                                match Project::load(&path) {
+                                    Ok(mut loaded) => {
+                                        // The same windows are open, and own the same
+                                        // parts of the project.
+                                        loaded.scene_owner = project.scene_owner;
+                                        project = loaded;
+                                        for w in windows.values_mut() {
+                                            if let Err(e) = w.load_from_project(&mut project) {
+                                                log::error!("failed to load project: {:#}", e);
+                                            }
+                                        }
+                                        log::info!("opened project {}", path.display());
+                                    }
+                                    Err(e) => log::error!("{:#}", e),
+                                }
+                            }
+
+                            WindowRedrawCallbackCommand::SetBindings(bindings) => {
+                                input_state.bindings = bindings;
+                                for w in windows.values_mut() {
+                                    w.set_bindings(&input_state.bindings);
+                                }
+                            }
+
+                            WindowRedrawCallbackCommand::StartRecordingInput(path) => {
+                                if let Some(recorder) = input_recorder.take() {
+                                    finish_recording(recorder);
+                                }
+                                input_recorder = Some(InputRecorder::new(path));
+                                log::info!("recording input");
+                            }
+
+                            WindowRedrawCallbackCommand::StopRecordingInput => {
+                                match input_recorder.take() {
+                                    Some(recorder) => finish_recording(recorder),
+                                    None => log::warn!("input isn't being recorded"),
+                                }
+                            }
+
+                            WindowRedrawCallbackCommand::ReplayInput(path) => {
+                                match Recording::load(&path) {
+                                    Ok(recording) => {
+                                        log::info!("replaying input from {}", path.display());
+                                        let window_count = recording.windows().len();
+                                        if window_count > 1 {
+                                            log::warn!(
+                                                "the recording has input from {} windows, \
+                                                 which all goes to this one",
+                                                window_count
+                                            );
+                                        }
+                                        input_replay = Some((InputReplay::new(recording), id));
+                                    }
+                                    Err(e) => log::error!("{:#}", e),
+                                }
+                            }
+
+                            WindowRedrawCallbackCommand::BeginRender { width, height } => {
+                                let scene =
+                                    windows.values().find_map(|w| w.plugin_scene(width, height));
+                                if scene.is_none() {
+                                    log::warn!("no 3D scene is open to render");
+                                }
+                                if let Some(w) = windows.get_mut(&id) {
+                                    w.begin_render(scene);
+                                }
+                            }
+
+                            WindowRedrawCallbackCommand::AddPrimitive(primitive) => {
+                                if !windows.values_mut().any(|w| w.add_primitive(primitive)) {
+                                    log::warn!(
+                                        "no 3D scene is open to add the {} to",
+                                        primitive.name()
+                                    );
+                                }
                             }
                         }
                     }
@@ -269,11 +502,19 @@ fn main() {
             _ => {}
         }
 
-        for w in windows.values_mut() {
-            for input_event in input_state.get_input_events() {
-                w.handle_input_event(&input_state, input_event)
-            }
+        if open_render_window && !render_window_active {
+            let new_window = RenderWindow::create(window_target, &user_config.render);
+            add_window(
+                &mut windows,
+                &mut project,
+                &input_state.bindings,
+                Box::new(new_window),
+            );
+            render_window_active = true;
         }
-        input_state.reset_release_events();
+
+        // Only passes with input are recorded, since those are the ones a replay has.
+        let recorder = input_recorder.as_mut().filter(|_| had_input);
+        send_input_events(&mut windows, &mut input_state, recorder);
     });
 }
@@ -74,6 +74,18 @@ impl Matrix4 {
             internal,        }
     }
 
+    /// An orthographic projection looking down +z, showing `height` units vertically
+    /// and `depth` units along z, centered on the origin.
+    pub fn create_orthographic(height: f32, aspect_ratio: f32, depth: f32) -> Self {
+        let half_width = height * aspect_ratio / 2.0;
+        let half_height = height / 2.0;
+        let half_depth = depth / 2.0;
+
+        Self {
+            internal: glam::Mat4::orthographic_lh(-half_width, half_width, -half_height, half_height, -half_depth, half_depth),
+        }
+    }
+
     /// Second column of matrix.
     pub fn y(&self) -> Vector4 {
         Vector4 {
@@ -79,6 +79,12 @@ impl Into<[f32; 3]> for Point3 {
     }
 }
 
+impl From<glam::Vec3> for Point3 {
+    fn from(v: glam::Vec3) -> Self {
+        Self { internal: v }
+    }
+}
+
 impl Point3 {
     pub fn new(x: f32, y: f32, z: f32) -> Self {
         Self {
@@ -97,4 +103,8 @@ impl Point3 {
             internal: self.internal,
         }
     }
+
+    pub fn to_glam_vec3(&self) -> glam::Vec3 {
+        self.internal
+    }
 }
@@ -33,6 +33,16 @@ impl Quaternion {
             internal: self.internal.normalize(),
         }
     }
+
+    pub fn to_glam_quat(&self) -> glam::Quat {
+        self.internal
+    }
+}
+
+impl From<glam::Quat> for Quaternion {
+    fn from(q: glam::Quat) -> Self {
+        Self { internal: q }
+    }
 }
 
 impl Mul for Quaternion {
@@ -93,6 +93,10 @@ impl Vector2 {
         self.internal.y
     }
 
+    pub fn to_glam_vec2(&self) -> glam::Vec2 {
+        self.internal
+    }
+
     pub fn normalize(self) -> Self {
         Self {
             internal: self.internal.normalize(),
@@ -0,0 +1,181 @@
+//! The interface renderer plugins implement, as seen from C.
+//!
+//! Every plugin exports `ekki_plugin_info`, which tells ekki what the plugin is and
+//! which ABI version it was built against:
+//!
+//! ```c
+//! typedef struct {
+//!     uint32_t struct_size;   /* sizeof(EkkiPluginInfo) */
+//!     uint32_t abi_version;   /* ABI_VERSION */
+//!     const char *name;
+//!     const char *version;
+//!     uint64_t capabilities;  /* PluginCapabilities bits */
+//! } EkkiPluginInfo;
+//!
+//! const EkkiPluginInfo *ekki_plugin_info(void);
+//! ```
+//!
+//! The info, and the strings in it, must stay valid while the plugin is loaded. The
+//! other entry points a plugin exports depend on its capabilities, so new ones can be
+//! added without breaking plugins which don't know about them.
+//!
+//! Plugins which can render export `ekki_render`, which ekki calls on a thread of its
+//! own and which returns once the render is done:
+//!
+//! ```c
+//! typedef struct {
+//!     uint32_t struct_size;     /* sizeof(EkkiRender) */
+//!     uint32_t image_width, image_height;
+//!     float *rgb_data;          /* 3 * image_width * image_height floats */
+//!     void *host;               /* passed back to the functions below */
+//!     bool (*preview_requested)(void *host);
+//!     void (*present)(void *host, const float *rgb_data);
+//!     void (*set_progress)(void *host, float progress);
+//! } EkkiRender;
+//!
+//! void ekki_render(const EkkiRender *render);
+//! ```
+//!
+//! `rgb_data` holds the RGB values of each pixel, in rows from top to bottom. Only the
+//! plugin touches it until `ekki_render` returns, after which ekki shows it as the
+//! finished image. When `preview_requested` returns true, the plugin passes the image
+//! so far to `present`, which copies it for ekki to show. The functions can be called
+//! from any thread while `ekki_render` runs.
+
+use std::ffi::{c_char, c_void, CStr};
+
+use anyhow::{anyhow, bail};
+
+use crate::scene::plugin_scene::RawScene;
+
+/// The ABI version plugins built against this version of ekki have.
+pub const ABI_VERSION: u32 = 2;
+/// The oldest ABI version ekki can still load plugins of. Version 1 plugins shared
+/// flags and the image with ekki without any synchronization.
+pub const MIN_SUPPORTED_ABI_VERSION: u32 = 2;
+
+pub const INFO_SYMBOL: &[u8] = b"ekki_plugin_info\0";
+/// Required with `PluginCapabilities::INCREMENTAL_RENDER`.
+pub const RENDER_SYMBOL: &[u8] = b"ekki_render\0";
+/// Required with `PluginCapabilities::SCENE`.
+pub const SET_SCENE_SYMBOL: &[u8] = b"ekki_set_scene\0";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PluginCapabilities(pub u64);
+
+impl PluginCapabilities {
+    /// The plugin exports `ekki_render`.
+    pub const INCREMENTAL_RENDER: Self = Self(1 << 0);
+    /// The plugin exports `ekki_set_scene`, and renders the scene it is given. See
+    /// `scene::plugin_scene` for what the scene looks like.
+    pub const SCENE: Self = Self(1 << 1);
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// The names of the capabilities this version of ekki knows about.
+    pub fn names(self) -> Vec<&'static str> {
+        let mut names = Vec::new();
+        if self.contains(Self::INCREMENTAL_RENDER) {
+            names.push("incremental render");
+        }
+        if self.contains(Self::SCENE) {
+            names.push("scene");
+        }
+        names
+    }
+}
+
+#[repr(C)]
+pub struct RawPluginInfo {
+    pub struct_size: u32,
+    pub abi_version: u32,
+    pub name: *const c_char,
+    pub version: *const c_char,
+    pub capabilities: u64,
+}
+
+pub type FnPluginInfo = unsafe extern "C" fn() -> *const RawPluginInfo;
+
+#[repr(C)]
+pub struct RawRender {
+    pub struct_size: u32,
+    pub image_width: u32,
+    pub image_height: u32,
+    pub rgb_data: *mut f32,
+    pub host: *mut c_void,
+    pub preview_requested: extern "C" fn(*mut c_void) -> bool,
+    pub present: extern "C" fn(*mut c_void, *const f32),
+    pub set_progress: extern "C" fn(*mut c_void, f32),
+}
+
+pub type FnRender = unsafe extern "C" fn(*const RawRender);
+
+/// Called on the render thread right before `ekki_render`, with the scene to render,
+/// or null if there is none.
+pub type FnSetScene = unsafe extern "C" fn(*const RawScene);
+
+/// What a plugin says about itself.
+#[derive(Clone, Debug)]
+pub struct PluginInfo {
+    pub name: String,
+    pub version: String,
+    pub abi_version: u32,
+    pub capabilities: PluginCapabilities,
+}
+
+impl PluginInfo {
+    /// Reads and checks the info a plugin returned from `ekki_plugin_info`.
+    ///
+    /// # Safety
+    /// `raw` must be null, or point to info which is at least `struct_size` bytes long,
+    /// with strings which are null or nul terminated. Info which is too short to be
+    /// read whole is rejected after only reading `struct_size`.
+    pub unsafe fn from_raw(raw: *const RawPluginInfo) -> anyhow::Result<Self> {
+        if raw.is_null() {
+            bail!("ekki_plugin_info returned null");
+        }
+        // Later ABI versions may add fields at the end, but none can be left out.
+        let struct_size = std::ptr::read(raw.cast::<u32>());
+        if (struct_size as usize) < std::mem::size_of::<RawPluginInfo>() {
+            bail!(
+                "its plugin info is {} bytes long, but should be at least {}",
+                struct_size,
+                std::mem::size_of::<RawPluginInfo>()
+            );
+        }
+        let raw = &*raw;
+
+        if raw.abi_version > ABI_VERSION {
+            bail!(
+                "it was built for plugin ABI version {}, but this version of ekki only \
+                 supports up to version {}; update ekki to use it",
+                raw.abi_version,
+                ABI_VERSION
+            );
+        }
+        if raw.abi_version < MIN_SUPPORTED_ABI_VERSION {
+            bail!(
+                "it was built for plugin ABI version {}, which this version of ekki no \
+                 longer supports; rebuild it against version {}",
+                raw.abi_version,
+                ABI_VERSION
+            );
+        }
+
+        let string = |ptr: *const c_char, field: &str| {
+            if ptr.is_null() {
+                Err(anyhow!("its plugin info has no {}", field))
+            } else {
+                Ok(CStr::from_ptr(ptr).to_string_lossy().into_owned())
+            }
+        };
+        Ok(Self {
+            name: string(raw.name, "name")?,
+            version: string(raw.version, "version")?,
+            abi_version: raw.abi_version,
+            capabilities: PluginCapabilities(raw.capabilities),
+        })
+    }
+}
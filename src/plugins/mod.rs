@@ -1,20 +1,165 @@
 use std::{
-    sync::{Arc, Mutex, RwLock},
+    ffi::c_void,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, Mutex, PoisonError,
+    },
     thread::{self, JoinHandle},
 };
 
+use anyhow::{bail, Context};
 use libloading;
 
+use crate::scene::plugin_scene::PluginScene;
+
+use abi::{FnPluginInfo, FnRender, FnSetScene, PluginCapabilities, PluginInfo, RawRender};
+
+pub mod abi;
+
+/// The functions ekki calls in a plugin. They are only valid while the plugin's library
+/// is loaded.
+#[derive(Clone, Copy)]
+struct EntryPoints {
+    render: FnRender,
+    /// For plugins with `PluginCapabilities::SCENE`.
+    set_scene: Option<FnSetScene>,
+}
+
+/// Loads the plugin at `path`, checking that this version of ekki can use it.
+fn open_plugin(
+    path: &std::ffi::OsStr,
+) -> anyhow::Result<(libloading::Library, PluginInfo, EntryPoints)> {
+    let library = unsafe { libloading::Library::new(path)? };
+
+    let info = unsafe {
+        let info_fn = library
+            .get::<FnPluginInfo>(abi::INFO_SYMBOL)
+            .with_context(|| {
+                format!(
+                    "{} is not an ekki plugin, or was built for an older version of ekki: it \
+                     has no ekki_plugin_info entry point",
+                    path.to_string_lossy()
+                )
+            })?;
+        PluginInfo::from_raw(info_fn())
+    }
+    .with_context(|| format!("can't load plugin {}", path.to_string_lossy()))?;
+
+    if !info
+        .capabilities
+        .contains(PluginCapabilities::INCREMENTAL_RENDER)
+    {
+        bail!(
+            "plugin {} {} can't do incremental renders",
+            info.name,
+            info.version
+        );
+    }
+    let render = unsafe {
+        *library
+            .get::<FnRender>(abi::RENDER_SYMBOL)
+            .with_context(|| {
+                format!(
+                    "plugin {} {} says it can do incremental renders, but has no \
+                     ekki_render entry point",
+                    info.name, info.version
+                )
+            })?
+    };
+    let set_scene = if info.capabilities.contains(PluginCapabilities::SCENE) {
+        let set_scene = unsafe {
+            *library
+                .get::<FnSetScene>(abi::SET_SCENE_SYMBOL)
+                .with_context(|| {
+                    format!(
+                        "plugin {} {} says it can render scenes, but has no ekki_set_scene \
+                         entry point",
+                        info.name, info.version
+                    )
+                })?
+        };
+        Some(set_scene)
+    } else {
+        None
+    };
+
+    let entry_points = EntryPoints { render, set_scene };
+    Ok((library, info, entry_points))
+}
+
+/// What ekki and the thread a render runs on share.
+struct RenderShared {
+    preview_requested: AtomicBool,
+    /// Set when a new image is presented, and cleared once it has been seen.
+    image_ready: AtomicBool,
+    /// The bits of the progress, which is an `f32`.
+    progress: AtomicU32,
+    /// How many floats the image has.
+    image_len: usize,
+    /// The last image the plugin presented. The plugin renders into a buffer of its own
+    /// and only copies the image here when asked to, so images are never read while
+    /// they are half written.
+    image: Mutex<Vec<f32>>,
+}
+
+impl RenderShared {
+    fn new(image_width: u32, image_height: u32) -> Self {
+        let image_len = (3 * image_width * image_height) as usize;
+        Self {
+            preview_requested: AtomicBool::new(false),
+            image_ready: AtomicBool::new(false),
+            progress: AtomicU32::new(0f32.to_bits()),
+            image_len,
+            image: Mutex::new(vec![1.; image_len]),
+        }
+    }
+
+    fn present(&self, rgb_data: &[f32]) {
+        self.image
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .copy_from_slice(rgb_data);
+        self.preview_requested.store(false, Ordering::Release);
+        self.image_ready.store(true, Ordering::Release);
+    }
+}
+
+/// The functions plugins call are handed the `RenderShared` of the render as `host`.
+///
+/// # Safety
+/// `host` must point to a `RenderShared` which outlives `'a`. The render thread keeps
+/// it alive until the plugin returns from `ekki_render`.
+unsafe fn shared_from_host<'a>(host: *mut c_void) -> &'a RenderShared {
+    &*(host as *const RenderShared)
+}
+
+extern "C" fn preview_requested(host: *mut c_void) -> bool {
+    let shared = unsafe { shared_from_host(host) };
+    shared.preview_requested.load(Ordering::Acquire)
+}
+
+extern "C" fn present(host: *mut c_void, rgb_data: *const f32) {
+    let shared = unsafe { shared_from_host(host) };
+    if rgb_data.is_null() {
+        return;
+    }
+    shared.present(unsafe { std::slice::from_raw_parts(rgb_data, shared.image_len) });
+}
+
+extern "C" fn set_progress(host: *mut c_void, progress: f32) {
+    let shared = unsafe { shared_from_host(host) };
+    shared.progress.store(progress.to_bits(), Ordering::Release);
+}
+
 pub struct RendererPlugin {
     path: std::ffi::OsString,
     library: Arc<libloading::Library>,
+    info: PluginInfo,
+    entry_points: EntryPoints,
     thread_handle: Option<JoinHandle<anyhow::Result<()>>>,
-    read_request: Arc<bool>,
-    ready_to_read: Arc<bool>,
     render_width: u32,
     render_height: u32,
-    render_rgb_data: Arc<Vec<f32>>,
-    render_progress: Arc<f32>,
+    shared: Arc<RenderShared>,
 }
 
 impl RendererPlugin {
@@ -23,28 +168,40 @@ impl RendererPlugin {
         render_width: u32,
         render_height: u32,
     ) -> anyhow::Result<Self> {
-        let library = unsafe { libloading::Library::new(path)? };
+        let (library, info, entry_points) = open_plugin(path)?;
+        log::info!(
+            "loaded plugin {} {} (ABI version {}, capabilities: {})",
+            info.name,
+            info.version,
+            info.abi_version,
+            info.capabilities.names().join(", ")
+        );
 
         Ok(Self {
             path: path.to_os_string(),
             library: Arc::new(library),
+            info,
+            entry_points,
             thread_handle: None,
-            ready_to_read: Arc::new(false),
-            read_request: Arc::new(false),
             render_width,
             render_height,
-            render_rgb_data: Arc::new(vec![1.; (3 * render_width * render_height) as usize]),
-            render_progress: Arc::new(0.),
+            shared: Arc::new(RenderShared::new(render_width, render_height)),
         })
     }
 
     pub fn reload(&mut self) -> anyhow::Result<()> {
-        let mut new_lib = unsafe { Arc::new(libloading::Library::new(&self.path)?) };
+        let (library, info, entry_points) = open_plugin(&self.path)?;
 
-        std::mem::swap(&mut self.library, &mut new_lib);
+        self.library = Arc::new(library);
+        self.info = info;
+        self.entry_points = entry_points;
         Ok(())
     }
 
+    pub fn info(&self) -> &PluginInfo {
+        &self.info
+    }
+
     pub fn join_thread(&mut self) {
         let handle = std::mem::replace(&mut self.thread_handle, None);
         let _ = handle.unwrap().join();
@@ -58,96 +215,83 @@ impl RendererPlugin {
         false
     }
 
+    /// Asks the plugin for the image so far. `poll_read_request()` tells when it is
+    /// there.
     pub fn request_read(&mut self) {
-        let read_request = Arc::as_ptr(&self.read_request).cast_mut();
-        unsafe {
-            *read_request = true;
-        }
+        self.shared.preview_requested.store(true, Ordering::Release);
     }
 
+    /// Whether a new image has come in since the last time this returned true.
     pub fn poll_read_request(&mut self) -> bool {
-        if *self.ready_to_read {
-            let read_request = Arc::as_ptr(&self.read_request).cast_mut();
-            let ready_to_read = Arc::as_ptr(&self.ready_to_read).cast_mut();
-
-            unsafe {
-                *read_request = false;
-                *ready_to_read = false;
-            }
-
-            return true;
-        }
-
-        false
+        self.shared.image_ready.swap(false, Ordering::AcqRel)
     }
 
     pub fn get_render_progress(&self) -> f32 {
-        *self.render_progress
+        f32::from_bits(self.shared.progress.load(Ordering::Acquire))
     }
 
     /// Starts an incremental render. This spins up the plugin on another thread and then
     /// returns (without waiting for the plugin to finish rendering).
     ///
-    /// Communication with the plugin is achieved by giving the plugin access to pointers
-    /// whose memory is owned by the system. To achieve synchronization, a few flags are
-    /// available to be set. Ultimately, it is the responsibility of the plugin (and the
-    /// program) to properly update these flags and use them appropriately.
-    ///
-    /// ## Parameters:
-    /// - `read_request`: the program sets this to `true` when it wants to read the
-    /// current state of the render from `rgb_data`. The plugin should detect this and
-    /// complete the necessary tasks for the incremental render to be read. The program
-    /// should set this back to `false` when it is done reading the data.
-    /// - `ready_to_read`: the plugin sets this to `true` to indicate that the program is
-    /// free to read the data in `rgb_data`. When the program is done reading the data, it
-    /// is responsible for setting this flag back to `false`.
-    /// - `image_width`: width of the rendering surface in pixels
-    /// - `image_height`: height of the rendering surface in pixels
-    /// - `rgb_data`: the data for the render, represented as a vector with capacity (at
-    /// least) `3*image_width*image_height`. The data is expected to be laid out as follows:
-    /// the first `3*image_width` values correspond to the topmost row of pixels of the
-    /// image, from left to right, divided into triples (0,1,2), (3,4,5), ... representing
-    /// the RGB values of the pixel, e.g. the RGB values of the pixel in the top row,
-    /// second column from the left are the values in indices (3,4,5) of the returned vector.
-    /// **The program is responsible for keeping the data valid.**
+    /// The plugin renders into a buffer which only its thread touches, and copies it
+    /// into the image ekki shows through the functions it is given, which are safe to
+    /// call from any thread. See `abi` for how the plugin sees this. Once the render
+    /// is done, the finished image is shown.
     ///
-    /// ## Returns
-    /// - A `JoinHandle` to the spawned thread which called the render routine. The program
-    /// can, for example, call `is_finished()` on this to see if the rendering is done.
-    pub fn begin_incremental_render(&mut self) {
-        let read_request_threaddata = self.read_request.clone();
-        let ready_to_read_threaddata = self.ready_to_read.clone();
-        let rgb_data_threaddata = self.render_rgb_data.clone();
+    /// Plugins which render scenes are given `scene` first, on the same thread. It is
+    /// `None` if there is no scene to render.
+    pub fn begin_incremental_render(&mut self, scene: Option<PluginScene>) {
         let image_width = self.render_width;
         let image_height = self.render_height;
-        let progress = self.render_progress.clone();
+        // Earlier renders which are still running keep their own.
+        self.shared = Arc::new(RenderShared::new(image_width, image_height));
+        let shared = self.shared.clone();
 
+        // Keeps the library loaded for as long as the render runs.
         let lib_thread = self.library.clone();
-        unsafe {
-            self.thread_handle = Some(thread::spawn(move || -> anyhow::Result<()> {
-                let read_request_param = Arc::as_ptr(&read_request_threaddata).cast_mut();
-                let ready_to_read_param = Arc::as_ptr(&ready_to_read_threaddata).cast_mut();
-                let progress_param = Arc::as_ptr(&progress).cast_mut();
-
-                let rgb_data_param = (*Arc::as_ptr(&rgb_data_threaddata).cast_mut()).as_mut_ptr();
-
-                let symbol: libloading::Symbol<FnBeginIncrementalRender> =
-                    lib_thread.get(b"begin_incremental_render\0")?;
-                (symbol)(
-                    read_request_param,
-                    ready_to_read_param,
-                    image_width,
-                    image_height,
-                    rgb_data_param,
-                    progress_param,
-                );
+        let entry_points = self.entry_points;
+        self.thread_handle = Some(thread::spawn(move || -> anyhow::Result<()> {
+            let _library = lib_thread;
+            if let Some(set_scene) = entry_points.set_scene {
+                // SAFETY: The plugin was loaded with `PluginCapabilities::SCENE`, so per
+                // the ABI `ekki_set_scene` takes a scene which is only valid during the
+                // call, or null, and `_library` keeps it loaded.
+                unsafe {
+                    match &scene {
+                        Some(scene) => scene.with_raw(|raw| (set_scene)(raw)),
+                        None => (set_scene)(std::ptr::null()),
+                    }
+                }
+            }
 
-                Ok(())
-            }));
-        }
+            let mut rgb_data = vec![1.; (3 * image_width * image_height) as usize];
+            let render = RawRender {
+                struct_size: std::mem::size_of::<RawRender>() as u32,
+                image_width,
+                image_height,
+                rgb_data: rgb_data.as_mut_ptr(),
+                host: Arc::as_ptr(&shared) as *mut c_void,
+                preview_requested,
+                present,
+                set_progress,
+            };
+            // SAFETY: Per the ABI, `ekki_render` only writes to `rgb_data` until it
+            // returns, and only reaches `shared` through the functions it is given, which
+            // can be called from any thread. Both outlive the call, and `_library` keeps
+            // the plugin loaded.
+            unsafe { (entry_points.render)(&render) };
+            shared.present(&rgb_data);
+
+            Ok(())
+        }));
     }
 
     pub fn convert_rgb_data_to_egui_image(&self) -> egui::ColorImage {
+        let rgb_data = self
+            .shared
+            .image
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
         let mut colors = vec![
             egui::Color32::from_rgb(255, 255, 255);
             (self.render_width * self.render_height) as usize
@@ -158,9 +302,9 @@ impl RendererPlugin {
                 let start_idx = (3 * x + 3 * y * self.render_width) as usize;
 
                 let color = egui::Color32::from_rgb(
-                    (self.render_rgb_data.get(start_idx).unwrap() * 255.999) as u8,
-                    (self.render_rgb_data.get(start_idx + 1).unwrap() * 255.999) as u8,
-                    (self.render_rgb_data.get(start_idx + 2).unwrap() * 255.999) as u8,
+                    (rgb_data.get(start_idx).unwrap() * 255.999) as u8,
+                    (rgb_data.get(start_idx + 1).unwrap() * 255.999) as u8,
+                    (rgb_data.get(start_idx + 2).unwrap() * 255.999) as u8,
                 );
 
                 let idx = (x + y * self.render_width) as usize;
@@ -174,12 +318,3 @@ impl RendererPlugin {
         }
     }
 }
-
-type FnBeginIncrementalRender = extern "C" fn(
-    *mut bool,              // read_request
-    *mut bool,              // ready_to_read
-    std::ffi::c_uint,       // image_width
-    std::ffi::c_uint,       // image_height
-    *mut std::ffi::c_float, // rgb_data
-    *mut std::ffi::c_float, // progress
-);
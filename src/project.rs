@@ -0,0 +1,117 @@
+//! On-disk format for saving and loading whole ekki sessions.
+//!
+//! A project is a versioned JSON document. Each window contributes the parts of the
+//! session it owns (see `WindowLike::save_to_project()`), and takes them back out when
+//! a project is opened (see `WindowLike::load_from_project()`). Parts that no open
+//! window claims stay in the project, so they survive being saved again.
+
+use std::path::Path;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use winit::window::WindowId;
+
+use crate::scene::SceneDescription;
+
+pub const PROJECT_FILE_EXTENSION: &str = "ekki";
+
+/// Bump this whenever the format changes in a way older versions of ekki can't read.
+/// Additive changes should instead use `#[serde(default)]` so older files still load.
+pub const PROJECT_FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+pub struct Project {
+    pub version: u32,
+    pub scene: Option<SceneDescription>,
+    /// The node graph types belong to the node map window, so the graph is stored
+    /// untyped here and converted by that window.
+    pub node_graph: Option<serde_json::Value>,
+    /// The 3D viewer the scene belongs to, see `claim_scene()`. Other viewers have
+    /// scenes of their own, which aren't saved.
+    #[serde(skip)]
+    pub scene_owner: Option<WindowId>,
+}
+
+impl Default for Project {
+    fn default() -> Self {
+        Self {
+            version: PROJECT_FORMAT_VERSION,
+            scene: None,
+            node_graph: None,
+            scene_owner: None,
+        }
+    }
+}
+
+impl Project {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read project file {}", path.display()))?;
+
+        // Check the version before deserializing the whole thing, so that files from a
+        // newer ekki give a useful error instead of a confusing parse failure.
+        let value: serde_json::Value = serde_json::from_str(&contents)
+            .with_context(|| format!("{} is not a valid project file", path.display()))?;
+        let version = value
+            .get("version")
+            .and_then(|v| v.as_u64())
+            .with_context(|| format!("{} has no project format version", path.display()))?;
+        if version > PROJECT_FORMAT_VERSION as u64 {
+            anyhow::bail!(
+                "{} uses project format version {}, but this version of ekki only supports up to {}",
+                path.display(),
+                version,
+                PROJECT_FORMAT_VERSION
+            );
+        }
+
+        let project: Self = serde_json::from_value(value)
+            .with_context(|| format!("failed to parse project file {}", path.display()))?;
+
+        Ok(project)
+    }
+
+    /// Returns whether the scene belongs to the 3D viewer `window`. The first viewer to
+    /// ask gets it, and keeps it until it is closed.
+    pub fn claim_scene(&mut self, window: WindowId) -> bool {
+        *self.scene_owner.get_or_insert(window) == window
+    }
+
+    /// Gives up what the closed `window` owned, so that other windows can claim it.
+    pub fn release(&mut self, window: WindowId) {
+        if self.scene_owner == Some(window) {
+            self.scene_owner = None;
+        }
+    }
+
+    pub fn save(&mut self, path: &Path) -> anyhow::Result<()> {
+        self.version = PROJECT_FORMAT_VERSION;
+
+        let contents = serde_json::to_string(self)?;
+        std::fs::write(path, contents)
+            .with_context(|| format!("failed to write project file {}", path.display()))?;
+
+        Ok(())
+    }
+}
+
+/// Asks the user where to save the project. Adds the project extension if the user
+/// didn't type one.
+pub fn pick_save_path() -> Option<std::path::PathBuf> {
+    rfd::FileDialog::new()
+        .add_filter("ekki project", &[PROJECT_FILE_EXTENSION])
+        .save_file()
+        .map(|path| {
+            if path.extension().is_none() {
+                path.with_extension(PROJECT_FILE_EXTENSION)
+            } else {
+                path
+            }
+        })
+}
+
+pub fn pick_open_path() -> Option<std::path::PathBuf> {
+    rfd::FileDialog::new()
+        .add_filter("ekki project", &[PROJECT_FILE_EXTENSION])
+        .pick_file()
+}
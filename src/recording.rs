@@ -0,0 +1,352 @@
+//! Recording the input the event loop gets, and replaying it, to reproduce bugs.
+//!
+//! A recording holds the input windows got, in the form the input state takes it, along
+//! with the input events each window was sent in response. It can be replayed into the
+//! running app, or onto a camera without any windows with `HeadlessViewport`, so that
+//! where the camera ends up can be checked in tests.
+
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::input::{InputEvent, WindowInput};
+
+pub const RECORDING_FILE_EXTENSION: &str = "json";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum RecordedEvent {
+    /// Windows are told apart by their winit ids, which differ between runs.
+    WindowInput { window: u64, input: WindowInput },
+    /// Raw mouse movement, which doesn't belong to any window.
+    MouseMotion(glam::Vec2),
+    /// What a window was sent after the input before this was handled.
+    InputEvents {
+        window: u64,
+        events: Vec<InputEvent>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TimedEvent {
+    /// When the event happened, since the recording started.
+    pub time: Duration,
+    pub event: RecordedEvent,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Recording {
+    pub events: Vec<TimedEvent>,
+}
+
+impl Recording {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read input recording {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("{} is not a valid input recording", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let contents = serde_json::to_string(self)?;
+        std::fs::write(path, contents)
+            .with_context(|| format!("failed to write input recording {}", path.display()))
+    }
+
+    /// The windows input was recorded in, in the order they first got any.
+    pub fn windows(&self) -> Vec<u64> {
+        let mut windows = Vec::new();
+        for event in &self.events {
+            if let RecordedEvent::WindowInput { window, .. } = event.event {
+                if !windows.contains(&window) {
+                    windows.push(window);
+                }
+            }
+        }
+        windows
+    }
+}
+
+/// Records input as it happens, until it is written to `path` with `finish()`.
+pub struct InputRecorder {
+    path: PathBuf,
+    start: Instant,
+    recording: Recording,
+}
+
+impl InputRecorder {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            start: Instant::now(),
+            recording: Recording::default(),
+        }
+    }
+
+    pub fn record(&mut self, event: RecordedEvent) {
+        self.recording.events.push(TimedEvent {
+            time: self.start.elapsed(),
+            event,
+        });
+    }
+
+    pub fn finish(self) -> anyhow::Result<PathBuf> {
+        self.recording.save(&self.path)?;
+        Ok(self.path)
+    }
+}
+
+/// Feeds the input of a recording back in, at the pace it was recorded.
+pub struct InputReplay {
+    start: Instant,
+    events: VecDeque<TimedEvent>,
+}
+
+impl InputReplay {
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            start: Instant::now(),
+            events: recording.events.into(),
+        }
+    }
+
+    /// The recorded input which is due by now. The input events sent in response are
+    /// left out, since replaying the input sends them again.
+    pub fn due_events(&mut self) -> Vec<RecordedEvent> {
+        let elapsed = self.start.elapsed();
+        let mut due = Vec::new();
+        while let Some(event) = self.events.front() {
+            if event.time > elapsed {
+                break;
+            }
+            let event = self.events.pop_front().unwrap().event;
+            if !matches!(event, RecordedEvent::InputEvents { .. }) {
+                due.push(event);
+            }
+        }
+        due
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+/// Asks the user where to save the recording. Adds the extension if the user didn't
+/// type one.
+pub fn pick_save_path() -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .add_filter("input recording", &[RECORDING_FILE_EXTENSION])
+        .save_file()
+        .map(|path| {
+            if path.extension().is_none() {
+                path.with_extension(RECORDING_FILE_EXTENSION)
+            } else {
+                path
+            }
+        })
+}
+
+pub fn pick_open_path() -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .add_filter("input recording", &[RECORDING_FILE_EXTENSION])
+        .pick_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use winit::{event::MouseButton, window::WindowId};
+
+    use super::*;
+    use crate::{
+        bindings::Trigger,
+        camera::{Camera, CameraNavigation, RotationStyle},
+        input::InputState,
+    };
+
+    /// Moves a camera for recorded input the way the 3D viewer does, without a window.
+    /// Input is replayed as fast as it can be, rather than at the pace it was recorded.
+    struct HeadlessViewport {
+        input_state: InputState,
+        camera: Camera,
+        navigation: CameraNavigation,
+        window_size: glam::Vec2,
+    }
+
+    impl HeadlessViewport {
+        fn new(window_size: glam::Vec2) -> Self {
+            Self {
+                input_state: InputState::default(),
+                camera: Camera::initialize(window_size.x, window_size.y),
+                navigation: CameraNavigation::new(RotationStyle::Turntable, true),
+                window_size,
+            }
+        }
+
+        /// Replays all of `recording`, treating the input `window` got as the viewport's.
+        /// Returns the input events the viewport was sent, which can be compared with the
+        /// recorded ones. Like the event loop, every piece of input is handled in a pass of
+        /// its own.
+        fn replay(&mut self, recording: &Recording, window: u64) -> Vec<InputEvent> {
+            let window_id = WindowId::from(window);
+            let mut sent = Vec::new();
+            for event in &recording.events {
+                match &event.event {
+                    RecordedEvent::WindowInput { window, input } => {
+                        self.input_state
+                            .handle_window_input(WindowId::from(*window), input);
+                    }
+                    RecordedEvent::MouseMotion(delta) => {
+                        self.input_state.handle_mouse_motion(*delta)
+                    }
+                    RecordedEvent::InputEvents { .. } => continue,
+                }
+
+                for input_event in self.input_state.get_input_events(window_id) {
+                    self.handle_input_event(input_event);
+                    sent.push(input_event);
+                }
+                self.input_state.reset_release_events();
+            }
+            sent
+        }
+
+        /// The input events the viewport was sent while `recording` was made.
+        fn recorded_input_events(recording: &Recording, window: u64) -> Vec<InputEvent> {
+            recording
+                .events
+                .iter()
+                .filter_map(|event| match &event.event {
+                    RecordedEvent::InputEvents {
+                        window: events_window,
+                        events,
+                    } if *events_window == window => Some(events.clone()),
+                    _ => None,
+                })
+                .flatten()
+                .collect()
+        }
+
+        fn handle_input_event(&mut self, input_event: InputEvent) {
+            let cursor = self.input_state.mouse.window_cursor_pos.to_glam_vec2();
+            match input_event {
+                InputEvent::DoViewportOrbit => {
+                    self.navigation
+                        .orbit(&mut self.camera, cursor, self.window_size);
+                }
+                InputEvent::FinishViewportOrbit => self.navigation.finish_orbit(&mut self.camera),
+                InputEvent::DoViewportPan => {
+                    self.navigation
+                        .pan(&mut self.camera, cursor, self.window_size);
+                }
+                InputEvent::FinishViewportPan => self.navigation.finish_pan(),
+                InputEvent::ViewportZoom => self.navigation.zoom(
+                    &mut self.camera,
+                    self.input_state.mouse.scroll_delta.y(),
+                    cursor,
+                    self.window_size,
+                ),
+                // Selecting needs a scene, and looking around only does something while
+                // flying, which is timed by the clock.
+                InputEvent::DoSelectionDrag
+                | InputEvent::FinishSelection
+                | InputEvent::ViewportLook => {}
+            }
+        }
+    }
+
+    /// A recording of `inputs`, each made in the window given with it and followed by
+    /// the input events that window was sent.
+    fn recording(inputs: Vec<(u64, WindowInput, Vec<InputEvent>)>) -> Recording {
+        let mut events = Vec::new();
+        for (index, (window, input, input_events)) in inputs.into_iter().enumerate() {
+            let time = Duration::from_millis(10 * index as u64);
+            events.push(TimedEvent {
+                time,
+                event: RecordedEvent::WindowInput { window, input },
+            });
+            if !input_events.is_empty() {
+                events.push(TimedEvent {
+                    time,
+                    event: RecordedEvent::InputEvents {
+                        window,
+                        events: input_events,
+                    },
+                });
+            }
+        }
+        Recording { events }
+    }
+
+    fn middle_mouse(pressed: bool) -> WindowInput {
+        WindowInput::Button {
+            trigger: Trigger::Mouse(MouseButton::Middle),
+            pressed,
+        }
+    }
+
+    #[test]
+    fn replay_moves_the_camera_like_the_recorded_input() {
+        let viewport = 2;
+        let recording = recording(vec![
+            (
+                1,
+                WindowInput::CursorMoved(glam::Vec2::new(10.0, 10.0)),
+                vec![],
+            ),
+            (
+                viewport,
+                WindowInput::CursorMoved(glam::Vec2::new(100.0, 100.0)),
+                vec![],
+            ),
+            (
+                viewport,
+                middle_mouse(true),
+                vec![InputEvent::DoViewportPan],
+            ),
+            (
+                viewport,
+                WindowInput::CursorMoved(glam::Vec2::new(150.0, 120.0)),
+                vec![InputEvent::DoViewportPan],
+            ),
+            (
+                viewport,
+                middle_mouse(false),
+                vec![InputEvent::FinishViewportPan],
+            ),
+            (
+                viewport,
+                WindowInput::Scroll(glam::Vec2::new(0.0, 2.0)),
+                vec![InputEvent::ViewportZoom],
+            ),
+        ]);
+        assert_eq!(recording.windows(), vec![1, viewport]);
+
+        let window_size = glam::Vec2::new(800.0, 600.0);
+        let mut headless = HeadlessViewport::new(window_size);
+        let sent = headless.replay(&recording, viewport);
+        assert_eq!(
+            sent,
+            HeadlessViewport::recorded_input_events(&recording, viewport)
+        );
+
+        // The same moves, made on the camera directly.
+        let mut camera = Camera::initialize(window_size.x, window_size.y);
+        let initial_state = camera.get_state();
+        let mut navigation = CameraNavigation::new(RotationStyle::Turntable, true);
+        let cursor = glam::Vec2::new(150.0, 120.0);
+        navigation.pan(&mut camera, glam::Vec2::new(100.0, 100.0), window_size);
+        navigation.pan(&mut camera, cursor, window_size);
+        navigation.finish_pan();
+        navigation.zoom(&mut camera, 2.0, cursor, window_size);
+
+        assert_ne!(camera.get_state(), initial_state);
+        assert_eq!(headless.camera.get_state(), camera.get_state());
+    }
+}
@@ -0,0 +1,225 @@
+//! Undoable edits to a `SceneData`, recorded in a `History` after they are made.
+
+use std::sync::Arc;
+
+use crate::{camera::CameraState, history::Command};
+
+use super::{
+    graph::{NodeId, RemovedNodes, Transform},
+    CameraBookmark, SceneCamera, SceneCameras, SceneData, SceneLight, SceneMaterial, SelectionMode,
+};
+
+/// What scene commands are undone and redone on.
+pub struct SceneTarget<'a> {
+    pub scene: &'a mut SceneData,
+    pub rend3_renderer: &'a Arc<rend3::Renderer>,
+}
+
+pub enum SceneCommand {
+    /// The node `id` and its descendants were added. Holds on to them while undone.
+    AddNode {
+        id: NodeId,
+        removed: Option<RemovedNodes>,
+    },
+    /// The light at `index` was added. Holds on to it while undone.
+    AddLight {
+        index: usize,
+        removed: Option<SceneLight>,
+    },
+    SetTransform {
+        id: NodeId,
+        before: Transform,
+        after: Transform,
+    },
+    SetName {
+        id: NodeId,
+        before: String,
+        after: String,
+    },
+    SetVisible {
+        id: NodeId,
+        before: bool,
+        after: bool,
+    },
+    SetMaterial {
+        id: NodeId,
+        before: SceneMaterial,
+        after: SceneMaterial,
+    },
+    SetLight {
+        index: usize,
+        before: SceneLight,
+        after: SceneLight,
+    },
+    Select {
+        before: Vec<NodeId>,
+        after: Vec<NodeId>,
+    },
+    /// The viewport camera moved.
+    SetCamera {
+        before: CameraState,
+        after: CameraState,
+    },
+    SetBookmarks {
+        before: Vec<CameraBookmark>,
+        after: Vec<CameraBookmark>,
+    },
+    SetSceneCameras {
+        before: SceneCameras,
+        after: SceneCameras,
+    },
+}
+
+impl SceneCommand {
+    pub fn add_node(id: NodeId) -> Self {
+        Self::AddNode { id, removed: None }
+    }
+
+    pub fn add_light(index: usize) -> Self {
+        Self::AddLight {
+            index,
+            removed: None,
+        }
+    }
+}
+
+fn set_camera(target: &mut SceneTarget, state: &CameraState) {
+    target.scene.camera.set_state(state);
+    target
+        .rend3_renderer
+        .set_camera_data(target.scene.camera.to_rend3_camera());
+}
+
+impl Command for SceneCommand {
+    type Target<'a> = SceneTarget<'a>;
+
+    fn undo(&mut self, target: &mut SceneTarget) {
+        match self {
+            Self::AddNode { id, removed } => {
+                *removed = Some(target.scene.remove_node(*id));
+            }
+            Self::AddLight { index, removed } => {
+                *removed = Some(target.scene.remove_light(*index));
+            }
+            Self::SetTransform { id, before, .. } => {
+                target
+                    .scene
+                    .set_transform(*id, *before, target.rend3_renderer);
+            }
+            Self::SetName { id, before, .. } => target.scene.set_name(*id, before.clone()),
+            Self::SetVisible { id, before, .. } => {
+                target
+                    .scene
+                    .set_visible(*id, *before, target.rend3_renderer);
+            }
+            Self::SetMaterial { id, before, .. } => {
+                target
+                    .scene
+                    .set_material(*id, before.clone(), target.rend3_renderer);
+            }
+            Self::SetLight { index, before, .. } => {
+                target
+                    .scene
+                    .set_light(*index, before.clone(), target.rend3_renderer);
+            }
+            Self::Select { before, .. } => {
+                target
+                    .scene
+                    .select(before, SelectionMode::Replace, target.rend3_renderer);
+            }
+            Self::SetCamera { before, .. } => set_camera(target, before),
+            Self::SetBookmarks { before, .. } => target.scene.bookmarks = before.clone(),
+            Self::SetSceneCameras { before, .. } => target.scene.cameras = before.clone(),
+        }
+    }
+
+    fn redo(&mut self, target: &mut SceneTarget) {
+        match self {
+            Self::AddNode { removed, .. } => {
+                if let Some(removed) = removed.take() {
+                    target.scene.restore_nodes(removed, target.rend3_renderer);
+                }
+            }
+            Self::AddLight { index, removed } => {
+                if let Some(light) = removed.take() {
+                    target
+                        .scene
+                        .insert_light(*index, light, target.rend3_renderer);
+                }
+            }
+            Self::SetTransform { id, after, .. } => {
+                target
+                    .scene
+                    .set_transform(*id, *after, target.rend3_renderer);
+            }
+            Self::SetName { id, after, .. } => target.scene.set_name(*id, after.clone()),
+            Self::SetVisible { id, after, .. } => {
+                target.scene.set_visible(*id, *after, target.rend3_renderer);
+            }
+            Self::SetMaterial { id, after, .. } => {
+                target
+                    .scene
+                    .set_material(*id, after.clone(), target.rend3_renderer);
+            }
+            Self::SetLight { index, after, .. } => {
+                target
+                    .scene
+                    .set_light(*index, after.clone(), target.rend3_renderer);
+            }
+            Self::Select { after, .. } => {
+                target
+                    .scene
+                    .select(after, SelectionMode::Replace, target.rend3_renderer);
+            }
+            Self::SetCamera { after, .. } => set_camera(target, after),
+            Self::SetBookmarks { after, .. } => target.scene.bookmarks = after.clone(),
+            Self::SetSceneCameras { after, .. } => target.scene.cameras = after.clone(),
+        }
+    }
+
+    fn memory_size(&self) -> usize {
+        let held = match self {
+            Self::AddNode { removed, .. } => removed.as_ref().map_or(0, RemovedNodes::memory_size),
+            Self::AddLight { removed, .. } => removed.as_ref().map_or(0, |light| light.name.len()),
+            Self::SetName { before, after, .. } => before.len() + after.len(),
+            Self::SetMaterial { before, after, .. } => {
+                let texture_size = |material: &SceneMaterial| {
+                    material
+                        .albedo_texture
+                        .as_ref()
+                        .map_or(0, |texture| texture.data.len())
+                };
+                texture_size(before) + texture_size(after)
+            }
+            Self::SetLight { before, after, .. } => before.name.len() + after.name.len(),
+            Self::SetBookmarks { before, after } => before
+                .iter()
+                .chain(after)
+                .map(|bookmark| std::mem::size_of::<CameraBookmark>() + bookmark.name.len())
+                .sum(),
+            Self::SetSceneCameras { before, after } => before
+                .cameras
+                .iter()
+                .chain(&after.cameras)
+                .map(|camera| std::mem::size_of::<SceneCamera>() + camera.name.len())
+                .sum(),
+            Self::Select { before, after } => {
+                (before.len() + after.len()) * std::mem::size_of::<NodeId>()
+            }
+            Self::SetTransform { .. } | Self::SetVisible { .. } | Self::SetCamera { .. } => 0,
+        };
+        std::mem::size_of::<Self>() + held
+    }
+
+    /// Clicking around shouldn't fill the history, so changes of the selection in a row
+    /// become one.
+    fn merge(&mut self, next: Self) -> Result<(), Self> {
+        match (self, next) {
+            (Self::Select { after, .. }, Self::Select { after: later, .. }) => {
+                *after = later;
+                Ok(())
+            }
+            (_, next) => Err(next),
+        }
+    }
+}
@@ -0,0 +1,1094 @@
+//! glTF 2.0 (`.gltf` and `.glb`) import and export.
+//!
+//! The node hierarchy of the file's default scene is kept as it is. A mesh with a
+//! single primitive becomes the object of its node; a mesh with several gets a child
+//! node per primitive. `KHR_lights_punctual` lights become `SceneLight`s, placed at
+//! the world position of their node.
+//!
+//! Exporting does the reverse, writing the scene graph and then one node per light.
+//! Textures are embedded in the binary data as PNG images.
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use anyhow::Context;
+
+use ::gltf::json;
+use json::validation::Checked::Valid;
+
+use super::{
+    flip_handedness, flip_handedness_of_transform, flip_winding,
+    graph::{NodeId, SceneGraph, SceneNode, Transform},
+    RawMesh, SceneLight, SceneLightKind, SceneMaterial, SceneObject, SceneTexture,
+};
+
+/// The contents of a glTF scene, ready to be added to a `SceneData`.
+#[derive(Default)]
+pub struct GltfScene {
+    pub graph: SceneGraph,
+    pub lights: Vec<SceneLight>,
+}
+
+pub fn import_gltf(path: &Path) -> anyhow::Result<GltfScene> {
+    let (document, buffers, images) =
+        ::gltf::import(path).with_context(|| format!("failed to import {}", path.display()))?;
+
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .with_context(|| format!("{} contains no scenes", path.display()))?;
+
+    let mut importer = GltfImporter {
+        buffers: &buffers,
+        images: &images,
+        materials: HashMap::new(),
+        imported: GltfScene::default(),
+    };
+    for node in scene.nodes() {
+        importer
+            .import_node(&node, None, glam::Mat4::IDENTITY)
+            .with_context(|| format!("failed to import {}", path.display()))?;
+    }
+
+    let imported = importer.imported;
+    let has_objects = imported.graph.iter().any(|(_, node)| node.object.is_some());
+    if !has_objects && imported.lights.is_empty() {
+        anyhow::bail!("{} contains no meshes or lights", path.display());
+    }
+
+    Ok(imported)
+}
+
+struct GltfImporter<'a> {
+    buffers: &'a [::gltf::buffer::Data],
+    images: &'a [::gltf::image::Data],
+    /// Keyed by material index. Primitives without a material use `None`, which gives
+    /// the glTF default material.
+    materials: HashMap<Option<usize>, SceneMaterial>,
+    imported: GltfScene,
+}
+
+impl<'a> GltfImporter<'a> {
+    /// Imports `node` and its descendants as children of `parent`. `parent_transform`
+    /// is the world transform of the node's parent, in glTF (right-handed) coordinates.
+    fn import_node(
+        &mut self,
+        node: &::gltf::Node,
+        parent: Option<NodeId>,
+        parent_transform: glam::Mat4,
+    ) -> anyhow::Result<()> {
+        let transform =
+            parent_transform * glam::Mat4::from_cols_array_2d(&node.transform().matrix());
+        let node_name = node
+            .name()
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("node {}", node.index()));
+
+        if let Some(light) = node.light() {
+            self.import_light(&light, &node_name, transform);
+        }
+
+        // Nodes which only hold a light or a camera would be empty in the scene graph.
+        let holds_light_or_camera = node.light().is_some() || node.camera().is_some();
+        if holds_light_or_camera && node.mesh().is_none() && node.children().len() == 0 {
+            return Ok(());
+        }
+
+        let mut objects = match node.mesh() {
+            Some(mesh) => self
+                .import_mesh(&mesh, &node_name)
+                .with_context(|| format!("in node '{}'", node_name))?,
+            None => Vec::new(),
+        };
+
+        let (translation, rotation, scale) = node.transform().decomposed();
+        let local_transform = flip_handedness_of_transform(Transform {
+            translation: glam::Vec3::from(translation),
+            rotation: glam::Quat::from_array(rotation),
+            scale: glam::Vec3::from(scale),
+        });
+        let mut scene_node = SceneNode::new(node_name, local_transform);
+        if objects.len() == 1 {
+            scene_node.object = objects.pop().map(|(_, object)| object);
+        }
+        let id = self.imported.graph.add(scene_node, parent);
+
+        for (name, object) in objects {
+            let primitive_node = SceneNode::with_object(name, Transform::IDENTITY, object);
+            self.imported.graph.add(primitive_node, Some(id));
+        }
+
+        for child in node.children() {
+            self.import_node(&child, Some(id), transform)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns one object per triangle primitive, and a name for each which tells them
+    /// apart.
+    fn import_mesh(
+        &mut self,
+        mesh: &::gltf::Mesh,
+        node_name: &str,
+    ) -> anyhow::Result<Vec<(String, SceneObject)>> {
+        let primitive_count = mesh.primitives().len();
+        let mut objects = Vec::new();
+
+        for primitive in mesh.primitives() {
+            if primitive.mode() != ::gltf::mesh::Mode::Triangles {
+                log::warn!(
+                    "glTF import: skipping primitive {} of '{}', only triangles are supported",
+                    primitive.index(),
+                    node_name
+                );
+                continue;
+            }
+
+            let raw_mesh = self
+                .read_primitive(&primitive)
+                .with_context(|| format!("in primitive {}", primitive.index()))?;
+
+            let material = primitive.material();
+            let name = if primitive_count > 1 {
+                let part = material
+                    .name()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| primitive.index().to_string());
+                format!("{} ({})", node_name, part)
+            } else {
+                node_name.to_string()
+            };
+
+            let object = SceneObject::new(raw_mesh, self.get_material(&material));
+            objects.push((name, object));
+        }
+
+        Ok(objects)
+    }
+
+    fn read_primitive(&self, primitive: &::gltf::Primitive) -> anyhow::Result<RawMesh> {
+        let buffers = self.buffers;
+        let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &data.0[..]));
+
+        let vertices: Vec<glam::Vec3> = reader
+            .read_positions()
+            .context("primitive has no positions")?
+            .map(|p| flip_handedness(glam::Vec3::from(p)))
+            .collect();
+
+        let mut normals: Vec<glam::Vec3> = reader
+            .read_normals()
+            .map(|iter| iter.map(|n| flip_handedness(glam::Vec3::from(n))).collect())
+            .unwrap_or_default();
+        if !normals.is_empty() && normals.len() != vertices.len() {
+            log::warn!("glTF import: normal count doesn't match vertex count, recomputing them");
+            normals.clear();
+        }
+
+        // glTF already puts the texture origin at the top left, like the renderer.
+        let mut uvs: Vec<glam::Vec2> = reader
+            .read_tex_coords(0)
+            .map(|iter| iter.into_f32().map(glam::Vec2::from).collect())
+            .unwrap_or_default();
+        if !uvs.is_empty() && uvs.len() != vertices.len() {
+            log::warn!(
+                "glTF import: texture coordinate count doesn't match vertex count, ignoring them"
+            );
+            uvs.clear();
+        }
+
+        let mut indices: Vec<u32> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect(),
+            // Non-indexed geometry: every three vertices make a triangle.
+            None => (0..vertices.len() as u32).collect(),
+        };
+        if indices.len() % 3 != 0 {
+            anyhow::bail!("index count {} is not a multiple of 3", indices.len());
+        }
+        if let Some(index) = indices.iter().find(|i| **i as usize >= vertices.len()) {
+            anyhow::bail!(
+                "index {} is out of range ({} vertices)",
+                index,
+                vertices.len()
+            );
+        }
+        flip_winding(&mut indices);
+
+        Ok(RawMesh {
+            vertices,
+            normals,
+            uvs,
+            indices,
+        })
+    }
+
+    fn get_material(&mut self, material: &::gltf::Material) -> SceneMaterial {
+        if let Some(scene_material) = self.materials.get(&material.index()) {
+            return scene_material.clone();
+        }
+
+        let pbr = material.pbr_metallic_roughness();
+        let albedo_texture = pbr.base_color_texture().and_then(|info| {
+            if info.tex_coord() != 0 {
+                log::warn!("glTF import: only the first set of texture coordinates is supported");
+            }
+
+            let image = &self.images[info.texture().source().index()];
+            let texture = convert_image(image);
+            if texture.is_none() {
+                log::warn!("glTF import: unsupported image format {:?}", image.format);
+            }
+            texture
+        });
+
+        let scene_material = SceneMaterial {
+            albedo: glam::Vec4::from(pbr.base_color_factor()),
+            albedo_texture,
+            roughness: pbr.roughness_factor(),
+            metallic: pbr.metallic_factor(),
+        };
+        self.materials
+            .insert(material.index(), scene_material.clone());
+
+        scene_material
+    }
+
+    fn import_light(
+        &mut self,
+        light: &::gltf::khr_lights_punctual::Light,
+        node_name: &str,
+        transform: glam::Mat4,
+    ) {
+        // Lights sit at the origin of their node and point down its -z axis.
+        let position = flip_handedness(transform.transform_point3(glam::Vec3::ZERO));
+        let direction =
+            flip_handedness(transform.transform_vector3(glam::Vec3::NEG_Z)).normalize_or_zero();
+
+        let kind = match light.kind() {
+            ::gltf::khr_lights_punctual::Kind::Directional => {
+                SceneLightKind::directional(direction)
+            }
+            ::gltf::khr_lights_punctual::Kind::Point => SceneLightKind::Point {
+                position,
+                range: light.range(),
+            },
+            ::gltf::khr_lights_punctual::Kind::Spot {
+                inner_cone_angle,
+                outer_cone_angle,
+            } => SceneLightKind::Spot {
+                position,
+                direction,
+                range: light.range(),
+                inner_cone_angle,
+                outer_cone_angle,
+            },
+        };
+
+        self.imported.lights.push(SceneLight {
+            name: light.name().unwrap_or(node_name).to_string(),
+            color: glam::Vec3::from(light.color()),
+            intensity: light.intensity(),
+            kind,
+        });
+    }
+}
+
+/// Converts decoded image data to 8 bit RGBA. Grayscale images are spread across the
+/// color channels, and wider channels are truncated.
+fn convert_image(image: &::gltf::image::Data) -> Option<SceneTexture> {
+    use ::gltf::image::Format;
+
+    // (bytes per channel, channels)
+    let (channel_size, channel_count) = match image.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (1, 2),
+        Format::R8G8B8 => (1, 3),
+        Format::R8G8B8A8 => (1, 4),
+        Format::R16 => (2, 1),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (2, 3),
+        Format::R16G16B16A16 => (2, 4),
+        Format::R32G32B32FLOAT => (4, 3),
+        Format::R32G32B32A32FLOAT => (4, 4),
+    };
+
+    let to_u8 = |bytes: &[u8]| -> u8 {
+        match channel_size {
+            1 => bytes[0],
+            2 => (u16::from_ne_bytes([bytes[0], bytes[1]]) >> 8) as u8,
+            _ => {
+                let value = f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                (value.clamp(0.0, 1.0) * 255.0).round() as u8
+            }
+        }
+    };
+
+    let pixel_size = channel_size * channel_count;
+    let pixel_count = (image.width * image.height) as usize;
+    if image.pixels.len() < pixel_count * pixel_size {
+        return None;
+    }
+
+    let mut data = Vec::with_capacity(pixel_count * 4);
+    for pixel in image.pixels.chunks_exact(pixel_size).take(pixel_count) {
+        let channels: Vec<u8> = pixel.chunks_exact(channel_size).map(to_u8).collect();
+        let rgba = match channels.as_slice() {
+            [l] => [*l, *l, *l, 255],
+            [l, a] => [*l, *l, *l, *a],
+            [r, g, b] => [*r, *g, *b, 255],
+            [r, g, b, a] => [*r, *g, *b, *a],
+            _ => return None,
+        };
+        data.extend_from_slice(&rgba);
+    }
+
+    Some(SceneTexture {
+        width: image.width,
+        height: image.height,
+        data,
+    })
+}
+
+/// Writes `graph` and `lights` to `path`. A `.glb` extension gives a single binary
+/// file, anything else a JSON file with the binary data in a `.bin` file next to it.
+pub fn export_gltf(graph: &SceneGraph, lights: &[SceneLight], path: &Path) -> anyhow::Result<()> {
+    let binary = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("glb"));
+
+    let mut exporter = GltfExporter::default();
+    for root in graph.roots() {
+        let index = exporter.export_node(graph, *root)?;
+        exporter.scene_nodes.push(index);
+    }
+    for light in lights {
+        exporter.export_light(light);
+    }
+
+    if binary {
+        exporter.write_glb(path)
+    } else {
+        exporter.write_gltf(path)
+    }
+    .with_context(|| format!("failed to write {}", path.display()))
+}
+
+#[derive(Default)]
+struct GltfExporter {
+    root: json::Root,
+    lights: Vec<json::extensions::scene::khr_lights_punctual::Light>,
+    /// Nodes without a parent.
+    scene_nodes: Vec<json::Index<json::Node>>,
+    /// The contents of the only buffer, which every buffer view points into.
+    buffer: Vec<u8>,
+}
+
+impl GltfExporter {
+    /// Exports `id` and its descendants, and returns the index of the node for `id`.
+    fn export_node(
+        &mut self,
+        graph: &SceneGraph,
+        id: NodeId,
+    ) -> anyhow::Result<json::Index<json::Node>> {
+        let node = graph.get(id).expect("invalid node id");
+
+        let mesh = match &node.object {
+            Some(object) => self
+                .export_object(object, &node.name)
+                .with_context(|| format!("failed to export '{}'", node.name))?,
+            None => None,
+        };
+
+        let children = node
+            .children()
+            .iter()
+            .map(|child| self.export_node(graph, *child))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let transform = flip_handedness_of_transform(*node.transform());
+        let index = json::Index::new(self.root.nodes.len() as u32);
+        self.root.nodes.push(json::Node {
+            mesh,
+            name: Some(node.name.clone()),
+            children: (!children.is_empty()).then_some(children),
+            translation: (transform.translation != glam::Vec3::ZERO)
+                .then(|| transform.translation.to_array()),
+            rotation: (transform.rotation != glam::Quat::IDENTITY)
+                .then(|| json::scene::UnitQuaternion(transform.rotation.to_array())),
+            scale: (transform.scale != glam::Vec3::ONE).then(|| transform.scale.to_array()),
+            ..Default::default()
+        });
+
+        Ok(index)
+    }
+
+    /// Returns `None` if the object has nothing to draw.
+    fn export_object(
+        &mut self,
+        object: &SceneObject,
+        name: &str,
+    ) -> anyhow::Result<Option<json::Index<json::Mesh>>> {
+        let mesh = &object.mesh;
+        if mesh.vertices.is_empty() || mesh.indices.is_empty() {
+            log::warn!(
+                "glTF export: skipping the mesh of '{}', it has no geometry",
+                name
+            );
+            return Ok(None);
+        }
+
+        let mut attributes = BTreeMap::new();
+
+        let positions: Vec<glam::Vec3> =
+            mesh.vertices.iter().map(|v| flip_handedness(*v)).collect();
+        let (min, max) = positions.iter().fold(
+            (glam::Vec3::splat(f32::MAX), glam::Vec3::splat(f32::MIN)),
+            |(min, max), p| (min.min(*p), max.max(*p)),
+        );
+        let view = self.add_buffer_view(
+            &vec3s_to_bytes(&positions),
+            Some(json::buffer::Target::ArrayBuffer),
+        );
+        let accessor = self.add_accessor(
+            view,
+            positions.len(),
+            json::accessor::ComponentType::F32,
+            json::accessor::Type::Vec3,
+            Some((min.to_array().to_vec(), max.to_array().to_vec())),
+        );
+        attributes.insert(Valid(json::mesh::Semantic::Positions), accessor);
+
+        if !mesh.normals.is_empty() {
+            let normals: Vec<glam::Vec3> =
+                mesh.normals.iter().map(|n| flip_handedness(*n)).collect();
+            let view = self.add_buffer_view(
+                &vec3s_to_bytes(&normals),
+                Some(json::buffer::Target::ArrayBuffer),
+            );
+            let accessor = self.add_accessor(
+                view,
+                normals.len(),
+                json::accessor::ComponentType::F32,
+                json::accessor::Type::Vec3,
+                None,
+            );
+            attributes.insert(Valid(json::mesh::Semantic::Normals), accessor);
+        }
+
+        if !mesh.uvs.is_empty() {
+            let bytes: Vec<u8> = mesh
+                .uvs
+                .iter()
+                .flat_map(|uv| uv.to_array())
+                .flat_map(f32::to_le_bytes)
+                .collect();
+            let view = self.add_buffer_view(&bytes, Some(json::buffer::Target::ArrayBuffer));
+            let accessor = self.add_accessor(
+                view,
+                mesh.uvs.len(),
+                json::accessor::ComponentType::F32,
+                json::accessor::Type::Vec2,
+                None,
+            );
+            attributes.insert(Valid(json::mesh::Semantic::TexCoords(0)), accessor);
+        }
+
+        let mut indices = mesh.indices.clone();
+        flip_winding(&mut indices);
+        let bytes: Vec<u8> = indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+        let view = self.add_buffer_view(&bytes, Some(json::buffer::Target::ElementArrayBuffer));
+        let indices = self.add_accessor(
+            view,
+            mesh.indices.len(),
+            json::accessor::ComponentType::U32,
+            json::accessor::Type::Scalar,
+            None,
+        );
+
+        let material = self.export_material(&object.material)?;
+
+        let mesh_index = json::Index::new(self.root.meshes.len() as u32);
+        self.root.meshes.push(json::Mesh {
+            extensions: Default::default(),
+            extras: Default::default(),
+            name: Some(name.to_string()),
+            primitives: vec![json::mesh::Primitive {
+                attributes,
+                extensions: Default::default(),
+                extras: Default::default(),
+                indices: Some(indices),
+                material: Some(material),
+                mode: Valid(json::mesh::Mode::Triangles),
+                targets: None,
+            }],
+            weights: None,
+        });
+
+        Ok(Some(mesh_index))
+    }
+
+    fn export_material(
+        &mut self,
+        material: &SceneMaterial,
+    ) -> anyhow::Result<json::Index<json::Material>> {
+        let base_color_texture = match &material.albedo_texture {
+            Some(texture) => Some(json::texture::Info {
+                index: self.export_texture(texture)?,
+                tex_coord: 0,
+                extensions: Default::default(),
+                extras: Default::default(),
+            }),
+            None => None,
+        };
+
+        let index = json::Index::new(self.root.materials.len() as u32);
+        self.root.materials.push(json::Material {
+            alpha_mode: Valid(if material.albedo.w < 1.0 {
+                json::material::AlphaMode::Blend
+            } else {
+                json::material::AlphaMode::Opaque
+            }),
+            pbr_metallic_roughness: json::material::PbrMetallicRoughness {
+                base_color_factor: json::material::PbrBaseColorFactor(material.albedo.to_array()),
+                base_color_texture,
+                metallic_factor: json::material::StrengthFactor(material.metallic),
+                roughness_factor: json::material::StrengthFactor(material.roughness),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+
+        Ok(index)
+    }
+
+    fn export_texture(
+        &mut self,
+        texture: &SceneTexture,
+    ) -> anyhow::Result<json::Index<json::Texture>> {
+        let png = texture.to_png()?;
+        let view = self.add_buffer_view(&png, None);
+        let image_index = json::Index::new(self.root.images.len() as u32);
+        self.root.images.push(json::Image {
+            buffer_view: Some(view),
+            mime_type: Some(json::image::MimeType("image/png".to_string())),
+            name: None,
+            uri: None,
+            extensions: Default::default(),
+            extras: Default::default(),
+        });
+
+        let index = json::Index::new(self.root.textures.len() as u32);
+        self.root.textures.push(json::Texture {
+            name: None,
+            sampler: None,
+            source: image_index,
+            extensions: Default::default(),
+            extras: Default::default(),
+        });
+
+        Ok(index)
+    }
+
+    fn export_light(&mut self, light: &SceneLight) {
+        use json::extensions::scene::khr_lights_punctual as khr;
+
+        // Lights shine down the -z axis of their node, so rotate that onto the light's
+        // direction.
+        let rotation = |direction: glam::Vec3| {
+            let direction = flip_handedness(direction).normalize_or_zero();
+            (direction != glam::Vec3::ZERO).then(|| {
+                let rotation = glam::Quat::from_rotation_arc(glam::Vec3::NEG_Z, direction);
+                json::scene::UnitQuaternion(rotation.to_array())
+            })
+        };
+
+        let (type_, range, spot, translation, rotation) = match light.kind {
+            SceneLightKind::Directional { direction, .. } => (
+                khr::Type::Directional,
+                None,
+                None,
+                None,
+                rotation(direction),
+            ),
+            SceneLightKind::Point { position, range } => (
+                khr::Type::Point,
+                range,
+                None,
+                Some(flip_handedness(position).to_array()),
+                None,
+            ),
+            SceneLightKind::Spot {
+                position,
+                direction,
+                range,
+                inner_cone_angle,
+                outer_cone_angle,
+            } => (
+                khr::Type::Spot,
+                range,
+                Some(khr::Spot {
+                    inner_cone_angle,
+                    outer_cone_angle,
+                }),
+                Some(flip_handedness(position).to_array()),
+                rotation(direction),
+            ),
+            SceneLightKind::Area { .. } => {
+                log::warn!(
+                    "glTF export: area lights aren't supported, leaving out '{}'",
+                    light.name
+                );
+                return;
+            }
+        };
+
+        let light_index = json::Index::new(self.lights.len() as u32);
+        self.lights.push(khr::Light {
+            color: light.color.to_array(),
+            extensions: Default::default(),
+            extras: Default::default(),
+            intensity: light.intensity,
+            name: Some(light.name.clone()),
+            range,
+            spot,
+            type_: Valid(type_),
+        });
+
+        self.scene_nodes
+            .push(json::Index::new(self.root.nodes.len() as u32));
+        self.root.nodes.push(json::Node {
+            name: Some(light.name.clone()),
+            translation,
+            rotation,
+            extensions: Some(json::extensions::scene::Node {
+                khr_lights_punctual: Some(khr::KhrLightsPunctual { light: light_index }),
+            }),
+            ..Default::default()
+        });
+    }
+
+    /// Appends `data` to the buffer, aligned so that any accessor can read from it.
+    fn add_buffer_view(
+        &mut self,
+        data: &[u8],
+        target: Option<json::buffer::Target>,
+    ) -> json::Index<json::buffer::View> {
+        pad_to_multiple_of_four(&mut self.buffer);
+        let byte_offset = self.buffer.len();
+        self.buffer.extend_from_slice(data);
+
+        let index = json::Index::new(self.root.buffer_views.len() as u32);
+        self.root.buffer_views.push(json::buffer::View {
+            buffer: json::Index::new(0),
+            byte_length: data.len().into(),
+            byte_offset: Some(byte_offset.into()),
+            byte_stride: None,
+            extensions: Default::default(),
+            extras: Default::default(),
+            name: None,
+            target: target.map(Valid),
+        });
+
+        index
+    }
+
+    /// `bounds` are the per-component minimum and maximum, which are required for
+    /// vertex positions.
+    fn add_accessor(
+        &mut self,
+        view: json::Index<json::buffer::View>,
+        count: usize,
+        component_type: json::accessor::ComponentType,
+        type_: json::accessor::Type,
+        bounds: Option<(Vec<f32>, Vec<f32>)>,
+    ) -> json::Index<json::Accessor> {
+        let (min, max) = match bounds {
+            Some((min, max)) => (Some(json::Value::from(min)), Some(json::Value::from(max))),
+            None => (None, None),
+        };
+
+        let index = json::Index::new(self.root.accessors.len() as u32);
+        self.root.accessors.push(json::Accessor {
+            buffer_view: Some(view),
+            byte_offset: None,
+            count: count.into(),
+            component_type: Valid(json::accessor::GenericComponentType(component_type)),
+            extensions: Default::default(),
+            extras: Default::default(),
+            type_: Valid(type_),
+            min,
+            max,
+            name: None,
+            normalized: false,
+            sparse: None,
+        });
+
+        index
+    }
+
+    /// Fills in the parts of the document which refer to everything exported so far.
+    /// `buffer_uri` is `None` when the buffer is stored in a GLB binary chunk.
+    fn finish(&mut self, buffer_uri: Option<String>) {
+        self.root.asset.generator = Some("ekki".to_string());
+
+        if !self.buffer.is_empty() {
+            pad_to_multiple_of_four(&mut self.buffer);
+            self.root.buffers.push(json::Buffer {
+                byte_length: self.buffer.len().into(),
+                extensions: Default::default(),
+                extras: Default::default(),
+                name: None,
+                uri: buffer_uri,
+            });
+        }
+
+        if !self.lights.is_empty() {
+            self.root
+                .extensions_used
+                .push("KHR_lights_punctual".to_string());
+            self.root.extensions = Some(json::extensions::Root {
+                khr_lights_punctual: Some(json::extensions::root::KhrLightsPunctual {
+                    lights: std::mem::take(&mut self.lights),
+                }),
+            });
+        }
+
+        self.root.scenes.push(json::Scene {
+            extensions: Default::default(),
+            extras: Default::default(),
+            name: None,
+            nodes: std::mem::take(&mut self.scene_nodes),
+        });
+        self.root.scene = Some(json::Index::new(0));
+    }
+
+    fn write_gltf(mut self, path: &Path) -> anyhow::Result<()> {
+        let bin_path = path.with_extension("bin");
+        let bin_file_name = bin_path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .with_context(|| format!("{} is not a valid file name", bin_path.display()))?;
+
+        self.finish(Some(encode_uri(bin_file_name)));
+
+        let json = self.root.to_string_pretty()?;
+        std::fs::write(path, json)?;
+        if !self.buffer.is_empty() {
+            std::fs::write(&bin_path, &self.buffer)
+                .with_context(|| format!("failed to write {}", bin_path.display()))?;
+        }
+
+        Ok(())
+    }
+
+    fn write_glb(mut self, path: &Path) -> anyhow::Result<()> {
+        self.finish(None);
+
+        let json = self.root.to_vec()?;
+        let glb = ::gltf::binary::Glb {
+            header: ::gltf::binary::Header {
+                magic: *b"glTF",
+                version: 2,
+                // Worked out by `to_writer()`.
+                length: 0,
+            },
+            json: Cow::Owned(json),
+            bin: (!self.buffer.is_empty()).then_some(Cow::Owned(self.buffer)),
+        };
+        let file = std::fs::File::create(path)?;
+        glb.to_writer(std::io::BufWriter::new(file))?;
+
+        Ok(())
+    }
+}
+
+fn vec3s_to_bytes(vectors: &[glam::Vec3]) -> Vec<u8> {
+    vectors
+        .iter()
+        .flat_map(|v| v.to_array())
+        .flat_map(f32::to_le_bytes)
+        .collect()
+}
+
+fn pad_to_multiple_of_four(bytes: &mut Vec<u8>) {
+    bytes.resize((bytes.len() + 3) & !3, 0);
+}
+
+/// Percent-encodes everything in `s` other than the characters URIs allow unescaped.
+fn encode_uri(s: &str) -> String {
+    s.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangle() -> RawMesh {
+        RawMesh {
+            vertices: vec![
+                glam::Vec3::new(0.0, 0.0, 1.0),
+                glam::Vec3::new(1.0, 0.0, 1.0),
+                glam::Vec3::new(0.0, 1.0, 1.0),
+            ],
+            normals: vec![glam::Vec3::NEG_Z; 3],
+            uvs: vec![
+                glam::Vec2::new(0.0, 0.0),
+                glam::Vec2::new(1.0, 0.0),
+                glam::Vec2::new(0.0, 1.0),
+            ],
+            indices: vec![0, 1, 2],
+        }
+    }
+
+    fn textured_material() -> SceneMaterial {
+        SceneMaterial {
+            albedo: glam::Vec4::new(0.25, 0.5, 0.75, 1.0),
+            albedo_texture: Some(SceneTexture {
+                width: 2,
+                height: 2,
+                data: (0..16).map(|i| i * 16).collect(),
+            }),
+            roughness: 0.25,
+            metallic: 0.5,
+        }
+    }
+
+    fn test_graph() -> SceneGraph {
+        let mut graph = SceneGraph::new();
+        let parent = graph.add(
+            SceneNode::with_object(
+                "parent".to_string(),
+                Transform {
+                    translation: glam::Vec3::new(1.0, 2.0, 3.0),
+                    rotation: glam::Quat::from_euler(glam::EulerRot::YXZ, 0.5, 0.25, 0.125),
+                    scale: glam::Vec3::new(1.0, 2.0, 3.0),
+                },
+                SceneObject::new(triangle(), textured_material()),
+            ),
+            None,
+        );
+        graph.add(
+            SceneNode::with_object(
+                "child".to_string(),
+                Transform {
+                    translation: glam::Vec3::new(0.0, 0.0, -1.0),
+                    ..Transform::IDENTITY
+                },
+                SceneObject::new(triangle(), SceneMaterial::default()),
+            ),
+            Some(parent),
+        );
+        graph.add(
+            SceneNode::new(
+                "empty".to_string(),
+                Transform {
+                    rotation: glam::Quat::from_rotation_z(1.0),
+                    ..Transform::IDENTITY
+                },
+            ),
+            Some(parent),
+        );
+        graph
+    }
+
+    fn test_lights() -> Vec<SceneLight> {
+        let direction = glam::Vec3::new(1.0, -2.0, 3.0).normalize();
+        vec![
+            SceneLight {
+                name: "sun".to_string(),
+                color: glam::Vec3::new(1.0, 0.5, 0.25),
+                intensity: 2.0,
+                kind: SceneLightKind::directional(direction),
+            },
+            SceneLight {
+                name: "bulb".to_string(),
+                color: glam::Vec3::ONE,
+                intensity: 10.0,
+                kind: SceneLightKind::Point {
+                    position: glam::Vec3::new(1.0, 2.0, 3.0),
+                    range: Some(5.0),
+                },
+            },
+            SceneLight {
+                name: "spot".to_string(),
+                color: glam::Vec3::new(0.0, 1.0, 0.0),
+                intensity: 20.0,
+                kind: SceneLightKind::Spot {
+                    position: glam::Vec3::new(-1.0, 0.0, 4.0),
+                    direction,
+                    range: None,
+                    inner_cone_angle: 0.25,
+                    outer_cone_angle: 0.5,
+                },
+            },
+        ]
+    }
+
+    fn temp_path(file_name: &str) -> std::path::PathBuf {
+        let directory = std::env::temp_dir().join(format!("ekki-gltf-test-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        directory.join(file_name)
+    }
+
+    fn assert_transforms_eq(actual: &Transform, expected: &Transform) {
+        assert!(actual.translation.abs_diff_eq(expected.translation, 1e-5));
+        assert!(actual.rotation.abs_diff_eq(expected.rotation, 1e-5));
+        assert!(actual.scale.abs_diff_eq(expected.scale, 1e-5));
+    }
+
+    fn assert_lights_eq(actual: &SceneLight, expected: &SceneLight) {
+        assert_eq!(actual.name, expected.name);
+        assert_eq!(actual.color, expected.color);
+        assert_eq!(actual.intensity, expected.intensity);
+        assert_eq!(actual.kind.name(), expected.kind.name());
+        match (&actual.kind, &expected.kind) {
+            (
+                SceneLightKind::Point { range, .. },
+                SceneLightKind::Point {
+                    range: expected_range,
+                    ..
+                },
+            ) => assert_eq!(range, expected_range),
+            (
+                SceneLightKind::Spot {
+                    range,
+                    inner_cone_angle,
+                    outer_cone_angle,
+                    ..
+                },
+                SceneLightKind::Spot {
+                    range: expected_range,
+                    inner_cone_angle: expected_inner_cone_angle,
+                    outer_cone_angle: expected_outer_cone_angle,
+                    ..
+                },
+            ) => {
+                assert_eq!(range, expected_range);
+                assert_eq!(inner_cone_angle, expected_inner_cone_angle);
+                assert_eq!(outer_cone_angle, expected_outer_cone_angle);
+            }
+            _ => {}
+        }
+        match (actual.kind.position(), expected.kind.position()) {
+            (Some(position), Some(expected_position)) => {
+                assert!(position.abs_diff_eq(expected_position, 1e-5))
+            }
+            (position, expected_position) => assert_eq!(position, expected_position),
+        }
+        match (actual.kind.direction(), expected.kind.direction()) {
+            (Some(direction), Some(expected_direction)) => {
+                assert!(direction.abs_diff_eq(expected_direction, 1e-5))
+            }
+            (direction, expected_direction) => assert_eq!(direction, expected_direction),
+        }
+    }
+
+    #[test]
+    fn export_converts_to_right_handed_coordinates() {
+        let mut graph = SceneGraph::new();
+        graph.add(
+            SceneNode::with_object(
+                "triangle".to_string(),
+                Transform {
+                    translation: glam::Vec3::new(0.0, 0.0, 2.0),
+                    ..Transform::IDENTITY
+                },
+                SceneObject::new(triangle(), SceneMaterial::default()),
+            ),
+            None,
+        );
+
+        let path = temp_path("handedness.gltf");
+        export_gltf(&graph, &[], &path).unwrap();
+        let (document, buffers, _) = ::gltf::import(&path).unwrap();
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        let node = document.nodes().next().unwrap();
+        assert_eq!(node.transform().decomposed().0, [0.0, 0.0, -2.0]);
+
+        let primitive = node.mesh().unwrap().primitives().next().unwrap();
+        let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()].0[..]));
+        let positions: Vec<[f32; 3]> = reader.read_positions().unwrap().collect();
+        assert_eq!(
+            positions,
+            [[0.0, 0.0, -1.0], [1.0, 0.0, -1.0], [0.0, 1.0, -1.0]]
+        );
+        let indices: Vec<u32> = reader.read_indices().unwrap().into_u32().collect();
+        assert_eq!(indices, [0, 2, 1]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let graph = test_graph();
+        let lights = test_lights();
+
+        for file_name in ["scene.gltf", "scene.glb"] {
+            let path = temp_path(file_name);
+            export_gltf(&graph, &lights, &path).unwrap();
+            let imported = import_gltf(&path);
+            std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+            let imported = imported.unwrap();
+
+            let expected_nodes: Vec<_> = graph.iter().map(|(_, node)| node).collect();
+            let nodes: Vec<_> = imported.graph.iter().map(|(_, node)| node).collect();
+            assert_eq!(nodes.len(), expected_nodes.len());
+            for (node, expected_node) in nodes.iter().zip(&expected_nodes) {
+                assert_eq!(node.name, expected_node.name);
+                assert_eq!(
+                    node.parent()
+                        .map(|parent| &imported.graph.get(parent).unwrap().name),
+                    expected_node
+                        .parent()
+                        .map(|parent| &graph.get(parent).unwrap().name)
+                );
+                assert_transforms_eq(node.transform(), expected_node.transform());
+
+                let (object, expected_object) = match (&node.object, &expected_node.object) {
+                    (Some(object), Some(expected_object)) => (object, expected_object),
+                    (None, None) => continue,
+                    _ => panic!("'{}' gained or lost its object", node.name),
+                };
+                let mesh = &object.mesh;
+                let expected_mesh = &expected_object.mesh;
+                assert_eq!(mesh.vertices, expected_mesh.vertices);
+                assert_eq!(mesh.normals, expected_mesh.normals);
+                assert_eq!(mesh.uvs, expected_mesh.uvs);
+                assert_eq!(mesh.indices, expected_mesh.indices);
+
+                let material = &object.material;
+                let expected_material = &expected_object.material;
+                assert_eq!(material.albedo, expected_material.albedo);
+                assert_eq!(material.roughness, expected_material.roughness);
+                assert_eq!(material.metallic, expected_material.metallic);
+                assert_eq!(
+                    material.albedo_texture.as_ref().map(|texture| (
+                        texture.width,
+                        texture.height,
+                        &texture.data
+                    )),
+                    expected_material.albedo_texture.as_ref().map(|texture| (
+                        texture.width,
+                        texture.height,
+                        &texture.data
+                    ))
+                );
+            }
+
+            assert_eq!(imported.lights.len(), lights.len());
+            for (light, expected_light) in imported.lights.iter().zip(&lights) {
+                assert_lights_eq(light, expected_light);
+            }
+        }
+    }
+}
@@ -0,0 +1,556 @@
+//! The scene graph: a hierarchy of named nodes, each positioned relative to its parent.
+//!
+//! Nodes are addressed by `NodeId`s, which stay valid until the node is removed. Every
+//! node caches its world transform; the graph keeps the caches up to date whenever a
+//! transform or parent changes, and reports which nodes were affected so that the
+//! renderer can be told about them.
+
+use serde::{Deserialize, Serialize};
+
+use super::SceneObject;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct NodeId(usize);
+
+/// Position, orientation and size of a node relative to its parent. Scaling is applied
+/// first, then rotation, then translation.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Transform {
+    pub translation: glam::Vec3,
+    pub rotation: glam::Quat,
+    pub scale: glam::Vec3,
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        translation: glam::Vec3::ZERO,
+        rotation: glam::Quat::IDENTITY,
+        scale: glam::Vec3::ONE,
+    };
+
+    /// Decomposes `matrix`, which must not contain shear or perspective.
+    pub fn from_matrix(matrix: glam::Mat4) -> Self {
+        let (scale, rotation, translation) = matrix.to_scale_rotation_translation();
+        Self {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn to_matrix(self) -> glam::Mat4 {
+        glam::Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SceneNode {
+    pub name: String,
+    /// The mesh drawn at this node, if any. Nodes without one are only used to group
+    /// their children.
+    pub object: Option<SceneObject>,
+    transform: Transform,
+    /// Hiding a node also hides its descendants, see `SceneGraph::is_visible()`.
+    visible: bool,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    /// node -> world space. Derived from the transforms of the node and its ancestors.
+    #[serde(skip, default = "identity_matrix")]
+    world_transform: glam::Mat4,
+}
+
+fn identity_matrix() -> glam::Mat4 {
+    glam::Mat4::IDENTITY
+}
+
+impl SceneNode {
+    /// A node without an object, used to group other nodes.
+    pub fn new(name: String, transform: Transform) -> Self {
+        Self {
+            name,
+            object: None,
+            transform,
+            visible: true,
+            parent: None,
+            children: Vec::new(),
+            world_transform: glam::Mat4::IDENTITY,
+        }
+    }
+
+    pub fn with_object(name: String, transform: Transform, object: SceneObject) -> Self {
+        Self {
+            object: Some(object),
+            ..Self::new(name, transform)
+        }
+    }
+
+    /// Relative to the parent. Use `SceneGraph::set_transform()` to change it.
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    /// Whether the node itself is shown. Use `SceneGraph::set_visible()` to change it.
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
+    /// node -> world space
+    pub fn world_transform(&self) -> glam::Mat4 {
+        self.world_transform
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+}
+
+/// A subtree taken out of a `SceneGraph`, which can be put back with
+/// `SceneGraph::restore()`.
+pub struct RemovedNodes {
+    /// Parents before their children, so the root of the subtree comes first.
+    nodes: Vec<(NodeId, SceneNode)>,
+    /// Where the root was among its siblings.
+    index: usize,
+}
+
+impl RemovedNodes {
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &SceneNode)> {
+        self.nodes.iter().map(|(id, node)| (*id, node))
+    }
+
+    /// Roughly how many bytes the nodes take up.
+    pub fn memory_size(&self) -> usize {
+        self.nodes
+            .iter()
+            .map(|(_, node)| {
+                std::mem::size_of::<(NodeId, SceneNode)>()
+                    + node.name.len()
+                    + node.children.len() * std::mem::size_of::<NodeId>()
+                    + node.object.as_ref().map_or(0, SceneObject::memory_size)
+            })
+            .sum()
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(try_from = "SerializedSceneGraph")]
+pub struct SceneGraph {
+    /// Indexed by `NodeId`. Removed nodes leave a `None` behind so that the ids of the
+    /// other nodes don't change.
+    nodes: Vec<Option<SceneNode>>,
+    /// Nodes without a parent, in display order.
+    roots: Vec<NodeId>,
+}
+
+/// The world transforms aren't saved, so they need recomputing after loading. The
+/// hierarchy is checked first, since a damaged file could contain anything.
+#[derive(Deserialize)]
+struct SerializedSceneGraph {
+    nodes: Vec<Option<SceneNode>>,
+    roots: Vec<NodeId>,
+}
+
+impl TryFrom<SerializedSceneGraph> for SceneGraph {
+    type Error = String;
+
+    fn try_from(serialized: SerializedSceneGraph) -> Result<Self, Self::Error> {
+        let mut graph = Self {
+            nodes: serialized.nodes,
+            roots: serialized.roots,
+        };
+        graph.validate()?;
+        for root in graph.roots.clone() {
+            graph.update_world_transforms(root);
+        }
+        Ok(graph)
+    }
+}
+
+impl SceneGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `node` as the last child of `parent`, or as a root if `parent` is `None`.
+    /// Any parent or children `node` had in another graph are discarded.
+    pub fn add(&mut self, mut node: SceneNode, parent: Option<NodeId>) -> NodeId {
+        let id = NodeId(self.nodes.len());
+
+        node.parent = parent;
+        node.children.clear();
+        self.nodes.push(Some(node));
+        match parent {
+            Some(parent) => self.node_mut(parent).children.push(id),
+            None => self.roots.push(id),
+        }
+        self.update_world_transforms(id);
+
+        id
+    }
+
+    /// Adds every node of `other` to this graph, with the roots of `other` becoming
+    /// children of `parent`. Returns the new ids of the nodes of `other`, parents
+    /// before their children.
+    pub fn append(&mut self, other: SceneGraph, parent: Option<NodeId>) -> Vec<NodeId> {
+        let mut added = Vec::new();
+        let mut other_nodes = other.nodes;
+        for root in other.roots {
+            self.append_subtree(&mut other_nodes, root, parent, &mut added);
+        }
+        added
+    }
+
+    fn append_subtree(
+        &mut self,
+        other_nodes: &mut [Option<SceneNode>],
+        id: NodeId,
+        parent: Option<NodeId>,
+        added: &mut Vec<NodeId>,
+    ) {
+        let Some(node) = other_nodes[id.0].take() else {
+            return;
+        };
+        let children = node.children.clone();
+
+        let new_id = self.add(node, parent);
+        added.push(new_id);
+        for child in children {
+            self.append_subtree(other_nodes, child, Some(new_id), added);
+        }
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&SceneNode> {
+        self.nodes.get(id.0).and_then(Option::as_ref)
+    }
+
+    /// Transforms and parents can't be changed through the returned reference, see
+    /// `set_transform()` and `set_parent()`.
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut SceneNode> {
+        self.nodes.get_mut(id.0).and_then(Option::as_mut)
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut SceneNode {
+        self.get_mut(id).expect("invalid node id")
+    }
+
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// Every node, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &SceneNode)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, node)| node.as_ref().map(|node| (NodeId(index), node)))
+    }
+
+    /// `id` followed by all of its descendants, parents before their children.
+    pub fn subtree(&self, id: NodeId) -> Vec<NodeId> {
+        let mut subtree = vec![id];
+        let mut next = 0;
+        while next < subtree.len() {
+            if let Some(node) = self.get(subtree[next]) {
+                subtree.extend_from_slice(&node.children);
+            }
+            next += 1;
+        }
+        subtree
+    }
+
+    /// Sets the transform of `id` relative to its parent. Returns the nodes whose world
+    /// transforms changed as a result: `id` and all of its descendants.
+    pub fn set_transform(&mut self, id: NodeId, transform: Transform) -> Vec<NodeId> {
+        self.node_mut(id).transform = transform;
+        self.update_world_transforms(id)
+    }
+
+    /// Shows or hides `id`. Returns the nodes whose visibility may have changed as a
+    /// result: `id` and all of its descendants.
+    pub fn set_visible(&mut self, id: NodeId, visible: bool) -> Vec<NodeId> {
+        self.node_mut(id).visible = visible;
+        self.subtree(id)
+    }
+
+    /// Whether `id` is shown, which it is if neither it nor any of its ancestors are
+    /// hidden.
+    pub fn is_visible(&self, id: NodeId) -> bool {
+        let mut next = Some(id);
+        while let Some(node) = next.and_then(|id| self.get(id)) {
+            if !node.visible {
+                return false;
+            }
+            next = node.parent;
+        }
+        true
+    }
+
+    /// Moves `id` to be the last child of `parent`, or a root if `parent` is `None`.
+    /// The node keeps its place in the world, so its local transform is adjusted to
+    /// the new parent. Returns the nodes whose world transforms changed, which can
+    /// only be due to rounding.
+    pub fn set_parent(
+        &mut self,
+        id: NodeId,
+        parent: Option<NodeId>,
+    ) -> anyhow::Result<Vec<NodeId>> {
+        if let Some(parent) = parent {
+            if self.subtree(id).contains(&parent) {
+                anyhow::bail!("a node can't be moved into one of its own descendants");
+            }
+        }
+
+        let old_parent = self.node_mut(id).parent;
+        self.detach(id, old_parent);
+
+        let parent_world_transform = match parent {
+            Some(parent) => {
+                let parent_node = self.node_mut(parent);
+                parent_node.children.push(id);
+                parent_node.world_transform
+            }
+            None => {
+                self.roots.push(id);
+                glam::Mat4::IDENTITY
+            }
+        };
+
+        let node = self.node_mut(id);
+        node.parent = parent;
+        node.transform =
+            Transform::from_matrix(parent_world_transform.inverse() * node.world_transform);
+
+        Ok(self.update_world_transforms(id))
+    }
+
+    /// Removes `id` and all of its descendants.
+    pub fn remove(&mut self, id: NodeId) -> RemovedNodes {
+        let parent = self.node_mut(id).parent;
+        let index = self
+            .siblings_mut(parent)
+            .iter()
+            .position(|sibling| *sibling == id)
+            .unwrap_or_default();
+        self.detach(id, parent);
+
+        let nodes = self
+            .subtree(id)
+            .into_iter()
+            .filter_map(|id| self.nodes[id.0].take().map(|node| (id, node)))
+            .collect();
+        RemovedNodes { nodes, index }
+    }
+
+    /// Puts back nodes taken out by `remove()`, with the same ids and in the same
+    /// place in the hierarchy. The parent they were removed from must still be in the
+    /// graph. Returns the restored nodes, parents before their children.
+    pub fn restore(&mut self, removed: RemovedNodes) -> Vec<NodeId> {
+        let Some((root, root_node)) = removed.nodes.first() else {
+            return Vec::new();
+        };
+        let (root, parent) = (*root, root_node.parent);
+
+        for (id, node) in removed.nodes {
+            if self.nodes.len() <= id.0 {
+                self.nodes.resize_with(id.0 + 1, || None);
+            }
+            self.nodes[id.0] = Some(node);
+        }
+        let siblings = self.siblings_mut(parent);
+        siblings.insert(removed.index.min(siblings.len()), root);
+
+        self.update_world_transforms(root)
+    }
+
+    /// The children of `parent`, or the roots.
+    fn siblings_mut(&mut self, parent: Option<NodeId>) -> &mut Vec<NodeId> {
+        match parent {
+            Some(parent) => &mut self.node_mut(parent).children,
+            None => &mut self.roots,
+        }
+    }
+
+    /// Removes `id` from the children of `parent`, or from the roots.
+    fn detach(&mut self, id: NodeId, parent: Option<NodeId>) {
+        self.siblings_mut(parent).retain(|sibling| *sibling != id);
+    }
+
+    /// Checks that every node is either a root or the child of the node it names as its
+    /// parent, exactly once, and that the nodes form a tree.
+    fn validate(&self) -> Result<(), String> {
+        let place = |parent: Option<NodeId>| match parent {
+            Some(parent) => format!("a child of node {}", parent.0),
+            None => "a root".to_string(),
+        };
+
+        // How often each node is listed as a root or as a child.
+        let mut listings = vec![0; self.nodes.len()];
+        let mut check_listing = |id: NodeId, parent: Option<NodeId>| {
+            let node = self.get(id).ok_or_else(|| {
+                format!(
+                    "node {} is listed as {}, but doesn't exist",
+                    id.0,
+                    place(parent)
+                )
+            })?;
+            if node.parent != parent {
+                return Err(format!(
+                    "node {} is listed as {}, but should be {}",
+                    id.0,
+                    place(parent),
+                    place(node.parent)
+                ));
+            }
+            listings[id.0] += 1;
+            if listings[id.0] > 1 {
+                return Err(format!("node {} is listed more than once", id.0));
+            }
+            Ok(())
+        };
+        for root in &self.roots {
+            check_listing(*root, None)?;
+        }
+        for (id, node) in self.iter() {
+            for child in &node.children {
+                check_listing(*child, Some(id))?;
+            }
+        }
+
+        if let Some((id, node)) = self.iter().find(|(id, _)| listings[id.0] == 0) {
+            return Err(format!(
+                "node {} should be {}, but isn't listed as one",
+                id.0,
+                place(node.parent)
+            ));
+        }
+        // Every node is listed exactly once, so nodes which can't be reached from the
+        // roots are part of a cycle.
+        let reachable: usize = self
+            .roots
+            .iter()
+            .map(|root| self.subtree(*root).len())
+            .sum();
+        if reachable != self.iter().count() {
+            return Err("the nodes' parents form a cycle".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Recomputes the world transforms of `id` and its descendants, and returns them.
+    fn update_world_transforms(&mut self, id: NodeId) -> Vec<NodeId> {
+        let subtree = self.subtree(id);
+        for id in &subtree {
+            let parent_world_transform = self
+                .get(*id)
+                .and_then(SceneNode::parent)
+                .and_then(|parent| self.get(parent))
+                .map_or(glam::Mat4::IDENTITY, |parent| parent.world_transform);
+
+            let node = self.node_mut(*id);
+            node.world_transform = parent_world_transform * node.transform.to_matrix();
+        }
+        subtree
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A root with two children, the first of which has a child of its own.
+    fn test_graph() -> SceneGraph {
+        let translated = |x: f32| Transform {
+            translation: glam::Vec3::new(x, 0.0, 0.0),
+            ..Transform::IDENTITY
+        };
+        let mut graph = SceneGraph::new();
+        let root = graph.add(SceneNode::new("root".to_string(), translated(1.0)), None);
+        let child = graph.add(
+            SceneNode::new("child".to_string(), translated(2.0)),
+            Some(root),
+        );
+        graph.add(
+            SceneNode::new("grandchild".to_string(), translated(3.0)),
+            Some(child),
+        );
+        graph.add(
+            SceneNode::new("other child".to_string(), translated(4.0)),
+            Some(root),
+        );
+        graph
+    }
+
+    #[test]
+    fn deserializing_recomputes_world_transforms() {
+        let json = serde_json::to_value(test_graph()).unwrap();
+        let graph: SceneGraph = serde_json::from_value(json).unwrap();
+
+        assert_eq!(graph.roots(), [NodeId(0)]);
+        assert_eq!(graph.subtree(NodeId(0)).len(), 4);
+        let grandchild = graph.get(NodeId(2)).unwrap();
+        assert_eq!(grandchild.parent(), Some(NodeId(1)));
+        assert_eq!(
+            grandchild
+                .world_transform()
+                .transform_point3(glam::Vec3::ZERO),
+            glam::Vec3::new(6.0, 0.0, 0.0)
+        );
+    }
+
+    /// Applies `breakage` to the serialized test graph, and checks that the result can't
+    /// be deserialized.
+    fn assert_rejected(breakage: impl FnOnce(&mut serde_json::Value)) {
+        let mut json = serde_json::to_value(test_graph()).unwrap();
+        breakage(&mut json);
+        assert!(serde_json::from_value::<SceneGraph>(json).is_err());
+    }
+
+    #[test]
+    fn deserializing_rejects_missing_nodes() {
+        assert_rejected(|json| json["roots"][0] = 7.into());
+        assert_rejected(|json| json["nodes"][1]["children"][0] = 7.into());
+        assert_rejected(|json| json["nodes"][1] = serde_json::Value::Null);
+    }
+
+    #[test]
+    fn deserializing_rejects_mismatched_parents() {
+        assert_rejected(|json| json["nodes"][2]["parent"] = 3.into());
+        assert_rejected(|json| json["nodes"][0]["parent"] = 1.into());
+        // Listed by another node.
+        assert_rejected(|json| json["nodes"][3]["children"] = serde_json::json!([2]));
+        // Listed twice by its parent.
+        assert_rejected(|json| json["nodes"][1]["children"] = serde_json::json!([2, 2]));
+        // Not listed by its parent.
+        assert_rejected(|json| json["nodes"][1]["children"] = serde_json::json!([]));
+    }
+
+    #[test]
+    fn deserializing_rejects_cycles() {
+        // Detach the child from the root, and make it the child of its own child.
+        assert_rejected(|json| {
+            json["nodes"][0]["children"] = serde_json::json!([3]);
+            json["nodes"][1]["parent"] = 2.into();
+            json["nodes"][2]["children"] = serde_json::json!([1]);
+        });
+        assert_rejected(|json| {
+            json["nodes"][0]["children"] = serde_json::json!([3]);
+            json["nodes"][1]["parent"] = 1.into();
+            json["nodes"][1]["children"] = serde_json::json!([1, 2]);
+        });
+    }
+}
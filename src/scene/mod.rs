@@ -1,12 +1,37 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
-use crate::camera::Camera;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::camera::{Camera, CameraState};
+
+use graph::{NodeId, RemovedNodes, SceneGraph, SceneNode, Transform};
+
+pub mod commands;
+pub mod gltf;
+pub mod graph;
+pub mod obj;
+pub mod picking;
+pub mod plugin_scene;
+pub mod primitives;
+
+/// Added to the emissive color of selected objects to make them stand out.
+const SELECTION_HIGHLIGHT: glam::Vec3 = glam::Vec3::new(0.6, 0.3, 0.0);
 
 pub struct SceneData {
     pub camera: Camera,
-    pub objects: Vec<SceneObject>,
-    rend3_object_handles: Vec<rend3::types::ResourceHandle<rend3::types::Object>>,
-    rend3_directional_handles: Vec<rend3::types::ResourceHandle<rend3::types::DirectionalLight>>,
+    /// Changes to the graph have to be mirrored in the renderer, so it is only
+    /// modified through `SceneData`.
+    graph: SceneGraph,
+    pub lights: Vec<SceneLight>,
+    pub bookmarks: Vec<CameraBookmark>,
+    pub cameras: SceneCameras,
+    /// Selected nodes, in the order they were selected.
+    selection: Vec<NodeId>,
+    /// One per node which has an object.
+    rend3_objects: HashMap<NodeId, Rend3Object>,
+    /// One per light. `None` for lights the viewport can't preview.
+    rend3_light_handles: Vec<Option<rend3::types::ResourceHandle<rend3::types::DirectionalLight>>>,
 }
 
 impl SceneData {
@@ -16,41 +41,818 @@ impl SceneData {
     ) -> Self {
         let camera = Camera::initialize(window_size.width as f32, window_size.height as f32);
 
+        let mut scene_data = Self {
+            camera,
+            graph: SceneGraph::new(),
+            lights: Vec::new(),
+            bookmarks: Vec::new(),
+            cameras: SceneCameras::default(),
+            selection: Vec::new(),
+            rend3_objects: HashMap::new(),
+            rend3_light_handles: Vec::new(),
+        };
+
         // Initialize scene: basic cube and directional light.
-        let basic_cube = SceneObject::create_basic_cube();
-        let basic_cube_handle = basic_cube.add_to_rend3_renderer(rend3_renderer).unwrap();
+        let cube = SceneNode::with_object(
+            "Cube".to_string(),
+            Transform::IDENTITY,
+            SceneObject::create_basic_cube(),
+        );
+        scene_data.add_node(cube, None, rend3_renderer).unwrap();
+        scene_data.add_light(SceneLight::default(), rend3_renderer);
 
-        let objects = vec![basic_cube];
-        let rend3_object_handles = vec![basic_cube_handle];
+        scene_data
+    }
 
-        // Create a single directional light
-        //
-        // We need to keep the directional light handle alive.
-        let direction_handle =
-            rend3_renderer.add_directional_light(rend3::types::DirectionalLight {
-                color: glam::Vec3::ONE,
-                intensity: 10.0,
-                // Direction will be normalized
-                direction: glam::Vec3::new(-1.0, -4.0, 2.0),
-                distance: 400.0,
-                resolution: 2048,
-            });
-        let rend3_directional_handles = vec![direction_handle];
+    pub fn graph(&self) -> &SceneGraph {
+        &self.graph
+    }
+
+    /// Adds `node` as the last child of `parent`, or as a root if `parent` is `None`.
+    pub fn add_node(
+        &mut self,
+        node: SceneNode,
+        parent: Option<NodeId>,
+        rend3_renderer: &Arc<rend3::Renderer>,
+    ) -> anyhow::Result<NodeId> {
+        let id = self.graph.add(node, parent);
+        if let Err(e) = self.add_rend3_object(id, rend3_renderer) {
+            self.graph.remove(id);
+            return Err(e);
+        }
+
+        Ok(id)
+    }
+
+    /// Adds every node of `graph`, with its roots becoming children of `parent`.
+    /// Objects which the renderer rejects are logged, and their nodes are kept without
+    /// being drawn so that the rest of the hierarchy stays intact.
+    pub fn add_graph(
+        &mut self,
+        graph: SceneGraph,
+        parent: Option<NodeId>,
+        rend3_renderer: &Arc<rend3::Renderer>,
+    ) -> Vec<NodeId> {
+        let added = self.graph.append(graph, parent);
+        for id in &added {
+            self.add_rend3_object_or_log(*id, rend3_renderer);
+        }
+
+        added
+    }
+
+    /// Removes `id` and all of its descendants, which are returned so that they can be
+    /// put back with `restore_nodes()`.
+    pub fn remove_node(&mut self, id: NodeId) -> RemovedNodes {
+        let removed = self.graph.remove(id);
+        // Dropping the handles is what removes the objects from the rend3 renderer.
+        for (id, _) in removed.iter() {
+            self.rend3_objects.remove(&id);
+            self.selection.retain(|selected| *selected != id);
+        }
+        removed
+    }
+
+    /// Puts back nodes taken out by `remove_node()`, with the same ids and in the same
+    /// place in the hierarchy. They aren't selected again.
+    pub fn restore_nodes(&mut self, removed: RemovedNodes, rend3_renderer: &Arc<rend3::Renderer>) {
+        for id in self.graph.restore(removed) {
+            self.add_rend3_object_or_log(id, rend3_renderer);
+        }
+    }
+
+    /// Sets the transform of `id` relative to its parent, and moves it and its
+    /// descendants in the renderer. Their meshes are left as they are.
+    pub fn set_transform(
+        &mut self,
+        id: NodeId,
+        transform: Transform,
+        rend3_renderer: &Arc<rend3::Renderer>,
+    ) {
+        for id in self.graph.set_transform(id, transform) {
+            self.update_rend3_transform(id, rend3_renderer);
+        }
+    }
+
+    /// Moves `id` to be the last child of `parent`, or a root if `parent` is `None`,
+    /// keeping its place in the world.
+    pub fn set_parent(
+        &mut self,
+        id: NodeId,
+        parent: Option<NodeId>,
+        rend3_renderer: &Arc<rend3::Renderer>,
+    ) -> anyhow::Result<()> {
+        for id in self.graph.set_parent(id, parent)? {
+            self.update_rend3_transform(id, rend3_renderer);
+        }
+
+        Ok(())
+    }
+
+    /// Shows or hides `id` and its descendants in the viewport. Hidden nodes can't be
+    /// picked, but are otherwise still part of the scene.
+    pub fn set_visible(
+        &mut self,
+        id: NodeId,
+        visible: bool,
+        rend3_renderer: &Arc<rend3::Renderer>,
+    ) {
+        for id in self.graph.set_visible(id, visible) {
+            if !self.graph.is_visible(id) {
+                // Dropping the handles is what removes the objects from the renderer.
+                self.rend3_objects.remove(&id);
+            } else if !self.rend3_objects.contains_key(&id) {
+                self.add_rend3_object_or_log(id, rend3_renderer);
+            }
+        }
+    }
+
+    /// Changes the material of the object of `id`, if it has one.
+    pub fn set_material(
+        &mut self,
+        id: NodeId,
+        material: SceneMaterial,
+        rend3_renderer: &Arc<rend3::Renderer>,
+    ) {
+        let Some(object) = self.graph.get_mut(id).and_then(|node| node.object.as_mut()) else {
+            return;
+        };
+
+        let texture_changed = object.material.albedo_texture != material.albedo_texture;
+        object.material = material;
+        if texture_changed {
+            if let Some(rend3_object) = self.rend3_objects.get_mut(&id) {
+                rend3_object.albedo_texture = object.material.add_rend3_texture(rend3_renderer);
+            }
+        }
+        self.update_rend3_material(id, rend3_renderer);
+    }
+
+    /// Renames `id`. Names don't need to be unique.
+    pub fn set_name(&mut self, id: NodeId, name: String) {
+        if let Some(node) = self.graph.get_mut(id) {
+            node.name = name;
+        }
+    }
+
+    /// Selected nodes, in the order they were selected. The last one is the active
+    /// node.
+    pub fn selection(&self) -> &[NodeId] {
+        &self.selection
+    }
+
+    pub fn is_selected(&self, id: NodeId) -> bool {
+        self.selection.contains(&id)
+    }
+
+    /// The corners of the world space box around the visible objects of the nodes
+    /// `ids` and their descendants, or `None` if there aren't any.
+    pub fn bounds<'a>(
+        &self,
+        ids: impl IntoIterator<Item = &'a NodeId>,
+    ) -> Option<(glam::Vec3, glam::Vec3)> {
+        ids.into_iter()
+            .flat_map(|id| self.graph.subtree(*id))
+            .filter(|id| self.graph.is_visible(*id))
+            .filter_map(|id| {
+                let node = self.graph.get(id)?;
+                let object = node.object.as_ref()?;
+                let world_transform = node.world_transform();
+                Some(
+                    object
+                        .mesh
+                        .vertices
+                        .iter()
+                        .map(move |vertex| world_transform.transform_point3(*vertex)),
+                )
+            })
+            .flatten()
+            .fold(None, |bounds, vertex| match bounds {
+                Some((min, max)) => Some((vertex.min(min), vertex.max(max))),
+                None => Some((vertex, vertex)),
+            })
+    }
+
+    /// Changes the selection by `ids` as described by `mode`, and highlights the
+    /// selected objects in the renderer.
+    pub fn select(
+        &mut self,
+        ids: &[NodeId],
+        mode: SelectionMode,
+        rend3_renderer: &Arc<rend3::Renderer>,
+    ) {
+        let old_selection = std::mem::take(&mut self.selection);
+        let mut selection = match mode {
+            SelectionMode::Replace => Vec::new(),
+            SelectionMode::Extend | SelectionMode::Toggle => old_selection.clone(),
+        };
+        for id in ids {
+            let position = selection.iter().position(|selected| selected == id);
+            match (mode, position) {
+                (SelectionMode::Toggle, Some(position)) => {
+                    selection.remove(position);
+                }
+                // Reselecting a node makes it the active one.
+                (_, Some(position)) => {
+                    selection.remove(position);
+                    selection.push(*id);
+                }
+                (_, None) => selection.push(*id),
+            }
+        }
+        self.selection = selection;
+
+        let changed = old_selection
+            .iter()
+            .filter(|id| !self.selection.contains(id))
+            .chain(
+                self.selection
+                    .iter()
+                    .filter(|id| !old_selection.contains(id)),
+            );
+        for id in changed {
+            self.update_rend3_material(*id, rend3_renderer);
+        }
+    }
+
+    /// Creates the rend3 object for the node `id`, if it has an object and is visible.
+    fn add_rend3_object(
+        &mut self,
+        id: NodeId,
+        rend3_renderer: &Arc<rend3::Renderer>,
+    ) -> anyhow::Result<()> {
+        let Some(node) = self.graph.get(id) else {
+            return Ok(());
+        };
+        if !self.graph.is_visible(id) {
+            return Ok(());
+        }
+        if let Some(object) = &node.object {
+            let albedo_texture = object.material.add_rend3_texture(rend3_renderer);
+            let material = self.rend3_material(id, &object.material, albedo_texture.as_ref());
+            // We need to keep the object handle alive.
+            let rend3_object = object.add_to_rend3_renderer(
+                node.world_transform(),
+                material,
+                albedo_texture,
+                rend3_renderer,
+            )?;
+            self.rend3_objects.insert(id, rend3_object);
+        }
+
+        Ok(())
+    }
+
+    /// Like `add_rend3_object()`, for when the node should stay in the scene even if
+    /// the renderer rejects its object.
+    fn add_rend3_object_or_log(&mut self, id: NodeId, rend3_renderer: &Arc<rend3::Renderer>) {
+        if let Err(e) = self.add_rend3_object(id, rend3_renderer) {
+            log::error!(
+                "failed to add '{}' to the renderer: {:#}",
+                self.graph.get(id).unwrap().name,
+                e
+            );
+        }
+    }
+
+    fn update_rend3_transform(&self, id: NodeId, rend3_renderer: &Arc<rend3::Renderer>) {
+        if let (Some(rend3_object), Some(node)) = (self.rend3_objects.get(&id), self.graph.get(id))
+        {
+            rend3_renderer.set_object_transform(&rend3_object.object, node.world_transform());
+        }
+    }
+
+    /// Updates the material of the rend3 object of `id`, reusing the texture which was
+    /// uploaded along with the object.
+    fn update_rend3_material(&self, id: NodeId, rend3_renderer: &Arc<rend3::Renderer>) {
+        let object = self.graph.get(id).and_then(|node| node.object.as_ref());
+        if let (Some(rend3_object), Some(object)) = (self.rend3_objects.get(&id), object) {
+            rend3_renderer.update_material(
+                &rend3_object.material,
+                self.rend3_material(id, &object.material, rend3_object.albedo_texture.as_ref()),
+            );
+        }
+    }
+
+    /// The material of the node `id` as drawn in the viewport, which includes the
+    /// selection highlight.
+    fn rend3_material(
+        &self,
+        id: NodeId,
+        material: &SceneMaterial,
+        albedo_texture: Option<&rend3::types::TextureHandle>,
+    ) -> rend3_routine::pbr::PbrMaterial {
+        let mut rend3_material = material.to_rend3_material(albedo_texture);
+        if self.is_selected(id) {
+            rend3_material.emissive =
+                rend3_routine::pbr::MaterialComponent::Value(SELECTION_HIGHLIGHT);
+        }
+        rend3_material
+    }
+
+    pub fn add_light(&mut self, light: SceneLight, rend3_renderer: &Arc<rend3::Renderer>) {
+        self.insert_light(self.lights.len(), light, rend3_renderer);
+    }
 
+    /// Adds `light` so that it ends up at `index` in `lights`.
+    pub fn insert_light(
+        &mut self,
+        index: usize,
+        light: SceneLight,
+        rend3_renderer: &Arc<rend3::Renderer>,
+    ) {
+        // We need to keep the light handle alive.
+        let handle = light
+            .to_rend3_directional_light()
+            .map(|l| rend3_renderer.add_directional_light(l));
+        self.lights.insert(index, light);
+        self.rend3_light_handles.insert(index, handle);
+    }
+
+    /// Replaces the light at `index`, e.g. with an edited copy of it.
+    pub fn set_light(
+        &mut self,
+        index: usize,
+        light: SceneLight,
+        rend3_renderer: &Arc<rend3::Renderer>,
+    ) {
+        let old = self.lights[index].to_rend3_directional_light();
+        let new = light.to_rend3_directional_light();
+        // The shadow map is sized when the light is added, so only a light which keeps
+        // its kind and resolution can be updated in place.
+        let keeps_shadow_map = match (&old, &new) {
+            (Some(old), Some(new)) => old.resolution == new.resolution,
+            _ => false,
+        };
+        match (&self.rend3_light_handles[index], new) {
+            (Some(handle), Some(new)) if keeps_shadow_map => {
+                rend3_renderer.update_directional_light(
+                    handle,
+                    rend3::types::DirectionalLightChange {
+                        color: Some(new.color),
+                        intensity: Some(new.intensity),
+                        direction: Some(new.direction),
+                        distance: Some(new.distance),
+                    },
+                );
+            }
+            // Dropping the old handle removes the old light.
+            (_, new) => {
+                let handle = new.map(|l| rend3_renderer.add_directional_light(l));
+                self.rend3_light_handles[index] = handle;
+            }
+        }
+        self.lights[index] = light;
+    }
+
+    /// Dropping the handle is what removes the light from the rend3 renderer.
+    pub fn remove_light(&mut self, index: usize) -> SceneLight {
+        self.rend3_light_handles.remove(index);
+        self.lights.remove(index)
+    }
+
+    /// Removes every node and light from the scene. Dropping the handles is what
+    /// removes them from the rend3 renderer.
+    pub fn clear(&mut self) {
+        self.graph = SceneGraph::new();
+        self.selection.clear();
+        self.rend3_objects.clear();
+        self.lights.clear();
+        self.rend3_light_handles.clear();
+    }
+
+    /// A copy of the scene which contains no renderer resources, suitable for writing
+    /// to disk.
+    pub fn to_description(&self) -> SceneDescription {
+        SceneDescription {
+            graph: self.graph.clone(),
+            lights: self.lights.clone(),
+            camera: self.camera.get_state(),
+            bookmarks: self.bookmarks.clone(),
+            cameras: self.cameras.clone(),
+        }
+    }
+
+    /// Replaces the contents of the scene with `description`. The caller is responsible
+    /// for sending the new camera data to the renderer.
+    pub fn load_description(
+        &mut self,
+        description: SceneDescription,
+        rend3_renderer: &Arc<rend3::Renderer>,
+    ) {
+        self.clear();
+        self.camera.set_state(&description.camera);
+        self.bookmarks = description.bookmarks;
+        self.cameras = description.cameras;
+
+        self.add_graph(description.graph, None, rend3_renderer);
+        for light in description.lights {
+            self.add_light(light, rend3_renderer);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelectionMode {
+    /// Select only the given nodes.
+    Replace,
+    /// Add the given nodes to the selection.
+    Extend,
+    /// Select the given nodes which aren't selected, and deselect those which are.
+    Toggle,
+}
+
+/// The renderer's copy of a node's object. Dropping it removes the object from the
+/// renderer.
+struct Rend3Object {
+    object: rend3::types::ResourceHandle<rend3::types::Object>,
+    /// Kept so that the material can be changed without recreating the object.
+    material: rend3::types::MaterialHandle,
+    /// Kept so that changing the material doesn't upload the texture again.
+    albedo_texture: Option<rend3::types::TextureHandle>,
+}
+
+/// The renderer-independent contents of a `SceneData`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SceneDescription {
+    pub graph: SceneGraph,
+    pub lights: Vec<SceneLight>,
+    pub camera: CameraState,
+    pub bookmarks: Vec<CameraBookmark>,
+    pub cameras: SceneCameras,
+}
+
+/// A view saved by the user to come back to later. Bookmarks belong to the editor, so
+/// renderers ignore them unless they are made into a `SceneCamera`.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct CameraBookmark {
+    pub name: String,
+    pub camera: CameraState,
+}
+
+/// A camera placed in the scene, which renderers can render the scene through.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneCamera {
+    pub name: String,
+    pub camera: CameraState,
+}
+
+impl From<CameraBookmark> for SceneCamera {
+    fn from(bookmark: CameraBookmark) -> Self {
         Self {
-            camera,
-            objects,
-            rend3_object_handles,
-            rend3_directional_handles,
+            name: bookmark.name,
+            camera: bookmark.camera,
+        }
+    }
+}
+
+/// The cameras placed in a scene, and which of them renders are made through.
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SceneCameras {
+    pub cameras: Vec<SceneCamera>,
+    /// Index into `cameras`. Without an active camera, renders use the viewport's view.
+    pub active: Option<usize>,
+}
+
+impl SceneCameras {
+    /// The camera renders are made through, if any.
+    pub fn active_camera(&self) -> Option<&SceneCamera> {
+        self.cameras.get(self.active?)
+    }
+
+    pub fn remove(&mut self, index: usize) -> SceneCamera {
+        self.active = match self.active {
+            Some(active) if active == index => None,
+            Some(active) if active > index => Some(active - 1),
+            active => active,
+        };
+        self.cameras.remove(index)
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneLight {
+    pub name: String,
+    pub color: glam::Vec3,
+    pub intensity: f32,
+    pub kind: SceneLightKind,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub enum SceneLightKind {
+    Directional {
+        /// Will be normalized by the renderer.
+        direction: glam::Vec3,
+        /// Distance from the camera the shadows are rendered to.
+        distance: f32,
+        /// Resolution of the shadow map.
+        resolution: u16,
+    },
+    Point {
+        position: glam::Vec3,
+        /// Distance after which the light has no effect, or `None` for no limit.
+        range: Option<f32>,
+    },
+    Spot {
+        position: glam::Vec3,
+        direction: glam::Vec3,
+        range: Option<f32>,
+        /// Angle in radians from the center of the cone where the falloff begins.
+        inner_cone_angle: f32,
+        /// Angle in radians from the center of the cone where the falloff ends.
+        outer_cone_angle: f32,
+    },
+    /// A rectangle which emits light from one side. Only render plugins draw these.
+    Area {
+        /// Center of the rectangle.
+        position: glam::Vec3,
+        /// The side the light comes out of. The rectangle is turned the way that turns
+        /// -z onto `direction`, with `width` along the turned x axis and `height` along
+        /// the turned y axis.
+        direction: glam::Vec3,
+        width: f32,
+        height: f32,
+    },
+}
+
+impl SceneLightKind {
+    /// A directional light with the default shadow settings.
+    pub fn directional(direction: glam::Vec3) -> Self {
+        Self::Directional {
+            direction,
+            distance: 400.0,
+            resolution: 2048,
+        }
+    }
+
+    /// One light of each kind, with reasonable settings for adding to a scene.
+    pub fn defaults() -> [Self; 4] {
+        let position = glam::Vec3::new(0.0, 3.0, 0.0);
+        let direction = glam::Vec3::NEG_Y;
+        [
+            Self::directional(glam::Vec3::new(-1.0, -4.0, 2.0)),
+            Self::Point {
+                position,
+                range: None,
+            },
+            Self::Spot {
+                position,
+                direction,
+                range: None,
+                inner_cone_angle: 0.0,
+                outer_cone_angle: std::f32::consts::FRAC_PI_4,
+            },
+            Self::Area {
+                position,
+                direction,
+                width: 1.0,
+                height: 1.0,
+            },
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Directional { .. } => "Directional",
+            Self::Point { .. } => "Point",
+            Self::Spot { .. } => "Spot",
+            Self::Area { .. } => "Area",
         }
     }
+
+    /// Where the light is, or `None` for directional lights, which are infinitely far
+    /// away.
+    pub fn position(&self) -> Option<glam::Vec3> {
+        match self {
+            Self::Directional { .. } => None,
+            Self::Point { position, .. }
+            | Self::Spot { position, .. }
+            | Self::Area { position, .. } => Some(*position),
+        }
+    }
+
+    /// Which way the light shines, or `None` for point lights, which shine every way.
+    pub fn direction(&self) -> Option<glam::Vec3> {
+        match self {
+            Self::Point { .. } => None,
+            Self::Directional { direction, .. }
+            | Self::Spot { direction, .. }
+            | Self::Area { direction, .. } => Some(*direction),
+        }
+    }
+}
+
+impl Default for SceneLight {
+    fn default() -> Self {
+        Self {
+            name: "Sun".to_string(),
+            color: glam::Vec3::ONE,
+            intensity: 10.0,
+            kind: SceneLightKind::directional(glam::Vec3::new(-1.0, -4.0, 2.0)),
+        }
+    }
+}
+
+impl SceneLight {
+    /// A white light named after its kind.
+    pub fn with_kind(kind: SceneLightKind) -> Self {
+        Self {
+            name: kind.name().to_string(),
+            color: glam::Vec3::ONE,
+            intensity: 10.0,
+            kind,
+        }
+    }
+
+    /// The viewport renderer only supports directional lights. Other kinds are kept in
+    /// the scene for the benefit of render plugins.
+    fn to_rend3_directional_light(&self) -> Option<rend3::types::DirectionalLight> {
+        match self.kind {
+            SceneLightKind::Directional {
+                direction,
+                distance,
+                resolution,
+            } => Some(rend3::types::DirectionalLight {
+                color: self.color,
+                intensity: self.intensity,
+                direction,
+                distance,
+                resolution,
+            }),
+            SceneLightKind::Point { .. }
+            | SceneLightKind::Spot { .. }
+            | SceneLightKind::Area { .. } => None,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SceneMaterial {
+    /// Multiplied with the albedo texture, if there is one.
+    pub albedo: glam::Vec4,
+    pub albedo_texture: Option<SceneTexture>,
+    pub roughness: f32,
+    pub metallic: f32,
 }
 
+impl Default for SceneMaterial {
+    fn default() -> Self {
+        Self {
+            albedo: glam::Vec4::new(0.0, 0.5, 0.5, 1.0),
+            albedo_texture: None,
+            roughness: 1.0,
+            metallic: 0.0,
+        }
+    }
+}
+
+impl SceneMaterial {
+    /// Uploads the albedo texture, if there is one.
+    fn add_rend3_texture(
+        &self,
+        rend3_renderer: &Arc<rend3::Renderer>,
+    ) -> Option<rend3::types::TextureHandle> {
+        self.albedo_texture
+            .as_ref()
+            .map(|texture| rend3_renderer.add_texture_2d(texture.to_rend3_texture()))
+    }
+
+    /// Builds the corresponding rend3 material. `albedo_texture` is the handle
+    /// `add_rend3_texture()` returned for this material's texture.
+    fn to_rend3_material(
+        &self,
+        albedo_texture: Option<&rend3::types::TextureHandle>,
+    ) -> rend3_routine::pbr::PbrMaterial {
+        let albedo = match albedo_texture {
+            Some(texture) => rend3_routine::pbr::AlbedoComponent::TextureValue {
+                texture: texture.clone(),
+                value: self.albedo,
+            },
+            None => rend3_routine::pbr::AlbedoComponent::Value(self.albedo),
+        };
+
+        rend3_routine::pbr::PbrMaterial {
+            albedo,
+            roughness_factor: Some(self.roughness),
+            metallic_factor: Some(self.metallic),
+            ..rend3_routine::pbr::PbrMaterial::default()
+        }
+    }
+}
+
+/// An sRGB, 8 bits per channel RGBA image. Project files store it as base64 encoded
+/// PNG, since JSON arrays of the raw pixels would be many times larger.
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(try_from = "EncodedTexture")]
+pub struct SceneTexture {
+    pub width: u32,
+    pub height: u32,
+    /// Rows from top to bottom, 4 bytes per pixel.
+    pub data: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EncodedTexture {
+    png: String,
+}
+
+impl Serialize for SceneTexture {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let png = self
+            .to_png()
+            .map_err(|e| serde::ser::Error::custom(format!("{:#}", e)))?;
+        EncodedTexture {
+            png: base64::encode(png),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl TryFrom<EncodedTexture> for SceneTexture {
+    type Error = String;
+
+    fn try_from(encoded: EncodedTexture) -> Result<Self, Self::Error> {
+        let png = base64::decode(encoded.png).map_err(|e| format!("invalid texture: {}", e))?;
+        Self::from_png(&png).map_err(|e| format!("{:#}", e))
+    }
+}
+
+impl SceneTexture {
+    pub fn to_png(&self) -> anyhow::Result<Vec<u8>> {
+        use image::ImageEncoder;
+
+        if self.data.len() as u64 != 4 * self.width as u64 * self.height as u64 {
+            anyhow::bail!(
+                "texture has {} bytes of data, but is {}x{} pixels",
+                self.data.len(),
+                self.width,
+                self.height
+            );
+        }
+
+        let mut png = Vec::new();
+        image::codecs::png::PngEncoder::new(&mut png)
+            .write_image(
+                &self.data,
+                self.width,
+                self.height,
+                image::ExtendedColorType::Rgba8,
+            )
+            .context("failed to encode texture as PNG")?;
+        Ok(png)
+    }
+
+    pub fn from_png(png: &[u8]) -> anyhow::Result<Self> {
+        let image = image::load_from_memory_with_format(png, image::ImageFormat::Png)
+            .context("failed to decode texture PNG")?
+            .to_rgba8();
+        Ok(Self {
+            width: image.width(),
+            height: image.height(),
+            data: image.into_raw(),
+        })
+    }
+
+    fn to_rend3_texture(&self) -> rend3::types::Texture {
+        rend3::types::Texture {
+            label: None,
+            data: self.data.clone(),
+            format: rend3::types::TextureFormat::Rgba8UnormSrgb,
+            size: glam::UVec2::new(self.width, self.height),
+            mip_count: rend3::types::MipmapCount::Maximum,
+            mip_source: rend3::types::MipmapSource::Generated,
+        }
+    }
+}
+
+/// A mesh and the material it is drawn with. Objects are placed in the scene by the
+/// `SceneNode` which holds them.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct SceneObject {
     mesh: RawMesh,
+    pub material: SceneMaterial,
 }
 
 impl SceneObject {
+    fn new(mesh: RawMesh, material: SceneMaterial) -> Self {
+        Self { mesh, material }
+    }
+
+    /// Roughly how many bytes the mesh and textures take up.
+    pub fn memory_size(&self) -> usize {
+        let mesh = &self.mesh;
+        let texture = self
+            .material
+            .albedo_texture
+            .as_ref()
+            .map_or(0, |texture| texture.data.len());
+        std::mem::size_of_val(mesh.vertices.as_slice())
+            + std::mem::size_of_val(mesh.normals.as_slice())
+            + std::mem::size_of_val(mesh.uvs.as_slice())
+            + std::mem::size_of_val(mesh.indices.as_slice())
+            + texture
+    }
+
     pub fn create_basic_cube() -> Self {
         let vertex_positions = [
             // far side (0.0, 0.0, 1.0)
@@ -94,25 +896,40 @@ impl SceneObject {
             20, 21, 22, 22, 23, 20, // bottom
         ];
 
-        Self {
-            mesh: RawMesh {
+        Self::new(
+            RawMesh {
                 vertices: vertex_positions.to_vec(),
+                normals: Vec::new(),
+                uvs: Vec::new(),
                 indices: index_data.to_vec(),
             },
-        }
+            SceneMaterial::default(),
+        )
     }
 
-    pub fn add_to_rend3_renderer(
+    /// `transform` is object -> world space. `material` is used instead of the object's
+    /// own material, so that the viewport can draw it differently.
+    fn add_to_rend3_renderer(
         &self,
+        transform: glam::Mat4,
+        material: rend3_routine::pbr::PbrMaterial,
+        albedo_texture: Option<rend3::types::TextureHandle>,
         rend3_renderer: &Arc<rend3::Renderer>,
-    ) -> anyhow::Result<rend3::types::ResourceHandle<rend3::types::Object>> {
-        // Create mesh and calculate smooth normals based on vertices
-        let mesh = rend3::types::MeshBuilder::new(
+    ) -> anyhow::Result<Rend3Object> {
+        // Create mesh. If the mesh has no normals of its own, rend3 calculates smooth
+        // normals based on vertices.
+        let mut mesh_builder = rend3::types::MeshBuilder::new(
             self.mesh.vertices.clone(),
             rend3::types::Handedness::Left,
         )
-        .with_indices(self.mesh.indices.clone())
-        .build()?;
+        .with_indices(self.mesh.indices.clone());
+        if !self.mesh.normals.is_empty() {
+            mesh_builder = mesh_builder.with_vertex_normals(self.mesh.normals.clone());
+        }
+        if !self.mesh.uvs.is_empty() {
+            mesh_builder = mesh_builder.with_vertex_texture_coordinates_0(self.mesh.uvs.clone());
+        }
+        let mesh = mesh_builder.build()?;
 
         // Add mesh to renderer's world.
         //
@@ -120,18 +937,13 @@ impl SceneObject {
         // make an object.
         let mesh_handle = rend3_renderer.add_mesh(mesh);
 
-        // Add PBR material with all defaults except a single color.
-        let material = rend3_routine::pbr::PbrMaterial {
-            albedo: rend3_routine::pbr::AlbedoComponent::Value(glam::Vec4::new(0.0, 0.5, 0.5, 1.0)),
-            ..rend3_routine::pbr::PbrMaterial::default()
-        };
         let material_handle = rend3_renderer.add_material(material);
 
         // Combine the mesh and the material with a location to give an object.
         let object = rend3::types::Object {
             mesh_kind: rend3::types::ObjectMeshKind::Static(mesh_handle),
-            material: material_handle,
-            transform: glam::Mat4::IDENTITY,
+            material: material_handle.clone(),
+            transform,
         };
 
         // Creating an object will hold onto both the mesh and the material
@@ -140,11 +952,48 @@ impl SceneObject {
         // We need to keep the object handle alive.
         let object_handle = rend3_renderer.add_object(object);
 
-        Ok(object_handle)
+        Ok(Rend3Object {
+            object: object_handle,
+            material: material_handle,
+            albedo_texture,
+        })
     }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 struct RawMesh {
     vertices: Vec<glam::Vec3>,
+    /// One per vertex, or empty to have the renderer compute smooth normals.
+    normals: Vec<glam::Vec3>,
+    /// One per vertex, or empty if the mesh isn't textured.
+    uvs: Vec<glam::Vec2>,
     indices: Vec<u32>,
 }
+
+/// Converts between the right-handed coordinates used by interchange formats like OBJ
+/// and glTF and the left-handed coordinates used by the renderer. Mirroring the z axis
+/// turns triangles inside out, so meshes also need `flip_winding()`. The conversion is
+/// its own inverse.
+fn flip_handedness(v: glam::Vec3) -> glam::Vec3 {
+    glam::Vec3::new(v.x, v.y, -v.z)
+}
+
+/// Reverses the winding of every triangle in `indices`, which turns front faces into
+/// back faces and vice versa.
+fn flip_winding(indices: &mut [u32]) {
+    for triangle in indices.chunks_exact_mut(3) {
+        triangle.swap(1, 2);
+    }
+}
+
+/// The equivalent of `flip_handedness()` for transforms: if `t` maps right-handed
+/// coordinates to right-handed parent coordinates, the result does the same for
+/// left-handed coordinates. Mirroring a rotation in z reverses the x and y components
+/// of its axis.
+fn flip_handedness_of_transform(t: Transform) -> Transform {
+    Transform {
+        translation: flip_handedness(t.translation),
+        rotation: glam::Quat::from_xyzw(-t.rotation.x, -t.rotation.y, t.rotation.z, t.rotation.w),
+        scale: t.scale,
+    }
+}
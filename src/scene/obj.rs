@@ -0,0 +1,897 @@
+//! Wavefront OBJ (and MTL) import and export.
+//!
+//! Every group (`o` or `g`) in the file becomes its own root node. Since a scene
+//! object only has a single material, a group which switches material part way
+//! through with `usemtl` is split into one node per material.
+//!
+//! OBJ has no notion of transforms or hierarchy, so exported objects have their world
+//! transform baked into their vertices.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+    path::Path,
+};
+
+use anyhow::Context;
+
+use super::{
+    flip_handedness,
+    graph::{SceneGraph, SceneNode, Transform},
+    RawMesh, SceneMaterial, SceneObject,
+};
+
+/// Name given to faces which appear before any `o` or `g` statement.
+const DEFAULT_GROUP_NAME: &str = "default";
+
+pub fn import_obj(path: &Path) -> anyhow::Result<SceneGraph> {
+    let source = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+
+    parse_obj(&source, path.parent())
+        .with_context(|| format!("failed to import {}", path.display()))
+}
+
+/// Parses the contents of an OBJ file. Material libraries referenced with `mtllib`
+/// are looked up relative to `material_directory`; if it is `None` they are skipped
+/// and every object gets the default material.
+pub fn parse_obj(source: &str, material_directory: Option<&Path>) -> anyhow::Result<SceneGraph> {
+    let mut parser = ObjParser::new(material_directory);
+
+    for (line_index, line) in source.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut tokens = line.split_whitespace();
+        let keyword = tokens.next().unwrap();
+        let args: Vec<&str> = tokens.collect();
+
+        parser
+            .parse_statement(keyword, &args)
+            .with_context(|| format!("line {}: '{}'", line_index + 1, line))?;
+    }
+
+    let graph = parser.finish();
+    if graph.is_empty() {
+        anyhow::bail!("no faces found");
+    }
+
+    Ok(graph)
+}
+
+/// Parses the contents of an MTL file into materials keyed by name.
+pub fn parse_mtl(source: &str) -> anyhow::Result<HashMap<String, SceneMaterial>> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlEntry)> = None;
+    let mut warned_about_textures = false;
+
+    for (line_index, line) in source.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut tokens = line.split_whitespace();
+        let keyword = tokens.next().unwrap();
+        let args: Vec<&str> = tokens.collect();
+
+        let result = (|| -> anyhow::Result<()> {
+            if keyword == "newmtl" {
+                if let Some((name, entry)) = current.take() {
+                    materials.insert(name, entry.to_scene_material());
+                }
+                let name = args.join(" ");
+                if name.is_empty() {
+                    anyhow::bail!("material has no name");
+                }
+                current = Some((name, MtlEntry::default()));
+                return Ok(());
+            }
+
+            let entry = match &mut current {
+                Some((_, entry)) => entry,
+                // Statements before the first material have nothing to apply to.
+                None => return Ok(()),
+            };
+
+            match keyword {
+                "Kd" => {
+                    let rgb = parse_floats::<3>(&args)?;
+                    entry.diffuse = Some(glam::Vec3::from(rgb));
+                }
+                "d" => entry.alpha = Some(parse_floats::<1>(&args)?[0]),
+                "Tr" => entry.alpha = Some(1.0 - parse_floats::<1>(&args)?[0]),
+                "Ns" => entry.shininess = Some(parse_floats::<1>(&args)?[0]),
+                "Pr" => entry.roughness = Some(parse_floats::<1>(&args)?[0]),
+                "Pm" => entry.metallic = Some(parse_floats::<1>(&args)?[0]),
+                k if (k.starts_with("map_") || k == "bump" || k == "disp")
+                    && !warned_about_textures =>
+                {
+                    log::warn!("OBJ import: texture maps are not supported and will be ignored");
+                    warned_about_textures = true;
+                }
+                _ => {}
+            }
+
+            Ok(())
+        })();
+        result.with_context(|| format!("line {}: '{}'", line_index + 1, line))?;
+    }
+
+    if let Some((name, entry)) = current.take() {
+        materials.insert(name, entry.to_scene_material());
+    }
+
+    Ok(materials)
+}
+
+/// Writes every object in `graph` to an OBJ file at `path`, and their materials to an
+/// MTL file with the same name next to it.
+pub fn export_obj(graph: &SceneGraph, path: &Path) -> anyhow::Result<()> {
+    let mtl_path = path.with_extension("mtl");
+    let mtl_file_name = mtl_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .with_context(|| format!("{} is not a valid file name", mtl_path.display()))?;
+
+    std::fs::write(path, write_obj(graph, Some(mtl_file_name)))
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    std::fs::write(&mtl_path, write_mtl(graph))
+        .with_context(|| format!("failed to write {}", mtl_path.display()))?;
+
+    Ok(())
+}
+
+/// Produces the contents of an OBJ file containing every object in `graph`, each as
+/// its own `o` group. If `material_library` is given, the file references it and
+/// every object uses the material `write_mtl()` writes for it.
+pub fn write_obj(graph: &SceneGraph, material_library: Option<&str>) -> String {
+    // Writing to a `String` can't fail, so the results of `write!` are ignored.
+    let mut obj = String::new();
+    if let Some(material_library) = material_library {
+        let _ = writeln!(obj, "mtllib {}", material_library);
+    }
+
+    let objects = objects(graph);
+    let object_names = object_names(&objects);
+    let material_names = material_names(&object_names);
+
+    // Indices are global to the file, and start at 1.
+    let mut position_offset = 1;
+    let mut uv_offset = 1;
+    let mut normal_offset = 1;
+
+    for (((node, object), object_name), material_name) in
+        objects.iter().zip(&object_names).zip(&material_names)
+    {
+        let mesh = &object.mesh;
+        let transform = node.world_transform();
+
+        let _ = writeln!(obj, "o {}", object_name);
+        if material_library.is_some() {
+            let _ = writeln!(obj, "usemtl {}", material_name);
+        }
+
+        let normal_transform = glam::Mat3::from_mat4(transform).inverse().transpose();
+        for vertex in &mesh.vertices {
+            let v = flip_handedness(transform.transform_point3(*vertex));
+            let _ = writeln!(obj, "v {} {} {}", v.x, v.y, v.z);
+        }
+        for uv in &mesh.uvs {
+            let _ = writeln!(obj, "vt {} {}", uv.x, 1.0 - uv.y);
+        }
+        for normal in &mesh.normals {
+            let n = flip_handedness(normal_transform * *normal).normalize_or_zero();
+            let _ = writeln!(obj, "vn {} {} {}", n.x, n.y, n.z);
+        }
+
+        // Converting to right-handed coordinates turns the triangles inside out, and so
+        // does a mirroring transform once it's baked into the vertices. If both happen
+        // they cancel out.
+        let mirrored = transform.determinant() < 0.0;
+        for triangle in mesh.indices.chunks_exact(3) {
+            let triangle = if mirrored {
+                [triangle[0], triangle[1], triangle[2]]
+            } else {
+                [triangle[0], triangle[2], triangle[1]]
+            };
+
+            obj.push('f');
+            for index in triangle {
+                let index = index as usize;
+                let position = position_offset + index;
+                let _ = match (mesh.uvs.is_empty(), mesh.normals.is_empty()) {
+                    (true, true) => write!(obj, " {}", position),
+                    (false, true) => write!(obj, " {}/{}", position, uv_offset + index),
+                    (true, false) => write!(obj, " {}//{}", position, normal_offset + index),
+                    (false, false) => write!(
+                        obj,
+                        " {}/{}/{}",
+                        position,
+                        uv_offset + index,
+                        normal_offset + index
+                    ),
+                };
+            }
+            obj.push('\n');
+        }
+
+        position_offset += mesh.vertices.len();
+        uv_offset += mesh.uvs.len();
+        normal_offset += mesh.normals.len();
+    }
+
+    obj
+}
+
+/// Produces the contents of an MTL file with one material per object in `graph`,
+/// named to match `write_obj()`.
+pub fn write_mtl(graph: &SceneGraph) -> String {
+    let mut mtl = String::new();
+    let objects = objects(graph);
+    let material_names = material_names(&object_names(&objects));
+
+    if objects
+        .iter()
+        .any(|(_, object)| object.material.albedo_texture.is_some())
+    {
+        log::warn!("OBJ export: textures are not supported and will be left out");
+    }
+
+    for ((_, object), material_name) in objects.iter().zip(&material_names) {
+        let material = &object.material;
+        let albedo = material.albedo;
+        let roughness = material.roughness.clamp(0.0, 1.0);
+        // For programs which don't understand the PBR extension, invert the mapping
+        // from the specular exponent used on import.
+        let shininess = (2.0 / (roughness * roughness).max(0.001) - 2.0).min(1000.0);
+
+        let _ = writeln!(mtl, "newmtl {}", material_name);
+        let _ = writeln!(mtl, "Kd {} {} {}", albedo.x, albedo.y, albedo.z);
+        let _ = writeln!(mtl, "d {}", albedo.w);
+        let _ = writeln!(mtl, "Ns {}", shininess);
+        let _ = writeln!(mtl, "Pr {}", roughness);
+        let _ = writeln!(mtl, "Pm {}", material.metallic);
+        mtl.push('\n');
+    }
+
+    mtl
+}
+
+/// The subset of an MTL material we understand, before being mapped onto the PBR
+/// parameters of a `SceneMaterial`.
+#[derive(Default)]
+struct MtlEntry {
+    diffuse: Option<glam::Vec3>,
+    alpha: Option<f32>,
+    shininess: Option<f32>,
+    roughness: Option<f32>,
+    metallic: Option<f32>,
+}
+
+impl MtlEntry {
+    fn to_scene_material(&self) -> SceneMaterial {
+        let default = SceneMaterial::default();
+
+        let albedo = match self.diffuse {
+            Some(diffuse) => diffuse.extend(self.alpha.unwrap_or(1.0)),
+            None => default.albedo,
+        };
+
+        // The PBR extension's roughness wins. Otherwise approximate it from the Phong
+        // specular exponent, using the usual Blinn-Phong to Beckmann mapping.
+        let roughness = self
+            .roughness
+            .or_else(|| self.shininess.map(|ns| (2.0 / (ns.max(0.0) + 2.0)).sqrt()))
+            .unwrap_or(default.roughness)
+            .clamp(0.0, 1.0);
+
+        let metallic = self.metallic.unwrap_or(default.metallic).clamp(0.0, 1.0);
+
+        SceneMaterial {
+            albedo,
+            albedo_texture: None,
+            roughness,
+            metallic,
+        }
+    }
+}
+
+/// Accumulates the triangles for one (group, material) pair. OBJ indexes positions,
+/// normals and texture coordinates separately, whereas the renderer wants a single
+/// index per vertex, so every distinct combination becomes its own vertex.
+struct ObjectBuilder {
+    group_name: String,
+    material_name: Option<String>,
+    vertices: Vec<glam::Vec3>,
+    normals: Vec<Option<glam::Vec3>>,
+    uvs: Vec<Option<glam::Vec2>>,
+    indices: Vec<u32>,
+    vertex_lookup: HashMap<FaceVertex, u32>,
+}
+
+impl ObjectBuilder {
+    fn new(group_name: String, material_name: Option<String>) -> Self {
+        Self {
+            group_name,
+            material_name,
+            vertices: Vec::new(),
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            indices: Vec::new(),
+            vertex_lookup: HashMap::new(),
+        }
+    }
+
+    fn add_vertex(&mut self, vertex: FaceVertex, attributes: &VertexAttributes) -> u32 {
+        if let Some(index) = self.vertex_lookup.get(&vertex) {
+            return *index;
+        }
+
+        let index = self.vertices.len() as u32;
+        self.vertices
+            .push(flip_handedness(attributes.positions[vertex.position]));
+        self.normals.push(
+            vertex
+                .normal
+                .map(|n| flip_handedness(attributes.normals[n]).normalize_or_zero()),
+        );
+        // OBJ puts the texture origin at the bottom left, the renderer at the top left.
+        self.uvs.push(vertex.uv.map(|uv| {
+            let uv = attributes.uvs[uv];
+            glam::Vec2::new(uv.x, 1.0 - uv.y)
+        }));
+        self.vertex_lookup.insert(vertex, index);
+
+        index
+    }
+
+    fn build(self, name: String, materials: &HashMap<String, SceneMaterial>) -> SceneNode {
+        // A mesh either has normals (or texture coordinates) for every vertex or for
+        // none of them. If the file only specified some, fall back to none.
+        let normals: Vec<glam::Vec3> = if self.normals.iter().all(|n| n.is_some()) {
+            self.normals.into_iter().flatten().collect()
+        } else {
+            if self.normals.iter().any(|n| n.is_some()) {
+                log::warn!(
+                    "OBJ import: '{}' only has normals for some vertices, recomputing them",
+                    name
+                );
+            }
+            Vec::new()
+        };
+        let uvs: Vec<glam::Vec2> = if self.uvs.iter().all(|uv| uv.is_some()) {
+            self.uvs.into_iter().flatten().collect()
+        } else {
+            Vec::new()
+        };
+
+        let material = match &self.material_name {
+            Some(material_name) => materials.get(material_name).cloned().unwrap_or_else(|| {
+                log::warn!(
+                    "OBJ import: material '{}' was never defined, using the default material",
+                    material_name
+                );
+                SceneMaterial::default()
+            }),
+            None => SceneMaterial::default(),
+        };
+
+        let object = SceneObject::new(
+            RawMesh {
+                vertices: self.vertices,
+                normals,
+                uvs,
+                indices: self.indices,
+            },
+            material,
+        );
+        SceneNode::with_object(name, Transform::IDENTITY, object)
+    }
+}
+
+/// Every position, normal and texture coordinate defined so far, in file order.
+#[derive(Default)]
+struct VertexAttributes {
+    positions: Vec<glam::Vec3>,
+    normals: Vec<glam::Vec3>,
+    uvs: Vec<glam::Vec2>,
+}
+
+/// Zero-based indices into the position, texture coordinate and normal lists.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct FaceVertex {
+    position: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+struct ObjParser<'a> {
+    material_directory: Option<&'a Path>,
+    attributes: VertexAttributes,
+    materials: HashMap<String, SceneMaterial>,
+    builders: Vec<ObjectBuilder>,
+    /// Index into `builders` which faces are currently added to.
+    current_builder: Option<usize>,
+    group_name: String,
+    material_name: Option<String>,
+}
+
+impl<'a> ObjParser<'a> {
+    fn new(material_directory: Option<&'a Path>) -> Self {
+        Self {
+            material_directory,
+            attributes: VertexAttributes::default(),
+            materials: HashMap::new(),
+            builders: Vec::new(),
+            current_builder: None,
+            group_name: DEFAULT_GROUP_NAME.to_string(),
+            material_name: None,
+        }
+    }
+
+    fn parse_statement(&mut self, keyword: &str, args: &[&str]) -> anyhow::Result<()> {
+        match keyword {
+            // Extra components (w, or vertex colors) are ignored.
+            "v" => self
+                .attributes
+                .positions
+                .push(glam::Vec3::from(parse_floats::<3>(args)?)),
+            "vn" => self
+                .attributes
+                .normals
+                .push(glam::Vec3::from(parse_floats::<3>(args)?)),
+            "vt" => {
+                if args.is_empty() {
+                    anyhow::bail!("expected at least 1 value, found none");
+                }
+                let u = parse_float(args[0])?;
+                let v = args
+                    .get(1)
+                    .map(|v| parse_float(v))
+                    .transpose()?
+                    .unwrap_or(0.0);
+                self.attributes.uvs.push(glam::Vec2::new(u, v));
+            }
+            "f" => self.parse_face(args)?,
+            "o" | "g" => {
+                self.group_name = if args.is_empty() {
+                    DEFAULT_GROUP_NAME.to_string()
+                } else {
+                    args.join(" ")
+                };
+                self.current_builder = None;
+            }
+            "usemtl" => {
+                self.material_name = if args.is_empty() {
+                    None
+                } else {
+                    Some(args.join(" "))
+                };
+                self.current_builder = None;
+            }
+            "mtllib" => self.load_material_libraries(args),
+            // Smoothing groups, lines, points and free-form geometry are not supported.
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn parse_face(&mut self, args: &[&str]) -> anyhow::Result<()> {
+        if args.len() < 3 {
+            anyhow::bail!("a face needs at least 3 vertices, found {}", args.len());
+        }
+
+        let face_vertices = args
+            .iter()
+            .map(|arg| self.parse_face_vertex(arg))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let builder_index = self.get_current_builder();
+        let builder = &mut self.builders[builder_index];
+        let indices: Vec<u32> = face_vertices
+            .into_iter()
+            .map(|vertex| builder.add_vertex(vertex, &self.attributes))
+            .collect();
+
+        // Triangulate polygons as a fan around the first vertex. The winding is reversed
+        // along with the handedness of the positions.
+        for i in 1..(indices.len() - 1) {
+            builder
+                .indices
+                .extend_from_slice(&[indices[0], indices[i + 1], indices[i]]);
+        }
+
+        Ok(())
+    }
+
+    /// Parses one of `v`, `v/vt`, `v//vn` or `v/vt/vn`.
+    fn parse_face_vertex(&self, arg: &str) -> anyhow::Result<FaceVertex> {
+        let mut parts = arg.split('/');
+
+        let attributes = &self.attributes;
+        let position = resolve_index(
+            parts.next().unwrap_or(""),
+            attributes.positions.len(),
+            "vertex",
+        )?;
+        let uv = match parts.next() {
+            Some(s) if !s.is_empty() => Some(resolve_index(
+                s,
+                attributes.uvs.len(),
+                "texture coordinate",
+            )?),
+            _ => None,
+        };
+        let normal = match parts.next() {
+            Some(s) if !s.is_empty() => Some(resolve_index(s, attributes.normals.len(), "normal")?),
+            _ => None,
+        };
+        if parts.next().is_some() {
+            anyhow::bail!("malformed face vertex '{}'", arg);
+        }
+
+        Ok(FaceVertex {
+            position,
+            uv,
+            normal,
+        })
+    }
+
+    /// Finds the builder for the current group and material, creating one if needed.
+    fn get_current_builder(&mut self) -> usize {
+        if let Some(index) = self.current_builder {
+            return index;
+        }
+
+        let existing = self.builders.iter().position(|builder| {
+            builder.group_name == self.group_name && builder.material_name == self.material_name
+        });
+        let index = existing.unwrap_or_else(|| {
+            self.builders.push(ObjectBuilder::new(
+                self.group_name.clone(),
+                self.material_name.clone(),
+            ));
+            self.builders.len() - 1
+        });
+        self.current_builder = Some(index);
+
+        index
+    }
+
+    /// Missing or broken material libraries aren't fatal: the geometry is still useful.
+    fn load_material_libraries(&mut self, args: &[&str]) {
+        let Some(directory) = self.material_directory else {
+            return;
+        };
+
+        for file_name in args {
+            let path = directory.join(file_name);
+            let parsed = std::fs::read_to_string(&path)
+                .with_context(|| format!("failed to read {}", path.display()))
+                .and_then(|source| parse_mtl(&source));
+
+            match parsed {
+                Ok(materials) => self.materials.extend(materials),
+                Err(e) => log::warn!("OBJ import: skipping material library: {:#}", e),
+            }
+        }
+    }
+
+    fn finish(self) -> SceneGraph {
+        let materials = self.materials;
+        let builders: Vec<ObjectBuilder> = self
+            .builders
+            .into_iter()
+            .filter(|builder| !builder.indices.is_empty())
+            .collect();
+
+        // Only mention the material in the name if it's needed to tell objects apart.
+        let mut group_counts: HashMap<String, usize> = HashMap::new();
+        for builder in &builders {
+            *group_counts.entry(builder.group_name.clone()).or_default() += 1;
+        }
+
+        let mut graph = SceneGraph::new();
+        for builder in builders {
+            let name = match (&builder.material_name, group_counts[&builder.group_name]) {
+                (Some(material_name), count) if count > 1 => {
+                    format!("{} ({})", builder.group_name, material_name)
+                }
+                _ => builder.group_name.clone(),
+            };
+            graph.add(builder.build(name, &materials), None);
+        }
+
+        graph
+    }
+}
+
+/// The nodes of `graph` which have an object, in the order they are exported.
+fn objects(graph: &SceneGraph) -> Vec<(&SceneNode, &SceneObject)> {
+    graph
+        .iter()
+        .filter_map(|(_, node)| node.object.as_ref().map(|object| (node, object)))
+        .collect()
+}
+
+/// Object names as they are written to an OBJ file: without comment characters or
+/// repeated whitespace, which wouldn't survive being read back in, and unique, so that
+/// objects don't get merged together on import.
+fn object_names(objects: &[(&SceneNode, &SceneObject)]) -> Vec<String> {
+    make_unique(objects.iter().map(|(node, _)| {
+        let name = node
+            .name
+            .replace('#', "_")
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if name.is_empty() {
+            DEFAULT_GROUP_NAME.to_string()
+        } else {
+            name
+        }
+    }))
+}
+
+/// Most programs don't allow spaces in material names.
+fn material_names(object_names: &[String]) -> Vec<String> {
+    make_unique(object_names.iter().map(|name| name.replace(' ', "_")))
+}
+
+/// Appends a number to any name which has already been used.
+fn make_unique(names: impl Iterator<Item = String>) -> Vec<String> {
+    let mut used = HashSet::new();
+
+    names
+        .map(|name| {
+            let mut unique_name = name.clone();
+            let mut suffix = 1;
+            while !used.insert(unique_name.clone()) {
+                unique_name = format!("{}.{:03}", name, suffix);
+                suffix += 1;
+            }
+            unique_name
+        })
+        .collect()
+}
+
+fn strip_comment(line: &str) -> &str {
+    match line.find('#') {
+        Some(index) => &line[..index],
+        None => line,
+    }
+}
+
+fn parse_float(s: &str) -> anyhow::Result<f32> {
+    s.parse::<f32>()
+        .map_err(|_| anyhow::anyhow!("expected a number, found '{}'", s))
+}
+
+/// Parses the first `N` arguments as numbers. Any further arguments are ignored.
+fn parse_floats<const N: usize>(args: &[&str]) -> anyhow::Result<[f32; N]> {
+    if args.len() < N {
+        anyhow::bail!("expected {} values, found {}", N, args.len());
+    }
+
+    let mut values = [0.0; N];
+    for (value, arg) in values.iter_mut().zip(args) {
+        *value = parse_float(arg)?;
+    }
+
+    Ok(values)
+}
+
+/// Turns a one-based (or negative, relative to the end) OBJ index into a zero-based
+/// index, checking it refers to an element which has already been defined.
+fn resolve_index(s: &str, len: usize, kind: &str) -> anyhow::Result<usize> {
+    let index: i64 = s
+        .parse()
+        .map_err(|_| anyhow::anyhow!("expected a {} index, found '{}'", kind, s))?;
+
+    let resolved = if index > 0 {
+        index - 1
+    } else if index < 0 {
+        len as i64 + index
+    } else {
+        anyhow::bail!("{} indices start at 1, found 0", kind);
+    };
+
+    if resolved < 0 || resolved >= len as i64 {
+        anyhow::bail!(
+            "{} index {} is out of range ({} defined so far)",
+            kind,
+            index,
+            len
+        );
+    }
+
+    Ok(resolved as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A unit quad in the xy plane, facing -z, with normals and texture coordinates.
+    fn quad() -> RawMesh {
+        RawMesh {
+            vertices: vec![
+                glam::Vec3::new(0.0, 0.0, 0.0),
+                glam::Vec3::new(0.0, 1.0, 0.0),
+                glam::Vec3::new(1.0, 1.0, 0.0),
+                glam::Vec3::new(1.0, 0.0, 0.0),
+            ],
+            normals: vec![glam::Vec3::NEG_Z; 4],
+            uvs: vec![
+                glam::Vec2::new(0.0, 1.0),
+                glam::Vec2::new(0.0, 0.0),
+                glam::Vec2::new(1.0, 0.0),
+                glam::Vec2::new(1.0, 1.0),
+            ],
+            indices: vec![0, 1, 2, 2, 3, 0],
+        }
+    }
+
+    fn material(albedo: glam::Vec4, roughness: f32, metallic: f32) -> SceneMaterial {
+        SceneMaterial {
+            albedo,
+            albedo_texture: None,
+            roughness,
+            metallic,
+        }
+    }
+
+    /// A quad below a transformed parent, and one mirrored by a negative scale.
+    fn test_graph() -> SceneGraph {
+        let mut graph = SceneGraph::new();
+        let parent = graph.add(
+            SceneNode::new(
+                "parent".to_string(),
+                Transform {
+                    translation: glam::Vec3::new(1.0, 2.0, 3.0),
+                    rotation: glam::Quat::from_rotation_y(0.5),
+                    scale: glam::Vec3::splat(2.0),
+                },
+            ),
+            None,
+        );
+        graph.add(
+            SceneNode::with_object(
+                "quad".to_string(),
+                Transform {
+                    translation: glam::Vec3::new(0.0, 1.0, 0.0),
+                    ..Transform::IDENTITY
+                },
+                SceneObject::new(
+                    quad(),
+                    material(glam::Vec4::new(0.25, 0.5, 0.75, 1.0), 0.25, 0.5),
+                ),
+            ),
+            Some(parent),
+        );
+        graph.add(
+            SceneNode::with_object(
+                "mirrored quad".to_string(),
+                Transform {
+                    scale: glam::Vec3::new(-1.0, 1.0, 1.0),
+                    ..Transform::IDENTITY
+                },
+                SceneObject::new(
+                    quad(),
+                    material(glam::Vec4::new(1.0, 0.0, 0.0, 0.5), 0.75, 0.0),
+                ),
+            ),
+            None,
+        );
+        graph
+    }
+
+    /// The corners of every triangle of `object`: position and normal in world space,
+    /// and texture coordinates.
+    fn triangles(
+        object: &SceneObject,
+        transform: glam::Mat4,
+    ) -> Vec<[(glam::Vec3, glam::Vec3, glam::Vec2); 3]> {
+        let mesh = &object.mesh;
+        let normal_transform = glam::Mat3::from_mat4(transform).inverse().transpose();
+        let corner = |index: u32| {
+            let index = index as usize;
+            (
+                transform.transform_point3(mesh.vertices[index]),
+                (normal_transform * mesh.normals[index]).normalize(),
+                mesh.uvs[index],
+            )
+        };
+        mesh.indices
+            .chunks_exact(3)
+            .map(|triangle| {
+                [
+                    corner(triangle[0]),
+                    corner(triangle[1]),
+                    corner(triangle[2]),
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn write_obj_converts_to_right_handed_coordinates() {
+        let mut graph = SceneGraph::new();
+        let mesh = RawMesh {
+            vertices: vec![
+                glam::Vec3::new(0.0, 0.0, 1.0),
+                glam::Vec3::new(1.0, 0.0, 1.0),
+                glam::Vec3::new(0.0, 1.0, 1.0),
+            ],
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            indices: vec![0, 1, 2],
+        };
+        let object = SceneObject::new(mesh, SceneMaterial::default());
+        graph.add(
+            SceneNode::with_object("triangle".to_string(), Transform::IDENTITY, object),
+            None,
+        );
+
+        let obj = write_obj(&graph, None);
+        let lines: Vec<&str> = obj.lines().collect();
+        assert_eq!(
+            lines,
+            ["o triangle", "v 0 0 -1", "v 1 0 -1", "v 0 1 -1", "f 1 3 2"]
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let graph = test_graph();
+
+        let directory =
+            std::env::temp_dir().join(format!("ekki-obj-round-trip-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("scene.mtl"), write_mtl(&graph)).unwrap();
+        let source = write_obj(&graph, Some("scene.mtl"));
+        let imported = parse_obj(&source, Some(&directory));
+        std::fs::remove_dir_all(&directory).unwrap();
+        let imported = imported.unwrap();
+
+        let expected = objects(&graph);
+        let actual = objects(&imported);
+        assert_eq!(actual.len(), expected.len());
+        for ((expected_node, expected_object), (node, object)) in expected.iter().zip(&actual) {
+            assert_eq!(node.name, expected_node.name);
+            // The transform is baked into the vertices.
+            assert_eq!(*node.transform(), Transform::IDENTITY);
+
+            let transform = expected_node.world_transform();
+            let mut expected_triangles = triangles(expected_object, transform);
+            // Baking in a mirroring transform turns the triangles inside out, so the
+            // importer should get them back the other way around.
+            if transform.determinant() < 0.0 {
+                for triangle in &mut expected_triangles {
+                    triangle.swap(1, 2);
+                }
+            }
+            let actual_triangles = triangles(object, glam::Mat4::IDENTITY);
+            assert_eq!(actual_triangles.len(), expected_triangles.len());
+            for (actual, expected) in actual_triangles.iter().zip(&expected_triangles) {
+                for ((position, normal, uv), (expected_position, expected_normal, expected_uv)) in
+                    actual.iter().zip(expected)
+                {
+                    assert!(position.abs_diff_eq(*expected_position, 1e-5));
+                    assert!(normal.abs_diff_eq(*expected_normal, 1e-5));
+                    assert!(uv.abs_diff_eq(*expected_uv, 1e-5));
+                }
+            }
+
+            let material = &object.material;
+            let expected_material = &expected_object.material;
+            assert_eq!(material.albedo, expected_material.albedo);
+            assert_eq!(material.roughness, expected_material.roughness);
+            assert_eq!(material.metallic, expected_material.metallic);
+        }
+    }
+}
@@ -0,0 +1,217 @@
+//! Finding the objects under the cursor, for selecting them in the viewport.
+
+use super::{graph::NodeId, RawMesh, SceneData};
+
+/// A half-line starting at `origin`. Distances along the ray are measured in multiples
+/// of `direction`, which doesn't need to be normalized.
+#[derive(Clone, Copy, Debug)]
+pub struct Ray {
+    pub origin: glam::Vec3,
+    pub direction: glam::Vec3,
+}
+
+impl Ray {
+    /// The same ray in the space `transform` maps to. Distances along the ray stay the
+    /// same, even if `transform` scales.
+    pub fn transformed(&self, transform: glam::Mat4) -> Self {
+        Self {
+            origin: transform.transform_point3(self.origin),
+            direction: transform.transform_vector3(self.direction),
+        }
+    }
+
+    /// Distance to where the ray enters the axis-aligned box from `min` to `max`, or 0
+    /// if it starts inside.
+    pub fn intersect_box(&self, min: glam::Vec3, max: glam::Vec3) -> Option<f32> {
+        // Slab test: intersect the ranges of distances in which the ray is between
+        // each pair of parallel planes. Division by zero gives infinities, which give
+        // the right result unless the origin is exactly on a plane.
+        let inverse_direction = self.direction.recip();
+        let to_min = (min - self.origin) * inverse_direction;
+        let to_max = (max - self.origin) * inverse_direction;
+        let enter = to_min.min(to_max).max_element().max(0.0);
+        let exit = to_min.max(to_max).min_element();
+
+        (enter <= exit).then_some(enter)
+    }
+
+    /// Distance to where the ray hits the triangle `a`, `b`, `c` from either side.
+    pub fn intersect_triangle(&self, a: glam::Vec3, b: glam::Vec3, c: glam::Vec3) -> Option<f32> {
+        // Möller-Trumbore: solve origin + t * direction = a + u * (b - a) + v * (c - a)
+        // for the barycentric coordinates u and v.
+        let ab = b - a;
+        let ac = c - a;
+        let p = self.direction.cross(ac);
+        let determinant = ab.dot(p);
+        if determinant.abs() < f32::EPSILON * ab.length() * ac.length() {
+            // The ray is parallel to the triangle, or the triangle is degenerate.
+            return None;
+        }
+
+        let from_a = self.origin - a;
+        let u = from_a.dot(p) / determinant;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = from_a.cross(ab);
+        let v = self.direction.dot(q) / determinant;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let t = ac.dot(q) / determinant;
+        (t >= 0.0).then_some(t)
+    }
+}
+
+impl RawMesh {
+    /// The corners of the axis-aligned box around the mesh, or `None` if it has no
+    /// vertices.
+    fn bounds(&self) -> Option<(glam::Vec3, glam::Vec3)> {
+        let first = *self.vertices.first()?;
+        Some(
+            self.vertices
+                .iter()
+                .fold((first, first), |(min, max), v| (min.min(*v), max.max(*v))),
+        )
+    }
+
+    /// Distance along `ray` to the nearest triangle it hits.
+    fn intersect(&self, ray: &Ray) -> Option<f32> {
+        let (min, max) = self.bounds()?;
+        ray.intersect_box(min, max)?;
+
+        self.indices
+            .chunks_exact(3)
+            .filter_map(|triangle| {
+                let [a, b, c] = [0, 1, 2].map(|i| self.vertices[triangle[i] as usize]);
+                ray.intersect_triangle(a, b, c)
+            })
+            .min_by(f32::total_cmp)
+    }
+}
+
+impl SceneData {
+    /// The node with the nearest object under the point `ndc` on the screen, where x
+    /// and y go from -1 at the bottom left to 1 at the top right.
+    pub fn pick(&self, ndc: glam::Vec2) -> Option<NodeId> {
+        let (origin, direction) = self.camera.get_ray_through(ndc);
+        let ray = Ray { origin, direction };
+
+        self.graph
+            .iter()
+            .filter(|(id, _)| self.graph.is_visible(*id))
+            .filter_map(|(id, node)| {
+                let object = node.object.as_ref()?;
+                let world_transform = node.world_transform();
+                // A node scaled to nothing can't be hit.
+                if world_transform.determinant() == 0.0 {
+                    return None;
+                }
+                let distance = object
+                    .mesh
+                    .intersect(&ray.transformed(world_transform.inverse()))?;
+                Some((id, distance))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(id, _)| id)
+    }
+
+    /// The nodes with objects that have a vertex inside the screen rectangle with
+    /// corners `corner` and `opposite_corner`, in the same coordinates as for `pick()`.
+    pub fn pick_in_rectangle(
+        &self,
+        corner: glam::Vec2,
+        opposite_corner: glam::Vec2,
+    ) -> Vec<NodeId> {
+        let min = corner.min(opposite_corner);
+        let max = corner.max(opposite_corner);
+        let view_projection = self.camera.get_view_projection_matrix().to_glam_mat4();
+
+        self.graph
+            .iter()
+            .filter(|(id, node)| {
+                let Some(object) = &node.object else {
+                    return false;
+                };
+                if !self.graph.is_visible(*id) {
+                    return false;
+                }
+                let object_to_clip = view_projection * node.world_transform();
+                object.mesh.vertices.iter().any(|vertex| {
+                    let clip = object_to_clip * vertex.extend(1.0);
+                    // Vertices behind the camera end up mirrored onto the screen.
+                    if clip.w <= 0.0 {
+                        return false;
+                    }
+                    let ndc = clip.truncate().truncate() / clip.w;
+                    ndc.cmpge(min).all() && ndc.cmple(max).all()
+                })
+            })
+            .map(|(id, _)| id)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec3;
+
+    const MIN: Vec3 = Vec3::splat(-1.0);
+    const MAX: Vec3 = Vec3::splat(1.0);
+    const A: Vec3 = Vec3::ZERO;
+    const B: Vec3 = Vec3::X;
+    const C: Vec3 = Vec3::Y;
+
+    fn ray(origin: Vec3, direction: Vec3) -> Ray {
+        Ray { origin, direction }
+    }
+
+    #[test]
+    fn ray_hits_box() {
+        let hit = ray(Vec3::new(-5.0, 0.5, 0.5), Vec3::X);
+        assert_eq!(hit.intersect_box(MIN, MAX), Some(4.0));
+        // Distances are in multiples of the direction.
+        let hit = ray(Vec3::new(-5.0, 0.5, 0.5), Vec3::X * 2.0);
+        assert_eq!(hit.intersect_box(MIN, MAX), Some(2.0));
+    }
+
+    #[test]
+    fn ray_misses_box() {
+        let beside = ray(Vec3::new(-5.0, 2.0, 0.0), Vec3::X);
+        assert_eq!(beside.intersect_box(MIN, MAX), None);
+        let away = ray(Vec3::new(-5.0, 0.0, 0.0), -Vec3::X);
+        assert_eq!(away.intersect_box(MIN, MAX), None);
+    }
+
+    #[test]
+    fn ray_starting_inside_box_hits_it_at_the_origin() {
+        let inside = ray(Vec3::new(0.5, -0.5, 0.0), Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(inside.intersect_box(MIN, MAX), Some(0.0));
+    }
+
+    #[test]
+    fn ray_hits_triangle_from_either_side() {
+        let front = ray(Vec3::new(0.25, 0.25, 2.0), -Vec3::Z);
+        assert_eq!(front.intersect_triangle(A, B, C), Some(2.0));
+        let back = ray(Vec3::new(0.25, 0.25, -2.0), Vec3::Z);
+        assert_eq!(back.intersect_triangle(A, B, C), Some(2.0));
+    }
+
+    #[test]
+    fn ray_misses_triangle() {
+        let beside = ray(Vec3::new(0.75, 0.75, 2.0), -Vec3::Z);
+        assert_eq!(beside.intersect_triangle(A, B, C), None);
+        let away = ray(Vec3::new(0.25, 0.25, 2.0), Vec3::Z);
+        assert_eq!(away.intersect_triangle(A, B, C), None);
+    }
+
+    #[test]
+    fn ray_parallel_to_triangle_misses_it() {
+        let above = ray(Vec3::new(-1.0, 0.25, 1.0), Vec3::X);
+        assert_eq!(above.intersect_triangle(A, B, C), None);
+        let in_plane = ray(Vec3::new(-1.0, 0.25, 0.0), Vec3::X);
+        assert_eq!(in_plane.intersect_triangle(A, B, C), None);
+    }
+}
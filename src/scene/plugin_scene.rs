@@ -0,0 +1,360 @@
+//! The scene as render plugins see it.
+//!
+//! Plugins with `PluginCapabilities::SCENE` are handed the scene through
+//! `ekki_set_scene` before a render begins:
+//!
+//! ```c
+//! typedef struct {
+//!     uint32_t width, height;
+//!     const uint8_t *data;          /* sRGB RGBA, rows from top to bottom */
+//! } EkkiTexture;
+//!
+//! typedef struct {
+//!     float albedo[4];              /* multiplied with the texture, if any */
+//!     const EkkiTexture *albedo_texture;  /* or NULL */
+//!     float roughness, metallic;
+//! } EkkiMaterial;
+//!
+//! typedef struct {
+//!     const char *name;
+//!     float transform[16];          /* object -> world */
+//!     const float *vertices;        /* xyz per vertex */
+//!     const float *normals;         /* xyz per vertex, or NULL */
+//!     const float *uvs;             /* uv per vertex, or NULL */
+//!     uint32_t vertex_count;
+//!     const uint32_t *indices;      /* three per triangle */
+//!     uint32_t index_count;
+//!     EkkiMaterial material;
+//! } EkkiObject;
+//!
+//! typedef struct {
+//!     const char *name;
+//!     uint32_t kind;                /* LIGHT_* */
+//!     float color[3], intensity;
+//!     float position[3];            /* unused by directional lights */
+//!     float direction[3];           /* unused by point lights */
+//!     float range;                  /* INFINITY for no limit */
+//!     float inner_cone_angle, outer_cone_angle;  /* spot lights, in radians */
+//!     float width, height;          /* area lights */
+//! } EkkiLight;
+//!
+//! typedef struct {
+//!     const char *name;             /* NULL for the viewport's view */
+//!     float camera_to_world[16];    /* looks down +z, with +y up */
+//!     uint32_t projection;          /* PROJECTION_* */
+//!     float vertical_fov;           /* in degrees */
+//!     float aspect_ratio;           /* width / height of the render */
+//!     float z_near, z_far;          /* z_far is INFINITY for no far plane */
+//!     float orthographic_height;    /* how much orthographic views show vertically */
+//! } EkkiCamera;
+//!
+//! typedef struct {
+//!     uint32_t struct_size;         /* sizeof(EkkiScene) */
+//!     const EkkiObject *objects;
+//!     uint32_t object_count;
+//!     const EkkiLight *lights;
+//!     uint32_t light_count;
+//!     EkkiCamera camera;
+//! } EkkiScene;
+//!
+//! void ekki_set_scene(const EkkiScene *scene);
+//! ```
+//!
+//! Coordinates are left-handed with y up, like the viewport's. Matrices are column
+//! major. Only visible objects are included. The scene, and everything it points to,
+//! is only valid until `ekki_set_scene` returns, so plugins copy what they need.
+
+use std::ffi::{c_char, CString};
+
+use crate::camera::{Camera, CameraState, LensSettings, ProjectionKind};
+
+use super::{SceneData, SceneLight, SceneLightKind, SceneObject};
+
+pub const LIGHT_DIRECTIONAL: u32 = 0;
+pub const LIGHT_POINT: u32 = 1;
+pub const LIGHT_SPOT: u32 = 2;
+pub const LIGHT_AREA: u32 = 3;
+
+pub const PROJECTION_PERSPECTIVE: u32 = 0;
+pub const PROJECTION_ORTHOGRAPHIC: u32 = 1;
+
+#[repr(C)]
+pub struct RawTexture {
+    pub width: u32,
+    pub height: u32,
+    pub data: *const u8,
+}
+
+#[repr(C)]
+pub struct RawMaterial {
+    pub albedo: [f32; 4],
+    pub albedo_texture: *const RawTexture,
+    pub roughness: f32,
+    pub metallic: f32,
+}
+
+#[repr(C)]
+pub struct RawObject {
+    pub name: *const c_char,
+    pub transform: [f32; 16],
+    pub vertices: *const f32,
+    pub normals: *const f32,
+    pub uvs: *const f32,
+    pub vertex_count: u32,
+    pub indices: *const u32,
+    pub index_count: u32,
+    pub material: RawMaterial,
+}
+
+#[repr(C)]
+pub struct RawLight {
+    pub name: *const c_char,
+    pub kind: u32,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub position: [f32; 3],
+    pub direction: [f32; 3],
+    pub range: f32,
+    pub inner_cone_angle: f32,
+    pub outer_cone_angle: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+#[repr(C)]
+pub struct RawCamera {
+    pub name: *const c_char,
+    pub camera_to_world: [f32; 16],
+    pub projection: u32,
+    pub vertical_fov: f32,
+    pub aspect_ratio: f32,
+    pub z_near: f32,
+    pub z_far: f32,
+    pub orthographic_height: f32,
+}
+
+#[repr(C)]
+pub struct RawScene {
+    pub struct_size: u32,
+    pub objects: *const RawObject,
+    pub object_count: u32,
+    pub lights: *const RawLight,
+    pub light_count: u32,
+    pub camera: RawCamera,
+}
+
+/// A copy of a scene taken when a render starts, so that the scene can be edited while
+/// the plugin renders on another thread.
+pub struct PluginScene {
+    objects: Vec<PluginObject>,
+    lights: Vec<(CString, SceneLight)>,
+    camera: PluginCamera,
+}
+
+struct PluginObject {
+    name: CString,
+    /// object -> world
+    transform: glam::Mat4,
+    object: SceneObject,
+}
+
+struct PluginCamera {
+    /// `None` for the viewport's view.
+    name: Option<CString>,
+    camera_to_world: glam::Mat4,
+    projection: ProjectionKind,
+    lens: LensSettings,
+    aspect_ratio: f32,
+    orthographic_height: f32,
+}
+
+/// Names can't contain nul bytes in C, so they are cut off at the first one.
+fn c_name(name: &str) -> CString {
+    let name = name.split('\0').next().unwrap_or_default();
+    CString::new(name).unwrap()
+}
+
+impl PluginScene {
+    /// Renders are made through the scene's active camera, or the viewport's view if
+    /// there isn't one, for an image `width` by `height` pixels.
+    pub fn new(scene: &SceneData, width: u32, height: u32) -> Self {
+        let graph = scene.graph();
+        let objects = graph
+            .iter()
+            .filter(|(id, _)| graph.is_visible(*id))
+            .filter_map(|(_, node)| {
+                Some(PluginObject {
+                    name: c_name(&node.name),
+                    transform: node.world_transform(),
+                    object: node.object.clone()?,
+                })
+            })
+            .collect();
+        let lights = scene
+            .lights
+            .iter()
+            .map(|light| (c_name(&light.name), light.clone()))
+            .collect();
+
+        let (name, state) = match scene.cameras.active_camera() {
+            Some(scene_camera) => (
+                Some(c_name(&scene_camera.name)),
+                scene_camera.camera.clone(),
+            ),
+            None => (None, scene.camera.get_state()),
+        };
+
+        Self {
+            objects,
+            lights,
+            camera: PluginCamera::new(name, &state, width, height),
+        }
+    }
+
+    /// Calls `f` with the scene laid out for C. Everything the scene points to is only
+    /// valid during the call.
+    pub fn with_raw<R>(&self, f: impl FnOnce(&RawScene) -> R) -> R {
+        let textures: Vec<Option<RawTexture>> = self
+            .objects
+            .iter()
+            .map(|object| {
+                let texture = object.object.material.albedo_texture.as_ref()?;
+                Some(RawTexture {
+                    width: texture.width,
+                    height: texture.height,
+                    data: texture.data.as_ptr(),
+                })
+            })
+            .collect();
+        let objects: Vec<RawObject> = self
+            .objects
+            .iter()
+            .zip(&textures)
+            .map(|(object, texture)| object.to_raw(texture.as_ref()))
+            .collect();
+        let lights: Vec<RawLight> = self
+            .lights
+            .iter()
+            .map(|(name, light)| raw_light(name, light))
+            .collect();
+
+        f(&RawScene {
+            struct_size: std::mem::size_of::<RawScene>() as u32,
+            objects: objects.as_ptr(),
+            object_count: objects.len() as u32,
+            lights: lights.as_ptr(),
+            light_count: lights.len() as u32,
+            camera: self.camera.to_raw(),
+        })
+    }
+}
+
+/// Null for empty slices, so that plugins can tell missing normals and uvs apart.
+fn floats_or_null<T>(values: &[T]) -> *const f32 {
+    if values.is_empty() {
+        std::ptr::null()
+    } else {
+        values.as_ptr().cast()
+    }
+}
+
+impl PluginObject {
+    fn to_raw(&self, texture: Option<&RawTexture>) -> RawObject {
+        let mesh = &self.object.mesh;
+        let material = &self.object.material;
+        RawObject {
+            name: self.name.as_ptr(),
+            transform: self.transform.to_cols_array(),
+            vertices: mesh.vertices.as_ptr().cast(),
+            normals: floats_or_null(&mesh.normals),
+            uvs: floats_or_null(&mesh.uvs),
+            vertex_count: mesh.vertices.len() as u32,
+            indices: mesh.indices.as_ptr(),
+            index_count: mesh.indices.len() as u32,
+            material: RawMaterial {
+                albedo: material.albedo.to_array(),
+                albedo_texture: texture.map_or(std::ptr::null(), |texture| texture),
+                roughness: material.roughness,
+                metallic: material.metallic,
+            },
+        }
+    }
+}
+
+fn raw_light(name: &CString, light: &SceneLight) -> RawLight {
+    let mut raw = RawLight {
+        name: name.as_ptr(),
+        kind: LIGHT_DIRECTIONAL,
+        color: light.color.to_array(),
+        intensity: light.intensity,
+        position: light.kind.position().unwrap_or_default().to_array(),
+        direction: light
+            .kind
+            .direction()
+            .map_or(glam::Vec3::ZERO, |direction| direction.normalize_or_zero())
+            .to_array(),
+        range: f32::INFINITY,
+        inner_cone_angle: 0.0,
+        outer_cone_angle: 0.0,
+        width: 0.0,
+        height: 0.0,
+    };
+    match light.kind {
+        SceneLightKind::Directional { .. } => {}
+        SceneLightKind::Point { range, .. } => {
+            raw.kind = LIGHT_POINT;
+            raw.range = range.unwrap_or(f32::INFINITY);
+        }
+        SceneLightKind::Spot {
+            range,
+            inner_cone_angle,
+            outer_cone_angle,
+            ..
+        } => {
+            raw.kind = LIGHT_SPOT;
+            raw.range = range.unwrap_or(f32::INFINITY);
+            raw.inner_cone_angle = inner_cone_angle;
+            raw.outer_cone_angle = outer_cone_angle;
+        }
+        SceneLightKind::Area { width, height, .. } => {
+            raw.kind = LIGHT_AREA;
+            raw.width = width;
+            raw.height = height;
+        }
+    }
+    raw
+}
+
+impl PluginCamera {
+    fn new(name: Option<CString>, state: &CameraState, width: u32, height: u32) -> Self {
+        let mut camera = Camera::initialize(width as f32, height as f32);
+        camera.set_state(state);
+        Self {
+            name,
+            camera_to_world: camera.get_view_matrix().inverse(),
+            projection: state.projection,
+            lens: state.lens,
+            aspect_ratio: width as f32 / height as f32,
+            orthographic_height: camera.orthographic_height(),
+        }
+    }
+
+    fn to_raw(&self) -> RawCamera {
+        RawCamera {
+            name: self
+                .name
+                .as_ref()
+                .map_or(std::ptr::null(), |name| name.as_ptr()),
+            camera_to_world: self.camera_to_world.to_cols_array(),
+            projection: match self.projection {
+                ProjectionKind::Perspective => PROJECTION_PERSPECTIVE,
+                ProjectionKind::Orthographic => PROJECTION_ORTHOGRAPHIC,
+            },
+            vertical_fov: self.lens.vertical_fov,
+            aspect_ratio: self.aspect_ratio,
+            z_near: self.lens.z_near,
+            z_far: self.lens.z_far.unwrap_or(f32::INFINITY),
+            orthographic_height: self.orthographic_height,
+        }
+    }
+}
@@ -0,0 +1,815 @@
+//! Procedurally generated meshes for the basic shapes that can be added to a scene.
+//!
+//! Every shape is centered on the origin with its axis along y, and comes with normals
+//! and texture coordinates. Texture coordinates have their origin at the top left of
+//! the image, and wrap around round shapes starting and ending at +x. Triangles are
+//! clockwise when seen from the outside, like those of `SceneObject::create_basic_cube()`.
+
+use std::{
+    collections::HashMap,
+    f32::consts::{FRAC_PI_2, PI, TAU},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{RawMesh, SceneMaterial, SceneObject};
+
+/// Subdividing an icosphere quadruples its triangle count, so this is already more
+/// than 300,000 triangles.
+pub const MAX_ICOSPHERE_SUBDIVISIONS: u32 = 7;
+
+/// The largest value a `PrimitiveParameter::Count` can be set to. Much more detail than
+/// this makes meshes slow to build, and eventually too large to index with `u32`.
+pub const MAX_COUNT: u32 = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PrimitiveKind {
+    UvSphere,
+    Icosphere,
+    Plane,
+    Cylinder,
+    Cone,
+    Torus,
+    Capsule,
+}
+
+impl PrimitiveKind {
+    pub const ALL: [Self; 7] = [
+        Self::UvSphere,
+        Self::Icosphere,
+        Self::Plane,
+        Self::Cylinder,
+        Self::Cone,
+        Self::Torus,
+        Self::Capsule,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::UvSphere => "UV sphere",
+            Self::Icosphere => "Icosphere",
+            Self::Plane => "Plane",
+            Self::Cylinder => "Cylinder",
+            Self::Cone => "Cone",
+            Self::Torus => "Torus",
+            Self::Capsule => "Capsule",
+        }
+    }
+
+    /// The shape with parameters that give a reasonable result at about the size of
+    /// the default cube.
+    pub fn default_primitive(self) -> Primitive {
+        match self {
+            Self::UvSphere => Primitive::UvSphere {
+                radius: 1.0,
+                segments: 32,
+                rings: 16,
+            },
+            Self::Icosphere => Primitive::Icosphere {
+                radius: 1.0,
+                subdivisions: 3,
+            },
+            Self::Plane => Primitive::Plane {
+                width: 2.0,
+                depth: 2.0,
+                columns: 1,
+                rows: 1,
+            },
+            Self::Cylinder => Primitive::Cylinder {
+                radius: 1.0,
+                height: 2.0,
+                segments: 32,
+                rings: 1,
+            },
+            Self::Cone => Primitive::Cone {
+                radius: 1.0,
+                height: 2.0,
+                segments: 32,
+                rings: 1,
+            },
+            Self::Torus => Primitive::Torus {
+                major_radius: 1.0,
+                minor_radius: 0.25,
+                major_segments: 48,
+                minor_segments: 12,
+            },
+            Self::Capsule => Primitive::Capsule {
+                radius: 0.5,
+                height: 1.0,
+                segments: 32,
+                rings: 8,
+            },
+        }
+    }
+}
+
+/// A shape and the parameters to generate its mesh with. Counts below the minimum a
+/// shape needs are raised to that minimum when the mesh is generated.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Primitive {
+    /// A sphere made of `segments` slices around its axis and `rings` stacked bands.
+    UvSphere {
+        radius: f32,
+        segments: u32,
+        rings: u32,
+    },
+    /// A sphere made of evenly sized triangles, by repeatedly subdividing an
+    /// icosahedron.
+    Icosphere { radius: f32, subdivisions: u32 },
+    /// A grid in the xz plane, facing +y.
+    Plane {
+        width: f32,
+        depth: f32,
+        columns: u32,
+        rows: u32,
+    },
+    /// A closed cylinder, with `rings` bands along its height.
+    Cylinder {
+        radius: f32,
+        height: f32,
+        segments: u32,
+        rings: u32,
+    },
+    /// A closed cone with its apex at the top.
+    Cone {
+        radius: f32,
+        height: f32,
+        segments: u32,
+        rings: u32,
+    },
+    /// A ring around the y axis. `major_radius` is measured to the center of the tube.
+    Torus {
+        major_radius: f32,
+        minor_radius: f32,
+        major_segments: u32,
+        minor_segments: u32,
+    },
+    /// A cylinder with hemispheres on both ends. `height` is the length of the
+    /// cylinder, not including the hemispheres, and each hemisphere has `rings` bands.
+    Capsule {
+        radius: f32,
+        height: f32,
+        segments: u32,
+        rings: u32,
+    },
+}
+
+/// A parameter of a `Primitive`, for editing any kind of primitive in the same way.
+pub enum PrimitiveParameter<'a> {
+    Length(&'static str, &'a mut f32),
+    Count(&'static str, &'a mut u32),
+}
+
+impl PrimitiveParameter<'_> {
+    /// Rounds `value` to a count between 0 and `MAX_COUNT`. NaN becomes 0.
+    pub fn count_from_f32(value: f32) -> u32 {
+        if value.is_nan() {
+            0
+        } else {
+            value.round().clamp(0.0, MAX_COUNT as f32) as u32
+        }
+    }
+}
+
+impl Primitive {
+    pub fn kind(&self) -> PrimitiveKind {
+        match self {
+            Self::UvSphere { .. } => PrimitiveKind::UvSphere,
+            Self::Icosphere { .. } => PrimitiveKind::Icosphere,
+            Self::Plane { .. } => PrimitiveKind::Plane,
+            Self::Cylinder { .. } => PrimitiveKind::Cylinder,
+            Self::Cone { .. } => PrimitiveKind::Cone,
+            Self::Torus { .. } => PrimitiveKind::Torus,
+            Self::Capsule { .. } => PrimitiveKind::Capsule,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.kind().name()
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<PrimitiveParameter<'_>> {
+        use PrimitiveParameter::{Count, Length};
+
+        match self {
+            Self::UvSphere {
+                radius,
+                segments,
+                rings,
+            } => vec![
+                Length("radius", radius),
+                Count("segments", segments),
+                Count("rings", rings),
+            ],
+            Self::Icosphere {
+                radius,
+                subdivisions,
+            } => vec![
+                Length("radius", radius),
+                Count("subdivisions", subdivisions),
+            ],
+            Self::Plane {
+                width,
+                depth,
+                columns,
+                rows,
+            } => vec![
+                Length("width", width),
+                Length("depth", depth),
+                Count("columns", columns),
+                Count("rows", rows),
+            ],
+            Self::Cylinder {
+                radius,
+                height,
+                segments,
+                rings,
+            }
+            | Self::Cone {
+                radius,
+                height,
+                segments,
+                rings,
+            }
+            | Self::Capsule {
+                radius,
+                height,
+                segments,
+                rings,
+            } => vec![
+                Length("radius", radius),
+                Length("height", height),
+                Count("segments", segments),
+                Count("rings", rings),
+            ],
+            Self::Torus {
+                major_radius,
+                minor_radius,
+                major_segments,
+                minor_segments,
+            } => vec![
+                Length("major radius", major_radius),
+                Length("minor radius", minor_radius),
+                Count("major segments", major_segments),
+                Count("minor segments", minor_segments),
+            ],
+        }
+    }
+
+    pub fn to_object(self) -> SceneObject {
+        match self {
+            Self::UvSphere {
+                radius,
+                segments,
+                rings,
+            } => SceneObject::create_uv_sphere(radius, segments, rings),
+            Self::Icosphere {
+                radius,
+                subdivisions,
+            } => SceneObject::create_icosphere(radius, subdivisions),
+            Self::Plane {
+                width,
+                depth,
+                columns,
+                rows,
+            } => SceneObject::create_plane(width, depth, columns, rows),
+            Self::Cylinder {
+                radius,
+                height,
+                segments,
+                rings,
+            } => SceneObject::create_cylinder(radius, height, segments, rings),
+            Self::Cone {
+                radius,
+                height,
+                segments,
+                rings,
+            } => SceneObject::create_cone(radius, height, segments, rings),
+            Self::Torus {
+                major_radius,
+                minor_radius,
+                major_segments,
+                minor_segments,
+            } => SceneObject::create_torus(
+                major_radius,
+                minor_radius,
+                major_segments,
+                minor_segments,
+            ),
+            Self::Capsule {
+                radius,
+                height,
+                segments,
+                rings,
+            } => SceneObject::create_capsule(radius, height, segments, rings),
+        }
+    }
+}
+
+impl SceneObject {
+    pub fn create_uv_sphere(radius: f32, segments: u32, rings: u32) -> Self {
+        let rings = rings.max(2);
+        let profile = (0..=rings)
+            .map(|ring| {
+                let polar_angle = PI * ring as f32 / rings as f32;
+                let normal = glam::Vec2::new(polar_angle.sin(), polar_angle.cos());
+                ProfilePoint {
+                    position: normal * radius,
+                    normal,
+                    v: ring as f32 / rings as f32,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut mesh = empty_mesh();
+        add_surface_of_revolution(&mut mesh, &profile, segments);
+        Self::new(mesh, SceneMaterial::default())
+    }
+
+    /// `subdivisions` is at most `MAX_ICOSPHERE_SUBDIVISIONS`.
+    pub fn create_icosphere(radius: f32, subdivisions: u32) -> Self {
+        let (directions, triangles) = icosahedron();
+        let (directions, triangles) = (0..subdivisions.min(MAX_ICOSPHERE_SUBDIVISIONS))
+            .fold((directions, triangles), |(directions, triangles), _| {
+                subdivide_sphere(directions, &triangles)
+            });
+
+        // Texture coordinates are projected like those of the UV sphere. Vertices on the
+        // seam and at the poles need different texture coordinates in different
+        // triangles, so vertices are duplicated per distinct texture coordinate.
+        let mut mesh = empty_mesh();
+        let mut vertex_indices = HashMap::new();
+        for triangle in triangles {
+            let at_pole = triangle.map(|index| directions[index as usize].y.abs() > 1.0 - 1e-5);
+            let mut uvs = triangle.map(|index| spherical_uv(directions[index as usize]));
+
+            // Triangles crossing the seam get u past 1 instead of wrapping back to 0.
+            let u_near = |range: std::ops::Range<f32>| {
+                (0..3).any(|i| !at_pole[i] && range.contains(&uvs[i].x))
+            };
+            if u_near(0.0..0.25) && u_near(0.75..1.0) {
+                for uv in &mut uvs {
+                    if uv.x < 0.5 {
+                        uv.x += 1.0;
+                    }
+                }
+            }
+            // The direction of a pole is the same for every u, so use the one which
+            // least distorts the texture in this triangle.
+            for i in 0..3 {
+                if at_pole[i] {
+                    uvs[i].x = (uvs[(i + 1) % 3].x + uvs[(i + 2) % 3].x) / 2.0;
+                }
+            }
+
+            for (index, uv) in triangle.into_iter().zip(uvs) {
+                let vertex = *vertex_indices
+                    .entry((index, uv.x.to_bits()))
+                    .or_insert_with(|| {
+                        let direction = directions[index as usize];
+                        mesh.vertices.push(direction * radius);
+                        mesh.normals.push(direction);
+                        mesh.uvs.push(uv);
+                        mesh.vertices.len() as u32 - 1
+                    });
+                mesh.indices.push(vertex);
+            }
+        }
+
+        Self::new(mesh, SceneMaterial::default())
+    }
+
+    pub fn create_plane(width: f32, depth: f32, columns: u32, rows: u32) -> Self {
+        let columns = columns.max(1);
+        let rows = rows.max(1);
+
+        // Seen from above, the top of the texture is at +z.
+        let mut mesh = empty_mesh();
+        for row in 0..=rows {
+            let v = row as f32 / rows as f32;
+            for column in 0..=columns {
+                let u = column as f32 / columns as f32;
+                mesh.vertices
+                    .push(glam::Vec3::new(width * (u - 0.5), 0.0, depth * (0.5 - v)));
+                mesh.normals.push(glam::Vec3::Y);
+                mesh.uvs.push(glam::Vec2::new(u, v));
+            }
+        }
+        add_grid_triangles(&mut mesh, 0, rows, columns, |_| false);
+
+        Self::new(mesh, SceneMaterial::default())
+    }
+
+    pub fn create_cylinder(radius: f32, height: f32, segments: u32, rings: u32) -> Self {
+        let rings = rings.max(1);
+        let profile = (0..=rings)
+            .map(|ring| {
+                let v = ring as f32 / rings as f32;
+                ProfilePoint {
+                    position: glam::Vec2::new(radius, height * (0.5 - v)),
+                    normal: glam::Vec2::X,
+                    v,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut mesh = empty_mesh();
+        add_surface_of_revolution(&mut mesh, &profile, segments);
+        add_disk(&mut mesh, radius, height / 2.0, true, segments);
+        add_disk(&mut mesh, radius, -height / 2.0, false, segments);
+        Self::new(mesh, SceneMaterial::default())
+    }
+
+    pub fn create_cone(radius: f32, height: f32, segments: u32, rings: u32) -> Self {
+        let rings = rings.max(1);
+        let normal = glam::Vec2::new(height, radius).normalize_or_zero();
+        let profile = (0..=rings)
+            .map(|ring| {
+                let v = ring as f32 / rings as f32;
+                ProfilePoint {
+                    position: glam::Vec2::new(radius * v, height * (0.5 - v)),
+                    normal,
+                    v,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut mesh = empty_mesh();
+        add_surface_of_revolution(&mut mesh, &profile, segments);
+        add_disk(&mut mesh, radius, -height / 2.0, false, segments);
+        Self::new(mesh, SceneMaterial::default())
+    }
+
+    pub fn create_torus(
+        major_radius: f32,
+        minor_radius: f32,
+        major_segments: u32,
+        minor_segments: u32,
+    ) -> Self {
+        // The profile is a circle starting at the top of the tube and going around the
+        // outside first, so that v increases downwards on the outside.
+        let minor_segments = minor_segments.max(3);
+        let profile = (0..=minor_segments)
+            .map(|segment| {
+                let v = segment as f32 / minor_segments as f32;
+                let angle = TAU * v;
+                let normal = glam::Vec2::new(angle.sin(), angle.cos());
+                ProfilePoint {
+                    position: glam::Vec2::new(major_radius, 0.0) + normal * minor_radius,
+                    normal,
+                    v,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut mesh = empty_mesh();
+        add_surface_of_revolution(&mut mesh, &profile, major_segments);
+        Self::new(mesh, SceneMaterial::default())
+    }
+
+    pub fn create_capsule(radius: f32, height: f32, segments: u32, rings: u32) -> Self {
+        let rings = rings.max(1);
+
+        // Both hemispheres include the equator, and the band between the two equators
+        // is the cylinder. Without a cylinder the band would be degenerate.
+        let hemisphere = |offset: f32, polar_angles: std::ops::RangeInclusive<u32>| {
+            polar_angles.map(move |ring| {
+                let polar_angle = FRAC_PI_2 * ring as f32 / rings as f32;
+                let normal = glam::Vec2::new(polar_angle.sin(), polar_angle.cos());
+                (normal * radius + glam::Vec2::new(0.0, offset), normal)
+            })
+        };
+        let points = hemisphere(height / 2.0, 0..=rings)
+            .chain(hemisphere(
+                -height / 2.0,
+                rings + u32::from(height <= 0.0)..=2 * rings,
+            ))
+            .collect::<Vec<_>>();
+
+        // v is proportional to the distance along the outline, so that the texture
+        // isn't stretched over the cylinder.
+        let mut distances = vec![0.0];
+        for pair in points.windows(2) {
+            distances.push(distances.last().unwrap() + pair[0].0.distance(pair[1].0));
+        }
+        let length = distances.last().unwrap().max(f32::EPSILON);
+        let profile = points
+            .into_iter()
+            .zip(distances)
+            .map(|((position, normal), distance)| ProfilePoint {
+                position,
+                normal,
+                v: distance / length,
+            })
+            .collect::<Vec<_>>();
+
+        let mut mesh = empty_mesh();
+        add_surface_of_revolution(&mut mesh, &profile, segments);
+        Self::new(mesh, SceneMaterial::default())
+    }
+}
+
+fn empty_mesh() -> RawMesh {
+    RawMesh {
+        vertices: Vec::new(),
+        normals: Vec::new(),
+        uvs: Vec::new(),
+        indices: Vec::new(),
+    }
+}
+
+/// A point on the outline of a shape which is symmetric around the y axis, in the
+/// half plane x >= 0.
+struct ProfilePoint {
+    /// (distance from the y axis, y)
+    position: glam::Vec2,
+    /// In the same coordinates as `position`.
+    normal: glam::Vec2,
+    v: f32,
+}
+
+/// The direction from the y axis at `angle` around it. Increasing the angle moves to
+/// the right when looking at the y axis from outside with +y up.
+fn radial_direction(angle: f32) -> glam::Vec3 {
+    glam::Vec3::new(angle.cos(), 0.0, angle.sin())
+}
+
+/// Adds the surface made by rotating `profile` around the y axis. `profile` must go
+/// from top to bottom as seen from outside the shape.
+fn add_surface_of_revolution(mesh: &mut RawMesh, profile: &[ProfilePoint], segments: u32) {
+    let segments = segments.max(3);
+    let first_vertex = mesh.vertices.len() as u32;
+
+    // The first and last column are in the same place but have different texture
+    // coordinates.
+    for point in profile {
+        for segment in 0..=segments {
+            let u = segment as f32 / segments as f32;
+            let radial = radial_direction(TAU * u);
+            mesh.vertices
+                .push(radial * point.position.x + glam::Vec3::Y * point.position.y);
+            mesh.normals.push(
+                (radial * point.normal.x + glam::Vec3::Y * point.normal.y).normalize_or_zero(),
+            );
+            mesh.uvs.push(glam::Vec2::new(u, point.v));
+        }
+    }
+
+    // Rows that lie on the axis, like the poles of a sphere, would only give
+    // degenerate triangles next to them.
+    let on_axis = |row: u32| profile[row as usize].position.x.abs() <= f32::EPSILON;
+    add_grid_triangles(
+        mesh,
+        first_vertex,
+        profile.len() as u32 - 1,
+        segments,
+        on_axis,
+    );
+}
+
+/// Adds the triangles for a grid of `(rows + 1) * (columns + 1)` vertices starting at
+/// `first_vertex`, row by row. Seen from the front, rows go down and columns go right.
+/// Triangles with an edge along a row for which `degenerate_row` is true are skipped.
+fn add_grid_triangles(
+    mesh: &mut RawMesh,
+    first_vertex: u32,
+    rows: u32,
+    columns: u32,
+    degenerate_row: impl Fn(u32) -> bool,
+) {
+    for row in 0..rows {
+        for column in 0..columns {
+            let top_left = first_vertex + row * (columns + 1) + column;
+            let top_right = top_left + 1;
+            let bottom_left = top_left + columns + 1;
+            let bottom_right = bottom_left + 1;
+
+            if !degenerate_row(row) {
+                mesh.indices
+                    .extend_from_slice(&[top_left, top_right, bottom_left]);
+            }
+            if !degenerate_row(row + 1) {
+                mesh.indices
+                    .extend_from_slice(&[top_right, bottom_right, bottom_left]);
+            }
+        }
+    }
+}
+
+/// Adds a disk at height `y`, facing +y if `facing_up` or -y otherwise. The texture
+/// is projected onto it from the side it faces, with its top at +z.
+fn add_disk(mesh: &mut RawMesh, radius: f32, y: f32, facing_up: bool, segments: u32) {
+    let segments = segments.max(3);
+    let normal = if facing_up {
+        glam::Vec3::Y
+    } else {
+        glam::Vec3::NEG_Y
+    };
+    // Seen from below, +x is on the left.
+    let u_sign = if facing_up { 1.0 } else { -1.0 };
+
+    let center = mesh.vertices.len() as u32;
+    mesh.vertices.push(glam::Vec3::new(0.0, y, 0.0));
+    mesh.normals.push(normal);
+    mesh.uvs.push(glam::Vec2::splat(0.5));
+    for segment in 0..segments {
+        let radial = radial_direction(TAU * segment as f32 / segments as f32);
+        mesh.vertices
+            .push(radial * radius + glam::Vec3::new(0.0, y, 0.0));
+        mesh.normals.push(normal);
+        mesh.uvs.push(glam::Vec2::new(
+            0.5 + 0.5 * u_sign * radial.x,
+            0.5 - 0.5 * radial.z,
+        ));
+    }
+
+    for segment in 0..segments {
+        let current = center + 1 + segment;
+        let next = center + 1 + (segment + 1) % segments;
+        let triangle = if facing_up {
+            [center, next, current]
+        } else {
+            [center, current, next]
+        };
+        mesh.indices.extend_from_slice(&triangle);
+    }
+}
+
+/// Texture coordinates for the point on the unit sphere in `direction`, matching those
+/// of the UV sphere.
+fn spherical_uv(direction: glam::Vec3) -> glam::Vec2 {
+    let u = direction.z.atan2(direction.x).rem_euclid(TAU) / TAU;
+    let v = direction.y.clamp(-1.0, 1.0).acos() / PI;
+    glam::Vec2::new(u, v)
+}
+
+/// The unit directions to the corners of a regular icosahedron, and its faces.
+fn icosahedron() -> (Vec<glam::Vec3>, Vec<[u32; 3]>) {
+    let t = (1.0 + 5.0_f32.sqrt()) / 2.0;
+    let directions = [
+        [-1.0, t, 0.0],
+        [1.0, t, 0.0],
+        [-1.0, -t, 0.0],
+        [1.0, -t, 0.0],
+        [0.0, -1.0, t],
+        [0.0, 1.0, t],
+        [0.0, -1.0, -t],
+        [0.0, 1.0, -t],
+        [t, 0.0, -1.0],
+        [t, 0.0, 1.0],
+        [-t, 0.0, -1.0],
+        [-t, 0.0, 1.0],
+    ]
+    .into_iter()
+    .map(|direction| glam::Vec3::from(direction).normalize())
+    .collect::<Vec<_>>();
+
+    let faces = [
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ]
+    .map(|[a, b, c]| {
+        let [pa, pb, pc] = [a, b, c].map(|i| directions[i as usize]);
+        // Make the faces clockwise from outside.
+        if (pb - pa).cross(pc - pa).dot(pa + pb + pc) < 0.0 {
+            [a, c, b]
+        } else {
+            [a, b, c]
+        }
+    });
+
+    // Tilt the icosahedron so that it has a vertex at each pole, which keeps it
+    // symmetric around the y axis like the other shapes.
+    let tilt = glam::Quat::from_rotation_arc(directions[0], glam::Vec3::Y);
+    let directions = directions.into_iter().map(|d| tilt * d).collect();
+
+    (directions, faces.to_vec())
+}
+
+/// Splits every triangle into four, moving the new vertices out onto the unit sphere.
+fn subdivide_sphere(
+    mut directions: Vec<glam::Vec3>,
+    triangles: &[[u32; 3]],
+) -> (Vec<glam::Vec3>, Vec<[u32; 3]>) {
+    // Edges are shared by two triangles, which have to share the vertex in the middle.
+    let mut midpoints = HashMap::new();
+    let mut midpoint = |a: u32, b: u32, directions: &mut Vec<glam::Vec3>| {
+        *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+            let direction = (directions[a as usize] + directions[b as usize]).normalize();
+            directions.push(direction);
+            directions.len() as u32 - 1
+        })
+    };
+
+    let mut subdivided = Vec::with_capacity(triangles.len() * 4);
+    for &[a, b, c] in triangles {
+        let ab = midpoint(a, b, &mut directions);
+        let bc = midpoint(b, c, &mut directions);
+        let ca = midpoint(c, a, &mut directions);
+        subdivided.extend_from_slice(&[[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]);
+    }
+
+    (directions, subdivided)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every kind of primitive with its default parameters, and with every count set to
+    /// 0 and to 1, which have to be raised to what the shape needs.
+    fn test_primitives() -> Vec<Primitive> {
+        let mut primitives = Vec::new();
+        for kind in PrimitiveKind::ALL {
+            primitives.push(kind.default_primitive());
+            for count in [0, 1] {
+                let mut primitive = kind.default_primitive();
+                for parameter in primitive.parameters_mut() {
+                    if let PrimitiveParameter::Count(_, value) = parameter {
+                        *value = count;
+                    }
+                }
+                primitives.push(primitive);
+            }
+        }
+        primitives
+    }
+
+    /// The point on the surface's skeleton that `position` is offset from, so that the
+    /// normal there should point away from it.
+    fn skeleton_point(primitive: &Primitive, position: glam::Vec3) -> glam::Vec3 {
+        match *primitive {
+            Primitive::Torus { major_radius, .. } => {
+                let radial = glam::Vec3::new(position.x, 0.0, position.z).normalize_or_zero();
+                radial * major_radius
+            }
+            _ => glam::Vec3::ZERO,
+        }
+    }
+
+    #[test]
+    fn primitives_have_valid_meshes() {
+        for primitive in test_primitives() {
+            let mesh = primitive.to_object().mesh;
+            let vertex_count = mesh.vertices.len();
+            assert!(!mesh.indices.is_empty(), "{:?}", primitive);
+            assert_eq!(mesh.indices.len() % 3, 0, "{:?}", primitive);
+            assert!(
+                mesh.indices
+                    .iter()
+                    .all(|index| (*index as usize) < vertex_count),
+                "{:?} has indices out of range",
+                primitive
+            );
+            assert_eq!(mesh.normals.len(), vertex_count, "{:?}", primitive);
+            assert_eq!(mesh.uvs.len(), vertex_count, "{:?}", primitive);
+
+            let max_u = match primitive {
+                Primitive::Icosphere { .. } => 2.0,
+                _ => 1.0,
+            };
+            for ((position, normal), uv) in mesh.vertices.iter().zip(&mesh.normals).zip(&mesh.uvs) {
+                assert!(
+                    (normal.length() - 1.0).abs() < 1e-4,
+                    "{:?} has normal {} at {}",
+                    primitive,
+                    normal,
+                    position
+                );
+                let offset = *position - skeleton_point(&primitive, *position);
+                assert!(
+                    normal.dot(offset) >= -1e-4,
+                    "{:?} has normal {} pointing inwards at {}",
+                    primitive,
+                    normal,
+                    position
+                );
+                assert!(
+                    (0.0..=max_u).contains(&uv.x) && (0.0..=1.0).contains(&uv.y),
+                    "{:?} has uv {} at {}",
+                    primitive,
+                    uv,
+                    position
+                );
+            }
+        }
+    }
+}
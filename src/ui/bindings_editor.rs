@@ -0,0 +1,134 @@
+//! A panel for changing which keys and mouse buttons do what.
+
+use std::collections::HashMap;
+
+use crate::{
+    bindings::{Action, ActionMap, Chord},
+    config::USER_CONFIG_PATH,
+};
+
+pub struct BindingsEditor {
+    /// The chords of every action as typed in, separated by commas.
+    texts: HashMap<Action, String>,
+}
+
+fn chords_text(bindings: &ActionMap, action: Action) -> String {
+    bindings
+        .chords(action)
+        .iter()
+        .map(Chord::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn parse_chords(text: &str) -> Result<Vec<Chord>, String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|chord| !chord.is_empty())
+        .map(Chord::parse)
+        .collect()
+}
+
+impl BindingsEditor {
+    pub fn new(bindings: &ActionMap) -> Self {
+        let texts = Action::ALL
+            .iter()
+            .map(|action| (*action, chords_text(bindings, *action)))
+            .collect();
+        Self { texts }
+    }
+
+    /// The bindings as typed in, or what is wrong with them.
+    fn parse(&self) -> Result<ActionMap, Vec<String>> {
+        let mut bindings = ActionMap::default();
+        let mut errors = Vec::new();
+        for action in Action::ALL {
+            match parse_chords(&self.texts[&action]) {
+                Ok(chords) => bindings.set_chords(action, chords),
+                Err(e) => errors.push(format!("{}: {}.", action.name(), e)),
+            }
+        }
+        if errors.is_empty() {
+            Ok(bindings)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Returns the edited bindings once the user applies them.
+    pub fn draw(&mut self, ui: &mut egui::Ui) -> Option<ActionMap> {
+        ui.label("Write chords like \"Ctrl+Shift+Z\" or \"Alt+LeftMouse\", separated by commas.");
+        ui.separator();
+
+        let defaults = ActionMap::default();
+        egui::ScrollArea::vertical()
+            .max_height(400.0)
+            .show(ui, |ui| {
+                egui::Grid::new("bindings")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for action in Action::ALL {
+                            let text = self.texts.entry(action).or_default();
+                            ui.label(action.name());
+                            ui.text_edit_singleline(text);
+                            let default = chords_text(&defaults, action);
+                            let reset = egui::Button::new("Reset").small();
+                            if ui
+                                .add_enabled(*text != default, reset)
+                                .on_hover_text(format!("Goes back to {}", default))
+                                .clicked()
+                            {
+                                *text = default;
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        let parsed = self.parse();
+        let problems = match &parsed {
+            Ok(bindings) => bindings.problems(),
+            Err(errors) => errors.clone(),
+        };
+        for problem in &problems {
+            ui.colored_label(egui::Color32::RED, problem);
+        }
+
+        ui.separator();
+        let mut applied = None;
+        ui.horizontal(|ui| {
+            if ui.button("Reset all").clicked() {
+                *self = Self::new(&defaults);
+            }
+            // Conflicting bindings would leave some actions unreachable, so they have to be
+            // fixed first.
+            let bindings = parsed.as_ref().ok().filter(|_| problems.is_empty());
+            if ui
+                .add_enabled(bindings.is_some(), egui::Button::new("Apply"))
+                .on_disabled_hover_text("Fix the problems above first")
+                .clicked()
+            {
+                applied = bindings.cloned();
+            }
+            if ui
+                .add_enabled(bindings.is_some(), egui::Button::new("Apply and save"))
+                .on_hover_text(format!(
+                    "Also writes the ones which differ from the defaults into {}",
+                    USER_CONFIG_PATH
+                ))
+                .on_disabled_hover_text("Fix the problems above first")
+                .clicked()
+            {
+                if let Some(bindings) = bindings {
+                    match bindings.save_to_config(USER_CONFIG_PATH.as_ref()) {
+                        Ok(()) => log::info!("saved bindings to {}", USER_CONFIG_PATH),
+                        Err(e) => log::error!("{:#}", e),
+                    }
+                }
+                applied = bindings.cloned();
+            }
+        });
+        applied
+    }
+}
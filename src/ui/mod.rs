@@ -1,2 +1,3 @@
+pub mod bindings_editor;
 pub mod console;
 pub mod windows;
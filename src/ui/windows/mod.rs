@@ -13,9 +13,13 @@ pub mod startup;
 use std::sync::Arc;
 
 use crate::{
+    bindings::{Action, ActionMap},
     camera::Camera,
     grid::GridRenderRoutine,
     input::{self, InputState},
+    project::{self, Project},
+    recording,
+    scene::{plugin_scene::PluginScene, primitives::Primitive},
     ui, MyImage, WindowCloseCallbackCommand, WindowRedrawCallbackCommand,
 };
 
@@ -188,4 +192,82 @@ pub trait WindowLike {
     }
 
     fn handle_input_event(&mut self, input_state: &InputState, input_event: input::InputEvent);
+    /// Called when the chord of `action` is pressed in this window, unless egui took
+    /// it. Drag actions and the actions every window has, like undo, aren't passed here.
+    fn action_pressed(&mut self, _action: Action, _input_state: &InputState) {}
+    /// Called when the key or mouse button of `action` is released in this window,
+    /// whatever modifiers are held.
+    fn action_released(&mut self, _action: Action, _input_state: &InputState) {}
+    /// Called with the key and mouse bindings when the window is created, and whenever
+    /// they change.
+    fn set_bindings(&mut self, _bindings: &ActionMap) {}
+
+    /// Undoes the most recent edit made in this window.
+    fn undo(&mut self) {}
+    /// Redoes the most recently undone edit made in this window.
+    fn redo(&mut self) {}
+
+    /// Adds `primitive` to the scene shown by this window. Returns false if the window
+    /// doesn't show a scene.
+    fn add_primitive(&mut self, _primitive: Primitive) -> bool {
+        false
+    }
+
+    /// The scene renders are made of, for an image `width` by `height` pixels, if this
+    /// window shows one.
+    fn plugin_scene(&self, _width: u32, _height: u32) -> Option<PluginScene> {
+        None
+    }
+    /// Starts a render of `scene`, which the window asked for with
+    /// `WindowRedrawCallbackCommand::BeginRender`.
+    fn begin_render(&mut self, _scene: Option<PluginScene>) {}
+
+    /// Writes the parts of the session owned by this window into `project`.
+    fn save_to_project(&self, _project: &mut Project) {}
+    /// Takes the parts of the session owned by this window out of `project`. This is
+    /// called when a project is opened, and when the window is created.
+    fn load_from_project(&mut self, _project: &mut Project) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// The "File" menu shared by windows which can open and save projects.
+pub(crate) fn draw_file_menu(ui: &mut egui::Ui, callbacks: &mut Vec<WindowRedrawCallbackCommand>) {
+    ui.menu_button("File", |ui| {
+        if ui.button("Open").clicked() {
+            ui.close_menu();
+            if let Some(path) = project::pick_open_path() {
+                callbacks.push(WindowRedrawCallbackCommand::OpenProject(path));
+            }
+        }
+
+        if ui.button("Save as").clicked() {
+            ui.close_menu();
+            if let Some(path) = project::pick_save_path() {
+                callbacks.push(WindowRedrawCallbackCommand::SaveProject(path));
+            }
+        }
+
+        ui.separator();
+        ui.menu_button("Input recording", |ui| {
+            if ui.button("Start recording").clicked() {
+                ui.close_menu();
+                if let Some(path) = recording::pick_save_path() {
+                    callbacks.push(WindowRedrawCallbackCommand::StartRecordingInput(path));
+                }
+            }
+
+            if ui.button("Stop recording").clicked() {
+                ui.close_menu();
+                callbacks.push(WindowRedrawCallbackCommand::StopRecordingInput);
+            }
+
+            if ui.button("Replay").clicked() {
+                ui.close_menu();
+                if let Some(path) = recording::pick_open_path() {
+                    callbacks.push(WindowRedrawCallbackCommand::ReplayInput(path));
+                }
+            }
+        });
+    });
 }
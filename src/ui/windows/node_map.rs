@@ -1,8 +1,20 @@
 use super::*;
 
+use crate::{
+    history::{self, Command, History},
+    scene::primitives::{Primitive, PrimitiveKind, PrimitiveParameter},
+};
+
 pub struct NodeMapWindow {
     info: WindowInfo,
     node_graph_example: NodeGraphExample,
+    history: History<NodeGraphCommand>,
+    /// The editor state as of the last recorded edit, see `snapshot()`.
+    last_snapshot: String,
+    /// The parts of `last_snapshot` which edits change, see `snapshot_contents()`.
+    last_contents: String,
+    /// What the user did since the last recorded edit, if the graph editor said.
+    pending_edit: Option<&'static str>,
 }
 
 // ==== EXAMPLE {{{1
@@ -15,7 +27,7 @@ use std::{borrow::Cow, collections::HashMap};
 /// The NodeData holds a custom data struct inside each node. It's useful to
 /// store additional information that doesn't live in parameters. For this
 /// example, the node data stores the template (i.e. the "type") of the node.
-#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+#[derive(serde::Serialize, serde::Deserialize)]
 pub struct MyNodeData {
     template: MyNodeTemplate,
 }
@@ -23,8 +35,7 @@ pub struct MyNodeData {
 /// NodeTemplate is a mechanism to define node templates. It's what the graph
 /// will display in the "new node" popup. The user code needs to tell the
 /// library how to convert a NodeTemplate into a Node.
-#[derive(Clone, Copy, PartialEq)]
-#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum MyNodeTemplate {
     MakeScalar,
     AddScalar,
@@ -36,18 +47,19 @@ pub enum MyNodeTemplate {
     ThreeDScene,
     JsonConverter,
     Stdout,
+    MakePrimitive(PrimitiveKind),
 }
 
 /// `DataType`s are what defines the possible range of connections when
 /// attaching two ports together. The graph UI will make sure to not allow
 /// attaching incompatible datatypes.
-#[derive(PartialEq, Eq)]
-#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+#[derive(PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum MyDataType {
     Scalar,
     Vec2,
     SceneData,
     Str,
+    Primitive,
 }
 
 /// In the graph, input parameters can optionally have a constant value. This
@@ -57,13 +69,13 @@ pub enum MyDataType {
 /// this library makes no attempt to check this consistency. For instance, it is
 /// up to the user code in this example to make sure no parameter is created
 /// with a DataType of Scalar and a ValueType of Vec2.
-#[derive(Clone, Debug)]
-#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub enum MyValueType {
     Vec2 { value: egui::Vec2 },
     Scalar { value: f32 },
     SceneData { value: f32 },
     Str { value: String },
+    Primitive { value: Primitive },
 }
 
 impl Default for MyValueType {
@@ -100,24 +112,32 @@ impl MyValueType {
             anyhow::bail!("Invalid cast from {:?} to String", self)
         }
     }
+
+    pub fn try_to_primitive(self) -> anyhow::Result<Primitive> {
+        if let MyValueType::Primitive { value } = self {
+            Ok(value)
+        } else {
+            anyhow::bail!("Invalid cast from {:?} to primitive", self)
+        }
+    }
 }
 
 /// The response type is used to encode side-effects produced when drawing a
 /// node in the graph. Most side-effects (creating new nodes, deleting existing
 /// nodes, handling connections...) are already handled by the library, but this
 /// mechanism allows creating additional side effects from user code.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum MyResponse {
     SetActiveNode(NodeId),
     ClearActiveNode,
     Open3DSceneEditor,
+    AddPrimitiveToScene(Primitive),
 }
 
 /// The graph 'global' state. This state struct is passed around to the node and
 /// parameter drawing callbacks. The contents of this struct are entirely up to
 /// the user. For this example, we use it to keep track of the 'active' node.
-#[derive(Default)]
-#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Default, serde::Serialize, serde::Deserialize)]
 pub struct MyGraphState {
     pub active_node: Option<NodeId>,
 }
@@ -132,6 +152,7 @@ impl DataTypeTrait<MyGraphState> for MyDataType {
             MyDataType::Vec2 => egui::Color32::from_rgb(238, 207, 109),
             MyDataType::SceneData => egui::Color32::from_rgb(38, 109, 211),
             MyDataType::Str => egui::Color32::from_rgb(238, 207, 109),
+            MyDataType::Primitive => egui::Color32::from_rgb(109, 207, 144),
         }
     }
 
@@ -141,6 +162,7 @@ impl DataTypeTrait<MyGraphState> for MyDataType {
             MyDataType::Vec2 => Cow::Borrowed("2d vector"),
             MyDataType::SceneData => Cow::Borrowed("Scene data"),
             MyDataType::Str => Cow::Borrowed("String"),
+            MyDataType::Primitive => Cow::Borrowed("Primitive"),
         }
     }
 }
@@ -166,6 +188,7 @@ impl NodeTemplateTrait for MyNodeTemplate {
             MyNodeTemplate::ThreeDScene => "3D scene",
             MyNodeTemplate::JsonConverter => "JSON converter",
             MyNodeTemplate::Stdout => "Stdout",
+            MyNodeTemplate::MakePrimitive(kind) => kind.name(),
         })
     }
 
@@ -182,6 +205,7 @@ impl NodeTemplateTrait for MyNodeTemplate {
             MyNodeTemplate::ThreeDScene
             | MyNodeTemplate::JsonConverter
             | MyNodeTemplate::Stdout => vec!["Scene"],
+            MyNodeTemplate::MakePrimitive(_) => vec!["Primitive"],
         }
     }
 
@@ -263,6 +287,9 @@ impl NodeTemplateTrait for MyNodeTemplate {
         let output_str = |graph: &mut MyGraph, name: &str| {
             graph.add_output_param(node_id, name.to_string(), MyDataType::Str);
         };
+        let output_primitive = |graph: &mut MyGraph, name: &str| {
+            graph.add_output_param(node_id, name.to_string(), MyDataType::Primitive);
+        };
 
         match self {
             MyNodeTemplate::AddScalar => {
@@ -325,6 +352,24 @@ impl NodeTemplateTrait for MyNodeTemplate {
             MyNodeTemplate::Stdout => {
                 input_str(graph, "string");
             }
+            MyNodeTemplate::MakePrimitive(kind) => {
+                // One input per parameter, starting out at the default values.
+                for parameter in kind.default_primitive().parameters_mut() {
+                    let (name, value) = match parameter {
+                        PrimitiveParameter::Length(name, value) => (name, *value),
+                        PrimitiveParameter::Count(name, value) => (name, *value as f32),
+                    };
+                    graph.add_input_param(
+                        node_id,
+                        name.to_string(),
+                        MyDataType::Scalar,
+                        MyValueType::Scalar { value },
+                        InputParamKind::ConnectionOrConstant,
+                        true,
+                    );
+                }
+                output_primitive(graph, "primitive");
+            }
         }
     }
 }
@@ -337,7 +382,7 @@ impl NodeTemplateIter for AllMyNodeTemplates {
         // This function must return a list of node kinds, which the node finder
         // will use to display it to the user. Crates like strum can reduce the
         // boilerplate in enumerating all variants of an enum.
-        vec![
+        let mut kinds = vec![
             MyNodeTemplate::MakeScalar,
             MyNodeTemplate::MakeVector,
             MyNodeTemplate::AddScalar,
@@ -348,7 +393,9 @@ impl NodeTemplateIter for AllMyNodeTemplates {
             MyNodeTemplate::ThreeDScene,
             MyNodeTemplate::JsonConverter,
             MyNodeTemplate::Stdout,
-        ]
+        ];
+        kinds.extend(PrimitiveKind::ALL.map(MyNodeTemplate::MakePrimitive));
+        kinds
     }
 }
 
@@ -388,6 +435,9 @@ impl WidgetValueTrait for MyValueType {
             MyValueType::Str { value: _ } => {
                 ui.label(param_name);
             }
+            MyValueType::Primitive { value: _ } => {
+                ui.label(param_name);
+            }
         }
         // This allows you to return your responses from the inline widgets.
         Vec::new()
@@ -433,11 +483,28 @@ impl NodeDataTrait for MyNodeData {
             .map(|node| node.user_data.template == MyNodeTemplate::ThreeDScene)
             .unwrap_or(false);
 
+        let is_primitive_node = _graph
+            .nodes
+            .get(node_id)
+            .map(|node| matches!(node.user_data.template, MyNodeTemplate::MakePrimitive(_)))
+            .unwrap_or(false);
+
         if is_threedscene_node {
             if ui.button("Edit").clicked() {
                 responses.push(NodeResponse::User(MyResponse::Open3DSceneEditor));
             }
         } else {
+            if is_primitive_node && ui.button("Add to 3D scene").clicked() {
+                match evaluate_node(_graph, node_id, &mut HashMap::new())
+                    .and_then(MyValueType::try_to_primitive)
+                {
+                    Ok(primitive) => responses.push(NodeResponse::User(
+                        MyResponse::AddPrimitiveToScene(primitive),
+                    )),
+                    Err(e) => log::error!("failed to evaluate primitive: {:#}", e),
+                }
+            }
+
             // Pressing the button will emit a custom user response to either set,
             // or clear the active node. These responses do nothing by themselves,
             // the library only makes the responses available to you after the graph
@@ -464,7 +531,7 @@ type MyGraph = Graph<MyNodeData, MyDataType, MyValueType>;
 type MyEditorState =
     GraphEditorState<MyNodeData, MyDataType, MyValueType, MyNodeTemplate, MyGraphState>;
 
-#[derive(Default)]
+#[derive(Default, serde::Serialize, serde::Deserialize)]
 pub struct NodeGraphExample {
     // The `GraphEditorState` is the top-level object. You "register" all your
     // custom types by specifying it as its generic parameters.
@@ -572,6 +639,13 @@ pub fn evaluate_node(
         fn output_str(&mut self, name: &str, value: String) -> anyhow::Result<MyValueType> {
             self.populate_output(name, MyValueType::Str { value })
         }
+        fn output_primitive(
+            &mut self,
+            name: &str,
+            value: Primitive,
+        ) -> anyhow::Result<MyValueType> {
+            self.populate_output(name, MyValueType::Primitive { value })
+        }
     }
 
     let node = &graph[node_id];
@@ -623,6 +697,20 @@ pub fn evaluate_node(
                 value: "printed to stdout".to_string(),
             })
         }
+        MyNodeTemplate::MakePrimitive(kind) => {
+            let mut primitive = kind.default_primitive();
+            for parameter in primitive.parameters_mut() {
+                match parameter {
+                    PrimitiveParameter::Length(name, value) => {
+                        *value = evaluator.input_scalar(name)?.max(0.0);
+                    }
+                    PrimitiveParameter::Count(name, value) => {
+                        *value = PrimitiveParameter::count_from_f32(evaluator.input_scalar(name)?);
+                    }
+                }
+            }
+            evaluator.output_primitive("primitive", primitive)
+        }
     }
 }
 
@@ -676,6 +764,97 @@ fn evaluate_input(
 
 // ==== EXAMPLE }}}1
 
+/// An edit to the node graph, stored as the editor state from before and after it.
+/// Node graphs are small, so this is simpler than reversing each kind of edit.
+pub struct NodeGraphCommand {
+    before: String,
+    after: String,
+}
+
+/// The editor state as JSON.
+fn snapshot(state: &MyEditorState) -> String {
+    serde_json::to_string(state).unwrap_or_else(|e| {
+        log::error!("failed to serialize node graph: {}", e);
+        String::new()
+    })
+}
+
+/// The parts of the editor state which edits change, as JSON. Panning, zooming and
+/// selecting aren't edits.
+fn snapshot_contents(state: &MyEditorState) -> String {
+    serde_json::to_string(&(&state.graph, &state.node_positions)).unwrap_or_default()
+}
+
+/// Goes back to a state from `snapshot()`, keeping the current view.
+fn restore_snapshot(state: &mut MyEditorState, snapshot: &str) {
+    match serde_json::from_str::<MyEditorState>(snapshot) {
+        Ok(mut restored) => {
+            std::mem::swap(&mut restored.pan_zoom, &mut state.pan_zoom);
+            *state = restored;
+        }
+        Err(e) => log::error!("failed to restore node graph: {}", e),
+    }
+}
+
+impl Command for NodeGraphCommand {
+    type Target<'a> = MyEditorState;
+
+    fn undo(&mut self, state: &mut MyEditorState) {
+        restore_snapshot(state, &self.before);
+    }
+
+    fn redo(&mut self, state: &mut MyEditorState) {
+        restore_snapshot(state, &self.after);
+    }
+
+    fn memory_size(&self) -> usize {
+        self.before.len() + self.after.len()
+    }
+}
+
+impl NodeMapWindow {
+    /// Records what changed in the graph since the last recorded edit. Nothing is
+    /// recorded while a mouse button is held, so that a drag becomes a single edit.
+    /// The graph is only compared once the graph editor reports an edit, or a button
+    /// or key is let go, since that is when values typed or dragged in nodes change.
+    fn record_edit(&mut self) {
+        let (button_down, released) = self.info.egui_context.input(|input| {
+            let key_released = input
+                .events
+                .iter()
+                .any(|event| matches!(event, egui::Event::Key { pressed: false, .. }));
+            (
+                input.pointer.any_down(),
+                input.pointer.any_released() || key_released,
+            )
+        });
+        if button_down || (self.pending_edit.is_none() && !released) {
+            return;
+        }
+
+        let state = &self.node_graph_example.state;
+        let contents = snapshot_contents(state);
+        if contents == self.last_contents {
+            self.pending_edit = None;
+            return;
+        }
+
+        let after = snapshot(state);
+        let before = std::mem::replace(&mut self.last_snapshot, after.clone());
+        self.last_contents = contents;
+        let name = self.pending_edit.take().unwrap_or("Edit value");
+        self.history.push(name, NodeGraphCommand { before, after });
+    }
+
+    /// Makes the current state the one the next edit is recorded against.
+    fn reset_snapshot(&mut self) {
+        let state = &self.node_graph_example.state;
+        self.last_snapshot = snapshot(state);
+        self.last_contents = snapshot_contents(state);
+        self.pending_edit = None;
+    }
+}
+
 impl NodeMapWindow {
     pub fn create<T>(window_target: &winit::event_loop::EventLoopWindowTarget<T>) -> Self
     where
@@ -689,10 +868,16 @@ impl NodeMapWindow {
 
         let node_graph_example = NodeGraphExample::default();
 
-        Self {
+        let mut window = Self {
             info,
             node_graph_example,
-        }
+            history: History::new(),
+            last_snapshot: String::new(),
+            last_contents: String::new(),
+            pending_edit: None,
+        };
+        window.reset_snapshot();
+        window
     }
 }
 
@@ -703,6 +888,39 @@ impl WindowLike for NodeMapWindow {
 
     fn handle_input_event(&mut self, _input_state: &InputState, _input_event: input::InputEvent) {}
 
+    fn undo(&mut self) {
+        if self.history.undo(&mut self.node_graph_example.state) {
+            self.reset_snapshot();
+        } else {
+            log::info!("nothing to undo");
+        }
+    }
+
+    fn redo(&mut self) {
+        if self.history.redo(&mut self.node_graph_example.state) {
+            self.reset_snapshot();
+        } else {
+            log::info!("nothing to redo");
+        }
+    }
+
+    fn save_to_project(&self, project: &mut Project) {
+        match serde_json::to_value(&self.node_graph_example) {
+            Ok(value) => project.node_graph = Some(value),
+            Err(e) => log::error!("failed to serialize node graph: {}", e),
+        }
+    }
+
+    fn load_from_project(&mut self, project: &mut Project) -> anyhow::Result<()> {
+        if let Some(value) = project.node_graph.take() {
+            self.node_graph_example = serde_json::from_value(value)?;
+            self.history.clear();
+            self.reset_snapshot();
+        }
+
+        Ok(())
+    }
+
     fn close_requested(&mut self) -> WindowCloseCallbackCommand {
         WindowCloseCallbackCommand::QuitProgram
     }
@@ -737,6 +955,7 @@ impl WindowLike for NodeMapWindow {
         egui::TopBottomPanel::top("top").show(&self.info.egui_context, |ui| {
             egui::menu::bar(ui, |ui| {
                 egui::widgets::global_dark_light_mode_switch(ui);
+                draw_file_menu(ui, &mut callbacks);
             });
         });
         let graph_response = egui::CentralPanel::default()
@@ -750,6 +969,20 @@ impl WindowLike for NodeMapWindow {
             })
             .inner;
         for node_response in graph_response.node_responses {
+            // Name the edit for the history. Deleting a node disconnects it first, so
+            // later responses win, except that moving a node is only a last resort.
+            let edit = match &node_response {
+                NodeResponse::CreatedNode(_) => Some("Add node"),
+                NodeResponse::DeleteNodeFull { .. } => Some("Delete node"),
+                NodeResponse::ConnectEventEnded { .. } => Some("Connect"),
+                NodeResponse::DisconnectEvent { .. } => Some("Disconnect"),
+                NodeResponse::MoveNode { .. } => self.pending_edit.or(Some("Move node")),
+                _ => None,
+            };
+            if edit.is_some() {
+                self.pending_edit = edit;
+            }
+
             // Here, we ignore all other graph events. But you may find
             // some use for them. For example, by playing a sound when a new
             // connection is created
@@ -764,6 +997,9 @@ impl WindowLike for NodeMapWindow {
                     MyResponse::Open3DSceneEditor => {
                         callbacks.push(WindowRedrawCallbackCommand::Create3DWindow)
                     }
+                    MyResponse::AddPrimitiveToScene(primitive) => {
+                        callbacks.push(WindowRedrawCallbackCommand::AddPrimitive(primitive))
+                    }
                 }
             }
         }
@@ -792,6 +1028,21 @@ impl WindowLike for NodeMapWindow {
 
         // Example }}}
 
+        self.record_edit();
+
+        let mut history_position = None;
+        egui::Window::new("History")
+            .resizable(true)
+            .default_open(false)
+            .show(&self.info.egui_context, |ui| {
+                history_position = history::draw_egui_history(ui, &self.history);
+            });
+        if let Some(position) = history_position {
+            self.history
+                .go_to(position, &mut self.node_graph_example.state);
+            self.reset_snapshot();
+        }
+
         let egui::FullOutput {
             shapes,
             textures_delta,
@@ -1,11 +1,16 @@
-use crate::{config::RenderUserConfig, plugins::RendererPlugin};
+use crate::{config::RenderUserConfig, plugins::RendererPlugin, scene::plugin_scene::PluginScene};
 
 use super::*;
 
+const RENDER_WIDTH: u32 = 512;
+const RENDER_HEIGHT: u32 = 512;
+
 pub struct RenderWindow {
     info: WindowInfo,
     texture: RenderImage,
     renderer_plugin: Option<RendererPlugin>,
+    /// What the next render is of, once the scene has been gathered.
+    scene: Option<PluginScene>,
     render_settings_active: bool,
     renderer_path: String,
     render_in_progress: bool,
@@ -44,6 +49,7 @@ impl RenderWindow {
             info,
             texture: RenderImage::default(),
             renderer_plugin: None,
+            scene: None,
             render_settings_active: false,
             renderer_path,
             render_in_progress: false,
@@ -129,27 +135,37 @@ impl WindowLike for RenderWindow {
     }
 
     fn redraw(&mut self) -> Option<Vec<WindowRedrawCallbackCommand>> {
+        let mut callbacks = Vec::new();
+
         if self.reload_renderer && self.renderer_plugin.is_some() {
             if let Some(plug) = &mut self.renderer_plugin {
-                plug.reload().unwrap();
                 self.reload_renderer = false;
-                self.should_begin_render = true;
+                match plug.reload() {
+                    Ok(()) => callbacks.push(WindowRedrawCallbackCommand::BeginRender {
+                        width: RENDER_WIDTH,
+                        height: RENDER_HEIGHT,
+                    }),
+                    Err(e) => log::error!("failed to reload renderer plugin: {:#}", e),
+                }
             }
         }
 
         if self.should_begin_render {
             self.should_begin_render = false;
 
-            let renderer_plugin =
-                RendererPlugin::load_plugin(std::ffi::OsStr::new(&self.renderer_path), 512, 512);
-            if renderer_plugin.is_err() {
-                log::error!("failed to load renderer plugin");
+            let renderer_plugin = RendererPlugin::load_plugin(
+                std::ffi::OsStr::new(&self.renderer_path),
+                RENDER_WIDTH,
+                RENDER_HEIGHT,
+            );
+            if let Err(e) = &renderer_plugin {
+                log::error!("failed to load renderer plugin: {:#}", e);
             } else {
                 self.renderer_plugin = Some(renderer_plugin.unwrap());
                 self.renderer_plugin
                     .as_mut()
                     .unwrap()
-                    .begin_incremental_render();
+                    .begin_incremental_render(self.scene.take());
 
                 self.render_in_progress = true;
             }
@@ -214,7 +230,7 @@ impl WindowLike for RenderWindow {
 
         egui::TopBottomPanel::top("my_panel").show(&self.info.egui_context, |ui| {
             egui::menu::bar(ui, |ui| {
-                ui.menu_button("File", |ui| if ui.button("Save as").clicked() {});
+                draw_file_menu(ui, &mut callbacks);
 
                 ui.menu_button("Render", |ui| {
                     if ui.button("Settings").clicked() {
@@ -223,7 +239,10 @@ impl WindowLike for RenderWindow {
 
                     if ui.button("Render").clicked() {
                         if self.renderer_plugin.is_none() {
-                            self.should_begin_render = true;
+                            callbacks.push(WindowRedrawCallbackCommand::BeginRender {
+                                width: RENDER_WIDTH,
+                                height: RENDER_HEIGHT,
+                            });
                         }
                     }
 
@@ -235,6 +254,9 @@ impl WindowLike for RenderWindow {
         });
 
         egui::TopBottomPanel::bottom("render_info").show(&self.info.egui_context, |ui| {
+            if let Some(plug) = &self.renderer_plugin {
+                ui.label(format!("{} {}", plug.info().name, plug.info().version));
+            }
             ui.add(egui::ProgressBar::new(render_progress).show_percentage());
         });
 
@@ -293,13 +315,28 @@ impl WindowLike for RenderWindow {
         // Present the frame
         frame.present();
 
-        None
+        if callbacks.is_empty() {
+            None
+        } else {
+            Some(callbacks)
+        }
+    }
+
+    fn begin_render(&mut self, scene: Option<PluginScene>) {
+        self.scene = scene;
+        self.should_begin_render = true;
     }
 
     fn handle_input_event(&mut self, _input_state: &InputState, input_event: input::InputEvent) {
         match input_event {
             input::InputEvent::DoViewportOrbit => {}
             input::InputEvent::FinishViewportOrbit => {}
+            input::InputEvent::DoSelectionDrag => {}
+            input::InputEvent::FinishSelection => {}
+            input::InputEvent::DoViewportPan => {}
+            input::InputEvent::FinishViewportPan => {}
+            input::InputEvent::ViewportZoom => {}
+            input::InputEvent::ViewportLook => {}
         }
     }
 }
@@ -1,4 +1,23 @@
-use crate::scene::SceneData;
+use crate::{
+    bindings::{Action, ActionMap},
+    camera::{
+        window_to_ndc, CameraAnimation, CameraNavigation, CameraState, FlyControl, LensSettings,
+        ProjectionKind, RotationStyle, ViewPreset,
+    },
+    config::ViewportUserConfig,
+    gizmo::{Gizmo, GizmoMode, GizmoRenderRoutine, GizmoSpace},
+    history::{self, History},
+    light_icons,
+    scene::{
+        commands::{SceneCommand, SceneTarget},
+        graph::{NodeId, SceneNode, Transform},
+        plugin_scene::PluginScene,
+        primitives::{self, Primitive, PrimitiveKind, PrimitiveParameter},
+        CameraBookmark, SceneCamera, SceneCameras, SceneData, SceneLight, SceneLightKind,
+        SceneMaterial, SelectionMode,
+    },
+    ui::bindings_editor::BindingsEditor,
+};
 
 use super::*;
 
@@ -8,11 +27,68 @@ pub struct SceneViewer3D {
     pbr_routine: rend3_routine::pbr::PbrRoutine,
     tonemapping_routine: rend3_routine::tonemapping::TonemappingRoutine,
     grid_render_routine: GridRenderRoutine,
+    gizmo_render_routine: GizmoRenderRoutine,
     scene_data: SceneData,
+    gizmo: Gizmo,
+    history: History<SceneCommand>,
+    /// The camera from before the orbit or pan in progress, if any.
+    camera_drag_start: Option<CameraState>,
+    /// The camera from before the scrolling in progress, and when the wheel last moved.
+    zoom_start: Option<(CameraState, std::time::Instant)>,
+    navigation: CameraNavigation,
+    /// Moves the camera to a view picked from the "View" menu, a bit every frame.
+    camera_animation: Option<CameraAnimation>,
+    /// Kept between flights, so the speed the user picked is too.
+    fly_control: FlyControl,
+    flight: Option<Flight>,
+    /// The parameters for each kind of primitive in the "Add" menu, kept between uses.
+    new_primitives: Vec<Primitive>,
+    /// Where the selection button was pressed and where the cursor is now, in window
+    /// pixels, while the button is held down.
+    selection_box: Option<(glam::Vec2, glam::Vec2)>,
+    /// The active node and its properties from before the edit in progress in the
+    /// properties panel, if any. The edit is recorded once the user lets go.
+    properties_edit: Option<(NodeId, NodeProperties)>,
+    /// The rotation of the active node as XYZ Euler angles in degrees, along with the
+    /// rotation they stand for. Kept so that the angles don't jump between equivalent
+    /// values while they are being edited.
+    euler_angles: Option<(NodeId, glam::Quat, glam::Vec3)>,
+    /// Index of the light chosen in the outliner or by clicking its icon. It is shown
+    /// in the properties panel while no node is selected.
+    active_light: Option<usize>,
+    /// The active light from before the edit in progress in the properties panel, if
+    /// any.
+    light_edit: Option<(usize, SceneLight)>,
+    /// The bookmarks and scene cameras from before the edit in progress in the
+    /// bookmarks panel, if any.
+    bookmarks_edit: Option<(Vec<CameraBookmark>, SceneCameras)>,
+    /// The viewport camera from before the edit in progress in the camera panel, if any.
+    camera_edit: Option<CameraState>,
+    /// Kept in sync with the bindings used by the event loop, for showing shortcuts.
+    bindings: ActionMap,
+    /// Open while the key bindings are being edited.
+    bindings_editor: Option<BindingsEditor>,
 }
 
+/// How long the scroll wheel has to be still for the scrolling to be recorded as
+/// finished. Scrolling is made up of many small steps, which are undone together.
+const ZOOM_SETTLE_TIME: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// How long the camera takes to move to a view picked from the "View" menu.
+const VIEW_ANIMATION_TIME: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// How fast the camera flies to begin with, in units per second.
+const DEFAULT_FLY_SPEED: f32 = 2.0;
+
+/// How far the cursor can move, in pixels, between pressing and releasing the selection
+/// button for it to still count as a click rather than dragging out a box.
+const CLICK_DISTANCE: f32 = 4.0;
+
 impl SceneViewer3D {
-    pub fn create<T>(window_target: &winit::event_loop::EventLoopWindowTarget<T>) -> Self
+    pub fn create<T>(
+        window_target: &winit::event_loop::EventLoopWindowTarget<T>,
+        user_config: &Option<ViewportUserConfig>,
+    ) -> Self
     where
         T: 'static,
     {
@@ -31,6 +107,8 @@ impl SceneViewer3D {
 
         let grid_render_routine =
             GridRenderRoutine::new(&info.rend3_renderer, info.preferred_texture_format.clone());
+        let gizmo_render_routine =
+            GizmoRenderRoutine::new(&info.rend3_renderer, info.preferred_texture_format);
 
         let mut data_core = info.rend3_renderer.data_core.lock();
         let pbr_routine = rend3_routine::pbr::PbrRoutine::new(
@@ -57,13 +135,1149 @@ impl SceneViewer3D {
         // Initial scene.
         let scene_data = SceneData::initialize(info.window_size, &info.rend3_renderer);
 
+        let zoom_to_cursor = user_config
+            .as_ref()
+            .and_then(|conf| conf.zoom_to_cursor)
+            .unwrap_or(true);
+        let rotation_style = user_config
+            .as_ref()
+            .map_or(RotationStyle::Turntable, |conf| conf.get_rotation_style());
+
         Self {
             info,
             base_rendergraph,
             pbr_routine,
             tonemapping_routine,
             grid_render_routine,
+            gizmo_render_routine,
             scene_data,
+            gizmo: Gizmo::default(),
+            history: History::new(),
+            camera_drag_start: None,
+            zoom_start: None,
+            navigation: CameraNavigation::new(rotation_style, zoom_to_cursor),
+            camera_animation: None,
+            fly_control: FlyControl::new(DEFAULT_FLY_SPEED),
+            flight: None,
+            new_primitives: PrimitiveKind::ALL
+                .iter()
+                .map(|kind| kind.default_primitive())
+                .collect(),
+            selection_box: None,
+            properties_edit: None,
+            euler_angles: None,
+            active_light: None,
+            light_edit: None,
+            bookmarks_edit: None,
+            camera_edit: None,
+            bindings: ActionMap::default(),
+            bindings_editor: None,
+        }
+    }
+}
+
+/// A change to the view picked from the "View" menu.
+enum ViewRequest {
+    Projection(ProjectionKind),
+    Preset(ViewPreset),
+    /// Frame the selected nodes, or everything.
+    Frame {
+        selection_only: bool,
+    },
+    ToggleFlying,
+}
+
+/// The viewport camera flying around in first person, steered with the keyboard and
+/// the mouse.
+struct Flight {
+    /// The camera from before the flight, which is recorded as one edit once it ends.
+    start: CameraState,
+    /// The movement actions whose keys are held down.
+    held: std::collections::HashSet<Action>,
+    last_update: std::time::Instant,
+}
+
+/// Which way a movement action flies the camera, as for `Camera::fly_move()`.
+fn fly_direction(action: Action) -> Option<glam::Vec3> {
+    match action {
+        Action::FlyForward => Some(glam::Vec3::Z),
+        Action::FlyBackward => Some(glam::Vec3::NEG_Z),
+        Action::FlyLeft => Some(glam::Vec3::NEG_X),
+        Action::FlyRight => Some(glam::Vec3::X),
+        Action::FlyUp => Some(glam::Vec3::Y),
+        Action::FlyDown => Some(glam::Vec3::NEG_Y),
+        _ => None,
+    }
+}
+
+/// Scene interchange formats which can be imported into and exported from the viewer.
+enum SceneFileFormat {
+    Obj,
+    /// Text or binary glTF, depending on the file extension.
+    Gltf,
+}
+
+/// Asks the user where to export to. Adds the first of `extensions` if the user didn't
+/// type an extension.
+fn pick_export_path(filter_name: &str, extensions: &[&str]) -> Option<std::path::PathBuf> {
+    rfd::FileDialog::new()
+        .add_filter(filter_name, extensions)
+        .save_file()
+        .map(|path| {
+            if path.extension().is_none() {
+                path.with_extension(extensions[0])
+            } else {
+                path
+            }
+        })
+}
+
+fn edit_primitive_parameters(ui: &mut egui::Ui, primitive: &mut Primitive) {
+    egui::Grid::new("primitive_parameters")
+        .num_columns(2)
+        .show(ui, |ui| {
+            for parameter in primitive.parameters_mut() {
+                match parameter {
+                    PrimitiveParameter::Length(name, value) => {
+                        ui.label(name);
+                        ui.add(
+                            egui::DragValue::new(value)
+                                .speed(0.01)
+                                .clamp_range(0.0..=f32::MAX),
+                        );
+                    }
+                    PrimitiveParameter::Count(name, value) => {
+                        ui.label(name);
+                        ui.add(egui::DragValue::new(value).clamp_range(0..=primitives::MAX_COUNT));
+                    }
+                }
+                ui.end_row();
+            }
+        });
+}
+
+/// What the properties panel edits about a node.
+#[derive(Clone, PartialEq)]
+struct NodeProperties {
+    name: String,
+    transform: Transform,
+    visible: bool,
+    /// `None` for nodes without an object.
+    material: Option<MaterialFactors>,
+}
+
+impl NodeProperties {
+    fn of(node: &SceneNode) -> Self {
+        Self {
+            name: node.name.clone(),
+            transform: *node.transform(),
+            visible: node.visible(),
+            material: node
+                .object
+                .as_ref()
+                .map(|object| MaterialFactors::of(&object.material)),
+        }
+    }
+}
+
+/// The parts of a material which can be edited, leaving out the texture so that it
+/// isn't copied every frame.
+#[derive(Clone, Copy, PartialEq)]
+struct MaterialFactors {
+    albedo: glam::Vec4,
+    roughness: f32,
+    metallic: f32,
+}
+
+impl MaterialFactors {
+    fn of(material: &SceneMaterial) -> Self {
+        Self {
+            albedo: material.albedo,
+            roughness: material.roughness,
+            metallic: material.metallic,
+        }
+    }
+
+    fn apply_to(self, material: &mut SceneMaterial) {
+        material.albedo = self.albedo;
+        material.roughness = self.roughness;
+        material.metallic = self.metallic;
+    }
+}
+
+fn euler_degrees(rotation: glam::Quat) -> glam::Vec3 {
+    let (x, y, z) = rotation.to_euler(glam::EulerRot::XYZ);
+    glam::Vec3::new(x.to_degrees(), y.to_degrees(), z.to_degrees())
+}
+
+fn rotation_from_euler_degrees(angles: glam::Vec3) -> glam::Quat {
+    glam::Quat::from_euler(
+        glam::EulerRot::XYZ,
+        angles.x.to_radians(),
+        angles.y.to_radians(),
+        angles.z.to_radians(),
+    )
+}
+
+fn drag_vec3(ui: &mut egui::Ui, value: &mut glam::Vec3, speed: f32, suffix: &str) {
+    ui.horizontal(|ui| {
+        for component in [&mut value.x, &mut value.y, &mut value.z] {
+            ui.add(egui::DragValue::new(component).speed(speed).suffix(suffix));
+        }
+    });
+}
+
+/// Edits the settings particular to each kind of light.
+fn edit_light_kind(ui: &mut egui::Ui, kind: &mut SceneLightKind) {
+    let edit_range = |ui: &mut egui::Ui, range: &mut Option<f32>| {
+        ui.label("Range");
+        ui.horizontal(|ui| {
+            let mut limited = range.is_some();
+            ui.checkbox(&mut limited, "Limited");
+            if limited {
+                ui.add(
+                    egui::DragValue::new(range.get_or_insert(10.0))
+                        .speed(0.1)
+                        .clamp_range(0.0..=f32::MAX),
+                );
+            } else {
+                *range = None;
+            }
+        });
+        ui.end_row();
+    };
+    let edit_cone_angle = |ui: &mut egui::Ui, name: &str, angle: &mut f32| {
+        ui.label(name);
+        let mut degrees = angle.to_degrees();
+        if ui
+            .add(egui::Slider::new(&mut degrees, 0.0..=90.0).suffix("°"))
+            .changed()
+        {
+            *angle = degrees.to_radians();
+        }
+        ui.end_row();
+    };
+
+    match kind {
+        SceneLightKind::Directional {
+            direction,
+            distance,
+            resolution,
+        } => {
+            ui.label("Direction");
+            drag_vec3(ui, direction, 0.01, "");
+            ui.end_row();
+            ui.label("Shadow distance");
+            ui.add(egui::DragValue::new(distance).clamp_range(0.0..=f32::MAX));
+            ui.end_row();
+            ui.label("Shadow resolution");
+            egui::ComboBox::from_id_source("shadow_resolution")
+                .selected_text(resolution.to_string())
+                .show_ui(ui, |ui| {
+                    for option in [512, 1024, 2048, 4096] {
+                        ui.selectable_value(resolution, option, option.to_string());
+                    }
+                });
+            ui.end_row();
+        }
+        SceneLightKind::Point { position, range } => {
+            ui.label("Position");
+            drag_vec3(ui, position, 0.01, "");
+            ui.end_row();
+            edit_range(ui, range);
+        }
+        SceneLightKind::Spot {
+            position,
+            direction,
+            range,
+            inner_cone_angle,
+            outer_cone_angle,
+        } => {
+            ui.label("Position");
+            drag_vec3(ui, position, 0.01, "");
+            ui.end_row();
+            ui.label("Direction");
+            drag_vec3(ui, direction, 0.01, "");
+            ui.end_row();
+            edit_range(ui, range);
+            edit_cone_angle(ui, "Inner cone angle", inner_cone_angle);
+            edit_cone_angle(ui, "Outer cone angle", outer_cone_angle);
+            *inner_cone_angle = inner_cone_angle.min(*outer_cone_angle);
+        }
+        SceneLightKind::Area {
+            position,
+            direction,
+            width,
+            height,
+        } => {
+            ui.label("Position");
+            drag_vec3(ui, position, 0.01, "");
+            ui.end_row();
+            ui.label("Direction");
+            drag_vec3(ui, direction, 0.01, "");
+            ui.end_row();
+            ui.label("Size");
+            ui.horizontal(|ui| {
+                for length in [width, height] {
+                    ui.add(
+                        egui::DragValue::new(length)
+                            .speed(0.01)
+                            .clamp_range(0.0..=f32::MAX),
+                    );
+                }
+            });
+            ui.end_row();
+        }
+    }
+}
+
+/// What was clicked in the outliner.
+enum OutlinerAction {
+    Select(NodeId),
+    ToggleVisible(NodeId),
+    SelectLight(usize),
+}
+
+/// Draws a row for the node, with its children nested below it.
+fn draw_outliner_node(
+    ui: &mut egui::Ui,
+    scene: &SceneData,
+    id: NodeId,
+    actions: &mut Vec<OutlinerAction>,
+) {
+    let Some(node) = scene.graph().get(id) else {
+        return;
+    };
+
+    let mut draw_row = |ui: &mut egui::Ui| {
+        let mut visible = node.visible();
+        if ui
+            .checkbox(&mut visible, "")
+            .on_hover_text("Visible")
+            .changed()
+        {
+            actions.push(OutlinerAction::ToggleVisible(id));
+        }
+        if ui
+            .selectable_label(scene.is_selected(id), &node.name)
+            .clicked()
+        {
+            actions.push(OutlinerAction::Select(id));
+        }
+    };
+
+    if node.children().is_empty() {
+        ui.horizontal(draw_row);
+        return;
+    }
+
+    egui::collapsing_header::CollapsingState::load_with_default_open(
+        ui.ctx(),
+        ui.make_persistent_id(id),
+        true,
+    )
+    .show_header(ui, draw_row)
+    .body(|ui| {
+        for child in node.children() {
+            draw_outliner_node(ui, scene, *child, actions);
+        }
+    });
+}
+
+impl SceneViewer3D {
+    fn import(&mut self, format: SceneFileFormat, path: &std::path::Path) {
+        let imported = match format {
+            SceneFileFormat::Obj => {
+                crate::scene::obj::import_obj(path).map(|graph| (graph, Vec::new()))
+            }
+            SceneFileFormat::Gltf => crate::scene::gltf::import_gltf(path)
+                .map(|gltf_scene| (gltf_scene.graph, gltf_scene.lights)),
+        };
+        let (graph, lights) = match imported {
+            Ok(imported) => imported,
+            Err(e) => {
+                log::error!("{:#}", e);
+                return;
+            }
+        };
+
+        let object_count = graph
+            .iter()
+            .filter(|(_, node)| node.object.is_some())
+            .count();
+        let added = self
+            .scene_data
+            .add_graph(graph, None, &self.info.rend3_renderer);
+        // Undoing the roots takes their descendants with them.
+        let mut commands: Vec<SceneCommand> = added
+            .into_iter()
+            .filter(|id| {
+                self.scene_data
+                    .graph()
+                    .get(*id)
+                    .map_or(false, |node| node.parent().is_none())
+            })
+            .map(SceneCommand::add_node)
+            .collect();
+        let light_count = lights.len();
+        for light in lights {
+            commands.push(SceneCommand::add_light(self.scene_data.lights.len()));
+            self.scene_data.add_light(light, &self.info.rend3_renderer);
+        }
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        self.history
+            .push_group(format!("Import {}", file_name), commands);
+        log::info!(
+            "imported {} objects and {} lights from {}",
+            object_count,
+            light_count,
+            path.display()
+        );
+    }
+
+    fn add_primitive_node(&mut self, primitive: Primitive) {
+        let node = SceneNode::with_object(
+            primitive.name().to_string(),
+            Transform::IDENTITY,
+            primitive.to_object(),
+        );
+        match self
+            .scene_data
+            .add_node(node, None, &self.info.rend3_renderer)
+        {
+            Ok(id) => self.history.push(
+                format!("Add {}", primitive.name()),
+                SceneCommand::add_node(id),
+            ),
+            Err(e) => log::error!("failed to add {}: {:#}", primitive.name(), e),
+        }
+    }
+
+    /// Records the gizmo drag that just ended, if it moved anything.
+    fn finish_gizmo_drag(&mut self) {
+        let commands: Vec<SceneCommand> = self
+            .gizmo
+            .end_drag()
+            .into_iter()
+            .filter_map(|(id, before)| {
+                let after = *self.scene_data.graph().get(id)?.transform();
+                (before != after).then_some(SceneCommand::SetTransform { id, before, after })
+            })
+            .collect();
+        self.history.push_group(self.gizmo.mode.name(), commands);
+    }
+
+    /// Selects the object clicked on, or the objects in the box dragged out from
+    /// `start` to `end`. With `extend`, clicking toggles whether the object is
+    /// selected and boxes add to the selection.
+    fn finish_selection(&mut self, start: glam::Vec2, end: glam::Vec2, extend: bool) {
+        let window_size = self.info.resolution.as_vec2();
+        let is_click = start.distance(end) < CLICK_DISTANCE;
+        if is_click && !extend {
+            if let Some(index) = light_icons::light_at(&self.scene_data, end, window_size) {
+                self.select_light(index);
+                return;
+            }
+        }
+
+        let (ids, mode): (Vec<NodeId>, _) = if is_click {
+            let picked = self.scene_data.pick(window_to_ndc(end, window_size));
+            let mode = if extend {
+                SelectionMode::Toggle
+            } else {
+                SelectionMode::Replace
+            };
+            (picked.into_iter().collect(), mode)
+        } else {
+            let picked = self.scene_data.pick_in_rectangle(
+                window_to_ndc(start, window_size),
+                window_to_ndc(end, window_size),
+            );
+            let mode = if extend {
+                SelectionMode::Extend
+            } else {
+                SelectionMode::Replace
+            };
+            (picked, mode)
+        };
+        self.select(&ids, mode);
+    }
+
+    /// Changes the selection, recording it if anything changed.
+    fn select(&mut self, ids: &[NodeId], mode: SelectionMode) {
+        self.active_light = None;
+        let before = self.scene_data.selection().to_vec();
+        self.scene_data.select(ids, mode, &self.info.rend3_renderer);
+        let after = self.scene_data.selection().to_vec();
+        if before != after {
+            self.history
+                .push("Select", SceneCommand::Select { before, after });
+        }
+    }
+
+    /// Deselects every node so that the light at `index` is shown in the properties
+    /// panel.
+    fn select_light(&mut self, index: usize) {
+        self.select(&[], SelectionMode::Replace);
+        self.active_light = Some(index);
+    }
+
+    fn add_light(&mut self, light: SceneLight) {
+        let index = self.scene_data.lights.len();
+        let name = format!("Add {} light", light.kind.name());
+        self.scene_data.add_light(light, &self.info.rend3_renderer);
+        self.history.push(name, SceneCommand::add_light(index));
+        self.select_light(index);
+    }
+
+    /// Moves the camera smoothly to `to`, recording the move as soon as it starts.
+    fn animate_camera(&mut self, name: &str, to: CameraState) {
+        self.stop_flying();
+        let before = self.scene_data.camera.get_state();
+        self.history.push(
+            name,
+            SceneCommand::SetCamera {
+                before: before.clone(),
+                after: to.clone(),
+            },
+        );
+        self.camera_animation = Some(CameraAnimation::new(before, to, VIEW_ANIMATION_TIME));
+    }
+
+    fn set_view_preset(&mut self, preset: ViewPreset) {
+        let to = self.scene_data.camera.preset_state(preset);
+        self.animate_camera(&format!("{} view", preset.name()), to);
+    }
+
+    fn set_projection(&mut self, projection: ProjectionKind) {
+        let camera = &mut self.scene_data.camera;
+        if camera.get_projection() == projection {
+            return;
+        }
+        let before = camera.get_state();
+        camera.set_projection(projection);
+        let after = camera.get_state();
+        self.info
+            .rend3_renderer
+            .set_camera_data(camera.to_rend3_camera());
+        self.history
+            .push(projection.name(), SceneCommand::SetCamera { before, after });
+    }
+
+    /// Moves the camera to fit the selected nodes in the view, or with `selection_only`
+    /// false, the whole scene.
+    fn frame(&mut self, selection_only: bool) {
+        let bounds = if selection_only {
+            self.scene_data.bounds(self.scene_data.selection())
+        } else {
+            self.scene_data.bounds(self.scene_data.graph().roots())
+        };
+        let Some((min, max)) = bounds else {
+            log::info!("nothing to frame");
+            return;
+        };
+        let to = self.scene_data.camera.framing_state(min, max);
+        let name = if selection_only {
+            "Frame selected"
+        } else {
+            "Frame all"
+        };
+        self.animate_camera(name, to);
+    }
+
+    /// Edits the lens of the viewport camera. Changes are applied as they are made.
+    fn draw_camera_properties(&mut self, ui: &mut egui::Ui) {
+        let current = self.scene_data.camera.lens();
+        let mut edited = current;
+
+        egui::Grid::new("camera_properties")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Field of view");
+                ui.add(
+                    egui::DragValue::new(&mut edited.vertical_fov)
+                        .speed(0.5)
+                        .clamp_range(1.0..=170.0)
+                        .suffix("°"),
+                );
+                ui.end_row();
+
+                ui.label("Focal length");
+                let mut focal_length = edited.focal_length();
+                let response = ui.add(
+                    egui::DragValue::new(&mut focal_length)
+                        .speed(0.5)
+                        .clamp_range(1.0..=5000.0)
+                        .suffix(" mm"),
+                );
+                if response.changed() {
+                    edited.set_focal_length(focal_length);
+                }
+                ui.end_row();
+
+                ui.label("Sensor size");
+                ui.horizontal(|ui| {
+                    for size in [&mut edited.sensor_size.x, &mut edited.sensor_size.y] {
+                        ui.add(
+                            egui::DragValue::new(size)
+                                .speed(0.1)
+                                .clamp_range(1.0..=500.0)
+                                .suffix(" mm"),
+                        );
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Near");
+                ui.add(
+                    egui::DragValue::new(&mut edited.z_near)
+                        .speed(0.01)
+                        .clamp_range(0.001..=edited.z_far.unwrap_or(f32::MAX)),
+                );
+                ui.end_row();
+
+                ui.label("Far");
+                ui.horizontal(|ui| {
+                    let mut infinite = edited.z_far.is_none();
+                    ui.checkbox(&mut infinite, "Infinite");
+                    edited.z_far = match (infinite, edited.z_far) {
+                        (true, _) => None,
+                        (false, Some(z_far)) => Some(z_far),
+                        (false, None) => Some(edited.z_near * 1000.0),
+                    };
+                    if let Some(z_far) = &mut edited.z_far {
+                        ui.add(
+                            egui::DragValue::new(z_far)
+                                .speed(1.0)
+                                .clamp_range(edited.z_near..=f32::MAX),
+                        );
+                    }
+                });
+                ui.end_row();
+            });
+
+        if edited != current {
+            if self.camera_edit.is_none() {
+                self.commit_properties_edit();
+                self.camera_edit = Some(self.scene_data.camera.get_state());
+            }
+            self.set_lens(edited);
+        }
+    }
+
+    fn set_lens(&mut self, lens: LensSettings) {
+        self.scene_data.camera.set_lens(lens);
+        self.info
+            .rend3_renderer
+            .set_camera_data(self.scene_data.camera.to_rend3_camera());
+    }
+
+    fn go_to_bookmark(&mut self, index: usize) {
+        let Some(bookmark) = self.scene_data.bookmarks.get(index) else {
+            log::info!("there is no bookmark {}", index + 1);
+            return;
+        };
+        let name = format!("Go to {}", bookmark.name);
+        self.animate_camera(&name, bookmark.camera.clone());
+    }
+
+    /// Lists the saved views, and the cameras of the scene which renders can be made
+    /// through. Edits are applied as they are made.
+    fn draw_bookmarks(&mut self, ui: &mut egui::Ui) {
+        let current = self.scene_data.camera.get_state();
+        let mut bookmarks = self.scene_data.bookmarks.clone();
+        let mut cameras = self.scene_data.cameras.clone();
+        let mut go_to = None;
+
+        if ui
+            .button("Add bookmark")
+            .on_hover_text("Saves the current view")
+            .clicked()
+        {
+            bookmarks.push(CameraBookmark {
+                name: format!("View {}", bookmarks.len() + 1),
+                camera: current.clone(),
+            });
+        }
+        let mut removed = None;
+        egui::Grid::new("bookmarks").show(ui, |ui| {
+            for (index, bookmark) in bookmarks.iter_mut().enumerate() {
+                // The key which goes to the bookmark, if it has one.
+                ui.label(self.bindings.shortcut_text(Action::GoToBookmark(index)));
+                ui.text_edit_singleline(&mut bookmark.name);
+                if ui.button("Go").clicked() {
+                    go_to = Some((bookmark.name.clone(), bookmark.camera.clone()));
+                }
+                if ui
+                    .button("Update")
+                    .on_hover_text("Replaces the saved view with the current one")
+                    .clicked()
+                {
+                    bookmark.camera = current.clone();
+                }
+                if ui
+                    .button("Make camera")
+                    .on_hover_text("Adds a camera to the scene which renders can be made through")
+                    .clicked()
+                {
+                    cameras.cameras.push(SceneCamera::from(bookmark.clone()));
+                    cameras.active.get_or_insert(cameras.cameras.len() - 1);
+                }
+                if ui.button("Remove").clicked() {
+                    removed = Some(index);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(index) = removed {
+            bookmarks.remove(index);
+        }
+
+        ui.separator();
+        ui.label("Render through");
+        ui.radio_value(&mut cameras.active, None, "The viewport");
+        let mut removed = None;
+        egui::Grid::new("scene_cameras").show(ui, |ui| {
+            for (index, camera) in cameras.cameras.iter_mut().enumerate() {
+                ui.radio_value(&mut cameras.active, Some(index), "");
+                ui.text_edit_singleline(&mut camera.name);
+                if ui.button("Look through").clicked() {
+                    go_to = Some((camera.name.clone(), camera.camera.clone()));
+                }
+                if ui.button("Remove").clicked() {
+                    removed = Some(index);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(index) = removed {
+            cameras.remove(index);
+        }
+
+        if bookmarks != self.scene_data.bookmarks || cameras != self.scene_data.cameras {
+            if self.bookmarks_edit.is_none() {
+                self.commit_properties_edit();
+                self.bookmarks_edit = Some((
+                    std::mem::take(&mut self.scene_data.bookmarks),
+                    std::mem::take(&mut self.scene_data.cameras),
+                ));
+            }
+            self.scene_data.bookmarks = bookmarks;
+            self.scene_data.cameras = cameras;
+        }
+        if let Some((name, camera)) = go_to {
+            self.animate_camera(&format!("Go to {}", name), camera);
+        }
+    }
+
+    /// Hides the cursor and lets the keyboard and mouse fly the camera around, until
+    /// `stop_flying()`. The camera keeps looking the same way, so the view doesn't jump.
+    fn start_flying(&mut self) {
+        if self.flight.is_some() {
+            return;
+        }
+        self.camera_animation = None;
+        self.flight = Some(Flight {
+            start: self.scene_data.camera.get_state(),
+            held: std::collections::HashSet::new(),
+            last_update: std::time::Instant::now(),
+        });
+
+        // Keep the cursor from leaving the window, so that clicks don't go elsewhere
+        // while it is hidden. Not every platform can lock it in place.
+        use winit::window::CursorGrabMode;
+        let window = &self.info.raw_window;
+        if window.set_cursor_grab(CursorGrabMode::Locked).is_err() {
+            if let Err(e) = window.set_cursor_grab(CursorGrabMode::Confined) {
+                log::warn!("couldn't grab the cursor: {}", e);
+            }
+        }
+        window.set_cursor_visible(false);
+    }
+
+    /// Goes back to orbiting around the point in front of the camera.
+    fn stop_flying(&mut self) {
+        let Some(flight) = self.flight.take() else {
+            return;
+        };
+        self.fly_control.stop();
+
+        let window = &self.info.raw_window;
+        if let Err(e) = window.set_cursor_grab(winit::window::CursorGrabMode::None) {
+            log::warn!("couldn't release the cursor: {}", e);
+        }
+        window.set_cursor_visible(true);
+
+        let after = self.scene_data.camera.get_state();
+        if after != flight.start {
+            self.history.push(
+                "Fly",
+                SceneCommand::SetCamera {
+                    before: flight.start,
+                    after,
+                },
+            );
+        }
+    }
+
+    fn toggle_flying(&mut self) {
+        if self.flight.is_some() {
+            self.stop_flying();
+        } else {
+            self.start_flying();
+        }
+    }
+
+    fn update_flight(&mut self) {
+        // Keys released in another window never reach this one.
+        if !self.info.raw_window.has_focus() {
+            self.stop_flying();
+        }
+        let Some(flight) = &mut self.flight else {
+            return;
+        };
+
+        let now = std::time::Instant::now();
+        let direction = flight
+            .held
+            .iter()
+            .filter_map(|action| fly_direction(*action))
+            .sum();
+        self.fly_control.update(
+            &mut self.scene_data.camera,
+            direction,
+            now - flight.last_update,
+        );
+        flight.last_update = now;
+        self.info
+            .rend3_renderer
+            .set_camera_data(self.scene_data.camera.to_rend3_camera());
+
+        egui::Area::new("fly_hint")
+            .anchor(egui::Align2::LEFT_BOTTOM, [8.0, -8.0])
+            .show(&self.info.egui_context, |ui| {
+                ui.label(format!(
+                    "Flying at {:.2} units/s. WASD moves, Q and E go down and up, scrolling \
+                     changes the speed, Esc stops.",
+                    self.fly_control.speed
+                ));
+            });
+    }
+
+    /// Lists the nodes of the scene as a tree, followed by the lights. Clicking a node
+    /// selects it, or with Ctrl held, toggles whether it is selected.
+    fn draw_outliner(&mut self, ui: &mut egui::Ui) {
+        let mut actions = Vec::new();
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                for root in self.scene_data.graph().roots() {
+                    draw_outliner_node(ui, &self.scene_data, *root, &mut actions);
+                }
+
+                ui.separator();
+                ui.label("Lights");
+                for (index, light) in self.scene_data.lights.iter().enumerate() {
+                    let selected = self.active_light == Some(index);
+                    if ui.selectable_label(selected, &light.name).clicked() {
+                        actions.push(OutlinerAction::SelectLight(index));
+                    }
+                }
+            });
+
+        let extend = ui.input(|input| input.modifiers.ctrl);
+        for action in actions {
+            match action {
+                OutlinerAction::Select(id) => {
+                    let mode = if extend {
+                        SelectionMode::Toggle
+                    } else {
+                        SelectionMode::Replace
+                    };
+                    self.select(&[id], mode);
+                }
+                OutlinerAction::SelectLight(index) => self.select_light(index),
+                OutlinerAction::ToggleVisible(id) => {
+                    let Some(before) = self.scene_data.graph().get(id).map(SceneNode::visible)
+                    else {
+                        continue;
+                    };
+                    let after = !before;
+                    self.scene_data
+                        .set_visible(id, after, &self.info.rend3_renderer);
+                    self.history.push(
+                        if after { "Show" } else { "Hide" },
+                        SceneCommand::SetVisible { id, before, after },
+                    );
+                }
+            }
+        }
+    }
+
+    /// Edits the most recently selected node. Changes are applied as they are made.
+    fn draw_properties(&mut self, ui: &mut egui::Ui) {
+        let Some(id) = self.scene_data.selection().last().copied() else {
+            match self.active_light {
+                Some(index) if index < self.scene_data.lights.len() => {
+                    self.draw_light_properties(ui, index);
+                }
+                _ => {
+                    ui.label("Nothing is selected.");
+                }
+            }
+            return;
+        };
+        let Some(node) = self.scene_data.graph().get(id) else {
+            return;
+        };
+
+        let current = NodeProperties::of(node);
+        let mut edited = current.clone();
+        let angles = match self.euler_angles {
+            Some((cached_id, rotation, angles))
+                if cached_id == id && rotation == current.transform.rotation =>
+            {
+                angles
+            }
+            _ => euler_degrees(current.transform.rotation),
+        };
+        let mut edited_angles = angles;
+
+        egui::Grid::new("node_properties")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Name");
+                ui.text_edit_singleline(&mut edited.name);
+                ui.end_row();
+
+                ui.label("Visible");
+                ui.checkbox(&mut edited.visible, "");
+                ui.end_row();
+
+                ui.label("Location");
+                drag_vec3(ui, &mut edited.transform.translation, 0.01, "");
+                ui.end_row();
+                ui.label("Rotation");
+                drag_vec3(ui, &mut edited_angles, 1.0, "°");
+                ui.end_row();
+                ui.label("Scale");
+                drag_vec3(ui, &mut edited.transform.scale, 0.01, "");
+                ui.end_row();
+
+                if let Some(material) = &mut edited.material {
+                    ui.label("Albedo");
+                    let [r, g, b, a] = material.albedo.to_array();
+                    let mut albedo = egui::Rgba::from_rgba_unmultiplied(r, g, b, a);
+                    if egui::color_picker::color_edit_button_rgba(
+                        ui,
+                        &mut albedo,
+                        egui::color_picker::Alpha::OnlyBlend,
+                    )
+                    .changed()
+                    {
+                        material.albedo = glam::Vec4::from(albedo.to_rgba_unmultiplied());
+                    }
+                    ui.end_row();
+
+                    ui.label("Roughness");
+                    ui.add(egui::Slider::new(&mut material.roughness, 0.0..=1.0));
+                    ui.end_row();
+                    ui.label("Metallic");
+                    ui.add(egui::Slider::new(&mut material.metallic, 0.0..=1.0));
+                    ui.end_row();
+                }
+            });
+
+        if edited_angles != angles {
+            edited.transform.rotation = rotation_from_euler_degrees(edited_angles);
+        }
+        self.euler_angles = Some((id, edited.transform.rotation, edited_angles));
+
+        if edited != current {
+            match &self.properties_edit {
+                Some((edit_id, _)) if *edit_id == id => {}
+                _ => {
+                    self.commit_properties_edit();
+                    self.properties_edit = Some((id, current.clone()));
+                }
+            }
+            self.apply_node_properties(id, &current, &edited);
+        }
+    }
+
+    /// Edits the light at `index`. Changes are applied as they are made.
+    fn draw_light_properties(&mut self, ui: &mut egui::Ui, index: usize) {
+        let current = self.scene_data.lights[index].clone();
+        let mut edited = current.clone();
+
+        egui::Grid::new("light_properties")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Name");
+                ui.text_edit_singleline(&mut edited.name);
+                ui.end_row();
+
+                ui.label("Type");
+                ui.label(edited.kind.name());
+                ui.end_row();
+
+                ui.label("Color");
+                let mut color = edited.color.to_array();
+                egui::color_picker::color_edit_button_rgb(ui, &mut color);
+                edited.color = glam::Vec3::from(color);
+                ui.end_row();
+
+                ui.label("Intensity");
+                ui.add(
+                    egui::DragValue::new(&mut edited.intensity)
+                        .speed(0.1)
+                        .clamp_range(0.0..=f32::MAX),
+                );
+                ui.end_row();
+
+                edit_light_kind(ui, &mut edited.kind);
+            });
+
+        if edited != current {
+            match &self.light_edit {
+                Some((edit_index, _)) if *edit_index == index => {}
+                _ => {
+                    self.commit_properties_edit();
+                    self.light_edit = Some((index, current));
+                }
+            }
+            self.scene_data
+                .set_light(index, edited, &self.info.rend3_renderer);
+        }
+    }
+
+    fn apply_node_properties(
+        &mut self,
+        id: NodeId,
+        current: &NodeProperties,
+        edited: &NodeProperties,
+    ) {
+        let renderer = &self.info.rend3_renderer;
+        if edited.name != current.name {
+            self.scene_data.set_name(id, edited.name.clone());
+        }
+        if edited.visible != current.visible {
+            self.scene_data.set_visible(id, edited.visible, renderer);
+        }
+        if edited.transform != current.transform {
+            self.scene_data
+                .set_transform(id, edited.transform, renderer);
+        }
+        if let Some(factors) = edited
+            .material
+            .filter(|_| edited.material != current.material)
+        {
+            let node = self.scene_data.graph().get(id);
+            if let Some(object) = node.and_then(|node| node.object.as_ref()) {
+                let mut material = object.material.clone();
+                factors.apply_to(&mut material);
+                self.scene_data.set_material(id, material, renderer);
+            }
+        }
+    }
+
+    /// Records the edit made in the properties panel, if there is one, as a single entry.
+    fn commit_properties_edit(&mut self) {
+        if let Some(before) = self.camera_edit.take() {
+            let after = self.scene_data.camera.get_state();
+            if after != before {
+                self.history
+                    .push("Edit camera", SceneCommand::SetCamera { before, after });
+            }
+        }
+        if let Some((bookmarks, cameras)) = self.bookmarks_edit.take() {
+            let mut commands = Vec::new();
+            if bookmarks != self.scene_data.bookmarks {
+                commands.push(SceneCommand::SetBookmarks {
+                    before: bookmarks,
+                    after: self.scene_data.bookmarks.clone(),
+                });
+            }
+            if cameras != self.scene_data.cameras {
+                commands.push(SceneCommand::SetSceneCameras {
+                    before: cameras,
+                    after: self.scene_data.cameras.clone(),
+                });
+            }
+            self.history.push_group("Edit bookmarks", commands);
+        }
+
+        if let Some((index, before)) = self.light_edit.take() {
+            if let Some(after) = self.scene_data.lights.get(index) {
+                if *after != before {
+                    self.history.push(
+                        format!("Edit {}", after.name),
+                        SceneCommand::SetLight {
+                            index,
+                            before,
+                            after: after.clone(),
+                        },
+                    );
+                }
+            }
+        }
+
+        let Some((id, before)) = self.properties_edit.take() else {
+            return;
+        };
+        let Some(node) = self.scene_data.graph().get(id) else {
+            return;
+        };
+        let after = NodeProperties::of(node);
+
+        let mut commands = Vec::new();
+        if before.name != after.name {
+            commands.push(SceneCommand::SetName {
+                id,
+                before: before.name,
+                after: after.name.clone(),
+            });
+        }
+        if before.visible != after.visible {
+            commands.push(SceneCommand::SetVisible {
+                id,
+                before: before.visible,
+                after: after.visible,
+            });
+        }
+        if before.transform != after.transform {
+            commands.push(SceneCommand::SetTransform {
+                id,
+                before: before.transform,
+                after: after.transform,
+            });
+        }
+        if let (Some(object), Some(factors)) = (&node.object, before.material) {
+            if before.material != after.material {
+                let mut material = object.material.clone();
+                factors.apply_to(&mut material);
+                commands.push(SceneCommand::SetMaterial {
+                    id,
+                    before: material,
+                    after: object.material.clone(),
+                });
+            }
+        }
+        self.history
+            .push_group(format!("Edit {}", after.name), commands);
+    }
+
+    fn export(&self, format: SceneFileFormat, path: &std::path::Path) {
+        let graph = self.scene_data.graph();
+        let lights = &self.scene_data.lights;
+        let exported = match format {
+            SceneFileFormat::Obj => crate::scene::obj::export_obj(graph, path),
+            SceneFileFormat::Gltf => crate::scene::gltf::export_gltf(graph, lights, path),
+        };
+
+        match exported {
+            Ok(()) => log::info!("exported scene to {}", path.display()),
+            Err(e) => log::error!("failed to export scene: {:#}", e),
         }
     }
 }
@@ -96,6 +1310,8 @@ impl WindowLike for SceneViewer3D {
     }
 
     fn redraw(&mut self) -> Option<Vec<WindowRedrawCallbackCommand>> {
+        let mut callbacks = Vec::new();
+
         // UI
         self.info.egui_context.begin_frame(
             self.info
@@ -103,11 +1319,263 @@ impl WindowLike for SceneViewer3D {
                 .take_egui_input(&self.info.raw_window),
         );
 
-        egui::Window::new("Change color")
+        let mut import_request = None;
+        let mut export_request = None;
+        let mut add_request = None;
+        let mut add_light_request = None;
+        let mut view_request = None;
+        egui::TopBottomPanel::top("scene_viewer_menu").show(&self.info.egui_context, |ui| {
+            egui::menu::bar(ui, |ui| {
+                draw_file_menu(ui, &mut callbacks);
+
+                ui.menu_button("Import", |ui| {
+                    if ui.button("Wavefront (.obj)").clicked() {
+                        ui.close_menu();
+                        import_request = rfd::FileDialog::new()
+                            .add_filter("Wavefront OBJ", &["obj"])
+                            .pick_file()
+                            .map(|path| (SceneFileFormat::Obj, path));
+                    }
+
+                    if ui.button("glTF (.gltf, .glb)").clicked() {
+                        ui.close_menu();
+                        import_request = rfd::FileDialog::new()
+                            .add_filter("glTF", &["gltf", "glb"])
+                            .pick_file()
+                            .map(|path| (SceneFileFormat::Gltf, path));
+                    }
+                });
+
+                ui.menu_button("Export", |ui| {
+                    if ui.button("Wavefront (.obj)").clicked() {
+                        ui.close_menu();
+                        export_request = pick_export_path("Wavefront OBJ", &["obj"])
+                            .map(|path| (SceneFileFormat::Obj, path));
+                    }
+
+                    if ui.button("glTF (.glb, .gltf)").clicked() {
+                        ui.close_menu();
+                        export_request = pick_export_path("glTF", &["glb", "gltf"])
+                            .map(|path| (SceneFileFormat::Gltf, path));
+                    }
+                });
+
+                ui.menu_button("Add", |ui| {
+                    for primitive in &mut self.new_primitives {
+                        ui.menu_button(primitive.name(), |ui| {
+                            edit_primitive_parameters(ui, primitive);
+                            if ui.button("Add").clicked() {
+                                ui.close_menu();
+                                add_request = Some(*primitive);
+                            }
+                        });
+                    }
+                    ui.separator();
+                    ui.menu_button("Light", |ui| {
+                        for kind in SceneLightKind::defaults() {
+                            if ui.button(kind.name()).clicked() {
+                                ui.close_menu();
+                                add_light_request = Some(SceneLight::with_kind(kind));
+                            }
+                        }
+                    });
+                });
+
+                ui.menu_button("View", |ui| {
+                    ui.checkbox(&mut self.navigation.zoom_to_cursor, "Zoom to cursor");
+                    ui.separator();
+                    let projection = self.scene_data.camera.get_projection();
+                    for kind in [ProjectionKind::Perspective, ProjectionKind::Orthographic] {
+                        if ui.radio(projection == kind, kind.name()).clicked() {
+                            view_request = Some(ViewRequest::Projection(kind));
+                        }
+                    }
+                    let toggle = self.bindings.shortcut_text(Action::ToggleProjection);
+                    if !toggle.is_empty() {
+                        ui.label(format!("{} switches between them.", toggle));
+                    }
+                    ui.separator();
+                    for (preset, action) in [
+                        (ViewPreset::Front, Action::ViewFront),
+                        (ViewPreset::Back, Action::ViewBack),
+                        (ViewPreset::Right, Action::ViewRight),
+                        (ViewPreset::Left, Action::ViewLeft),
+                        (ViewPreset::Top, Action::ViewTop),
+                        (ViewPreset::Bottom, Action::ViewBottom),
+                    ] {
+                        let shortcut = self.bindings.shortcut_text(action);
+                        let button = egui::Button::new(preset.name()).shortcut_text(shortcut);
+                        if ui.add(button).clicked() {
+                            ui.close_menu();
+                            view_request = Some(ViewRequest::Preset(preset));
+                        }
+                    }
+                    ui.separator();
+                    for (selection_only, action) in
+                        [(true, Action::FrameSelected), (false, Action::FrameAll)]
+                    {
+                        let shortcut = self.bindings.shortcut_text(action);
+                        let button = egui::Button::new(action.name()).shortcut_text(shortcut);
+                        if ui.add(button).clicked() {
+                            ui.close_menu();
+                            view_request = Some(ViewRequest::Frame { selection_only });
+                        }
+                    }
+                    ui.separator();
+                    let fly = egui::Button::new("Fly")
+                        .shortcut_text(self.bindings.shortcut_text(Action::ToggleFlying));
+                    if ui.add(fly).clicked() {
+                        ui.close_menu();
+                        view_request = Some(ViewRequest::ToggleFlying);
+                    }
+                    ui.separator();
+                    ui.label("Rotation");
+                    for style in RotationStyle::ALL {
+                        ui.radio_value(&mut self.navigation.rotation_style, style, style.name());
+                    }
+                    ui.separator();
+                    if ui.button("Key bindings...").clicked() {
+                        ui.close_menu();
+                        self.bindings_editor = Some(BindingsEditor::new(&self.bindings));
+                    }
+                });
+
+                ui.separator();
+                for mode in GizmoMode::ALL {
+                    ui.selectable_value(&mut self.gizmo.mode, mode, mode.name());
+                }
+                egui::ComboBox::from_id_source("gizmo_space")
+                    .selected_text(self.gizmo.space.name())
+                    .show_ui(ui, |ui| {
+                        for space in GizmoSpace::ALL {
+                            ui.selectable_value(&mut self.gizmo.space, space, space.name());
+                        }
+                    });
+                ui.menu_button("Snap", |ui| {
+                    let snap = &mut self.gizmo.snap;
+                    ui.checkbox(&mut snap.enabled, "Snap while dragging");
+                    egui::Grid::new("snap_settings")
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            ui.label("Move");
+                            ui.add(
+                                egui::DragValue::new(&mut snap.translation)
+                                    .speed(0.01)
+                                    .clamp_range(0.0..=f32::MAX),
+                            );
+                            ui.end_row();
+                            ui.label("Rotate (degrees)");
+                            ui.add(
+                                egui::DragValue::new(&mut snap.rotation_degrees)
+                                    .clamp_range(0.0..=360.0),
+                            );
+                            ui.end_row();
+                            ui.label("Scale");
+                            ui.add(
+                                egui::DragValue::new(&mut snap.scale)
+                                    .speed(0.01)
+                                    .clamp_range(0.0..=f32::MAX),
+                            );
+                            ui.end_row();
+                        });
+                    ui.label("Hold Ctrl while dragging to invert.");
+                });
+            });
+        });
+        if let Some((format, path)) = import_request {
+            self.import(format, &path);
+        }
+        if let Some((format, path)) = export_request {
+            self.export(format, &path);
+        }
+        if let Some(primitive) = add_request {
+            self.add_primitive_node(primitive);
+        }
+        if let Some(light) = add_light_request {
+            self.add_light(light);
+        }
+        match view_request {
+            Some(ViewRequest::Projection(projection)) => self.set_projection(projection),
+            Some(ViewRequest::Preset(preset)) => self.set_view_preset(preset),
+            Some(ViewRequest::Frame { selection_only }) => self.frame(selection_only),
+            Some(ViewRequest::ToggleFlying) => self.toggle_flying(),
+            None => {}
+        }
+        self.update_flight();
+        if let Some(animation) = &self.camera_animation {
+            if !animation.update(&mut self.scene_data.camera) {
+                self.camera_animation = None;
+            }
+            self.info
+                .rend3_renderer
+                .set_camera_data(self.scene_data.camera.to_rend3_camera());
+        }
+
+        let egui_context = self.info.egui_context.clone();
+        egui::Window::new("Outliner")
+            .resizable(true)
+            .show(&egui_context, |ui| self.draw_outliner(ui));
+        egui::Window::new("Properties")
+            .resizable(true)
+            .show(&egui_context, |ui| self.draw_properties(ui));
+        egui::Window::new("Camera")
+            .resizable(true)
+            .default_open(false)
+            .show(&egui_context, |ui| self.draw_camera_properties(ui));
+        egui::Window::new("Bookmarks")
+            .resizable(true)
+            .default_open(false)
+            .show(&egui_context, |ui| self.draw_bookmarks(ui));
+        if let Some(editor) = &mut self.bindings_editor {
+            let mut open = true;
+            let mut applied = None;
+            egui::Window::new("Key bindings")
+                .open(&mut open)
+                .resizable(true)
+                .show(&egui_context, |ui| applied = editor.draw(ui));
+            if let Some(bindings) = applied {
+                callbacks.push(WindowRedrawCallbackCommand::SetBindings(bindings));
+            }
+            if !open {
+                self.bindings_editor = None;
+            }
+        }
+        // An edit lasts for as long as a widget is dragged or typed into.
+        if !egui_context.is_using_pointer()
+            && egui_context.memory(|memory| memory.focus().is_none())
+        {
+            self.commit_properties_edit();
+        }
+
+        if let Some((before, last_scrolled)) = &self.zoom_start {
+            if last_scrolled.elapsed() >= ZOOM_SETTLE_TIME {
+                let after = self.scene_data.camera.get_state();
+                self.history.push(
+                    "Zoom",
+                    SceneCommand::SetCamera {
+                        before: before.clone(),
+                        after,
+                    },
+                );
+                self.zoom_start = None;
+            }
+        }
+
+        let mut history_position = None;
+        egui::Window::new("History")
             .resizable(true)
+            .default_open(false)
             .show(&self.info.egui_context, |ui| {
-                ui.label("Change the color of the cube");
+                history_position = history::draw_egui_history(ui, &self.history);
             });
+        if let Some(position) = history_position {
+            let mut target = SceneTarget {
+                scene: &mut self.scene_data,
+                rend3_renderer: &self.info.rend3_renderer,
+            };
+            self.history.go_to(position, &mut target);
+            self.camera_animation = None;
+        }
 
         egui::Window::new("Console")
             .resizable(true)
@@ -116,6 +1584,45 @@ impl WindowLike for SceneViewer3D {
                 ui::console::draw_egui_logging_lines(ui);
             });
 
+        if let Some((start, end)) = self.selection_box {
+            if start.distance(end) >= CLICK_DISTANCE {
+                let pixels_per_point = self.info.egui_context.pixels_per_point();
+                let to_points = |p: glam::Vec2| egui::Pos2::from((p / pixels_per_point).to_array());
+                self.info
+                    .egui_context
+                    .layer_painter(egui::LayerId::new(
+                        egui::Order::Foreground,
+                        egui::Id::new("selection_box"),
+                    ))
+                    .rect(
+                        egui::Rect::from_two_pos(to_points(start), to_points(end)),
+                        0.0,
+                        egui::Color32::from_white_alpha(16),
+                        egui::Stroke::new(1.0, egui::Color32::WHITE),
+                    );
+            }
+        }
+
+        let window_size = self.info.resolution.as_vec2();
+        let hover_position = if self.info.egui_context.is_pointer_over_area() {
+            None
+        } else {
+            let pixels_per_point = self.info.egui_context.pixels_per_point();
+            self.info
+                .egui_context
+                .input(|input| input.pointer.hover_pos())
+                .map(|position| glam::Vec2::new(position.x, position.y) * pixels_per_point)
+        };
+        self.gizmo
+            .update_hover(&self.scene_data, hover_position, window_size);
+        let mut gizmo_geometry = self.gizmo.geometry(&self.scene_data, window_size);
+        light_icons::add_light_icons(
+            &mut gizmo_geometry,
+            &self.scene_data,
+            self.active_light,
+            window_size,
+        );
+
         let egui::FullOutput {
             shapes,
             textures_delta,
@@ -161,8 +1668,14 @@ impl WindowLike for SceneViewer3D {
             glam::Vec4::new(0.10, 0.05, 0.10, 1.0), // Nice scene-referred purple
         );
 
-        self.grid_render_routine
-            .add_to_graph(&mut graph, depth_target_handle, frame_handle);
+        self.grid_render_routine.add_to_graph(
+            &mut graph,
+            depth_target_handle,
+            frame_handle,
+            self.scene_data.camera.lens(),
+        );
+        self.gizmo_render_routine
+            .add_to_graph(&mut graph, frame_handle, gizmo_geometry);
         self.info
             .egui_routine
             .add_to_graph(&mut graph, input, frame_handle);
@@ -173,16 +1686,51 @@ impl WindowLike for SceneViewer3D {
         // Present the frame
         frame.present();
 
-        None
+        if callbacks.is_empty() {
+            None
+        } else {
+            Some(callbacks)
+        }
     }
 
     fn handle_input_event(&mut self, input_state: &InputState, input_event: input::InputEvent) {
         match input_event {
+            // While flying, moving the mouse turns the camera and scrolling changes how
+            // fast it flies, instead of their usual jobs.
+            input::InputEvent::DoViewportOrbit
+            | input::InputEvent::DoSelectionDrag
+            | input::InputEvent::DoViewportPan
+                if self.flight.is_some() => {}
+            input::InputEvent::ViewportZoom if self.flight.is_some() => {
+                self.fly_control
+                    .change_speed(input_state.mouse.scroll_delta.y());
+            }
+            input::InputEvent::ViewportLook => {
+                if self.flight.is_some() {
+                    self.scene_data
+                        .camera
+                        .fly_look(input_state.mouse.motion_delta.to_glam_vec2());
+                    self.info
+                        .rend3_renderer
+                        .set_camera_data(self.scene_data.camera.to_rend3_camera());
+                }
+            }
+
             input::InputEvent::DoViewportOrbit => {
-                self.scene_data.camera.turntable_rotate(
-                    &input_state.mouse.curr_cursor_pos
-                        - input_state.mouse.cursor_pos_on_pressed.as_ref().unwrap(),
-                    self.info.window_size.into(),
+                // Orbiting takes over from a selection or gizmo drag in progress, and
+                // from the camera moving to a view on its own.
+                self.selection_box = None;
+                self.camera_animation = None;
+                if self.gizmo.is_dragging() {
+                    self.finish_gizmo_drag();
+                }
+                if self.camera_drag_start.is_none() {
+                    self.camera_drag_start = Some(self.scene_data.camera.get_state());
+                }
+                self.navigation.orbit(
+                    &mut self.scene_data.camera,
+                    input_state.mouse.window_cursor_pos.to_glam_vec2(),
+                    self.info.resolution.as_vec2(),
                 );
                 self.info
                     .rend3_renderer
@@ -191,16 +1739,237 @@ impl WindowLike for SceneViewer3D {
             }
 
             input::InputEvent::FinishViewportOrbit => {
-                self.scene_data.camera.solidify_view_info();
+                self.navigation.finish_orbit(&mut self.scene_data.camera);
                 self.info
                     .rend3_renderer
                     .set_camera_data(self.scene_data.camera.to_rend3_camera());
+                // The whole orbit is undone at once.
+                if let Some(before) = self.camera_drag_start.take() {
+                    let after = self.scene_data.camera.get_state();
+                    self.history
+                        .push("Orbit", SceneCommand::SetCamera { before, after });
+                }
                 log::trace!("(event) finish viewport orbit");
             }
+
+            input::InputEvent::DoSelectionDrag => {
+                let cursor = input_state.mouse.window_cursor_pos.to_glam_vec2();
+                let window_size = self.info.resolution.as_vec2();
+                if self.gizmo.is_dragging() {
+                    self.gizmo.drag(
+                        &mut self.scene_data,
+                        cursor,
+                        window_size,
+                        input_state.keyboard.ctrl(),
+                        &self.info.rend3_renderer,
+                    );
+                    return;
+                }
+                match &mut self.selection_box {
+                    Some((_, end)) => *end = cursor,
+                    None => {
+                        if !self.gizmo.begin_drag(&self.scene_data, cursor, window_size) {
+                            self.selection_box = Some((cursor, cursor));
+                        }
+                    }
+                }
+            }
+
+            input::InputEvent::DoViewportPan => {
+                self.camera_animation = None;
+                if self.camera_drag_start.is_none() {
+                    self.camera_drag_start = Some(self.scene_data.camera.get_state());
+                }
+                if self.navigation.pan(
+                    &mut self.scene_data.camera,
+                    input_state.mouse.window_cursor_pos.to_glam_vec2(),
+                    self.info.resolution.as_vec2(),
+                ) {
+                    self.info
+                        .rend3_renderer
+                        .set_camera_data(self.scene_data.camera.to_rend3_camera());
+                }
+                log::trace!("(event) do viewport pan");
+            }
+
+            input::InputEvent::FinishViewportPan => {
+                self.navigation.finish_pan();
+                if let Some(before) = self.camera_drag_start.take() {
+                    let after = self.scene_data.camera.get_state();
+                    self.history
+                        .push("Pan", SceneCommand::SetCamera { before, after });
+                }
+                log::trace!("(event) finish viewport pan");
+            }
+
+            input::InputEvent::ViewportZoom => {
+                self.camera_animation = None;
+                let before = match self.zoom_start.take() {
+                    Some((before, _)) => before,
+                    None => self.scene_data.camera.get_state(),
+                };
+                self.zoom_start = Some((before, std::time::Instant::now()));
+                self.navigation.zoom(
+                    &mut self.scene_data.camera,
+                    input_state.mouse.scroll_delta.y(),
+                    input_state.mouse.window_cursor_pos.to_glam_vec2(),
+                    self.info.resolution.as_vec2(),
+                );
+                self.info
+                    .rend3_renderer
+                    .set_camera_data(self.scene_data.camera.to_rend3_camera());
+                log::trace!("(event) viewport zoom");
+            }
+
+            input::InputEvent::FinishSelection => {
+                if self.gizmo.is_dragging() {
+                    self.finish_gizmo_drag();
+                    log::trace!("(event) finish gizmo drag");
+                } else if let Some((start, _)) = self.selection_box.take() {
+                    let end = input_state.mouse.window_cursor_pos.to_glam_vec2();
+                    self.finish_selection(start, end, input_state.keyboard.ctrl());
+                    log::trace!("(event) finish selection");
+                }
+            }
+        }
+    }
+
+    fn action_pressed(&mut self, action: Action, _input_state: &InputState) {
+        if let Some(flight) = &mut self.flight {
+            if fly_direction(action).is_some() {
+                flight.held.insert(action);
+                return;
+            }
+        }
+
+        let preset = match action {
+            Action::ViewFront => ViewPreset::Front,
+            Action::ViewBack => ViewPreset::Back,
+            Action::ViewRight => ViewPreset::Right,
+            Action::ViewLeft => ViewPreset::Left,
+            Action::ViewTop => ViewPreset::Top,
+            Action::ViewBottom => ViewPreset::Bottom,
+            Action::ToggleProjection => {
+                let projection = match self.scene_data.camera.get_projection() {
+                    ProjectionKind::Perspective => ProjectionKind::Orthographic,
+                    ProjectionKind::Orthographic => ProjectionKind::Perspective,
+                };
+                self.set_projection(projection);
+                return;
+            }
+            Action::FrameSelected => {
+                self.frame(true);
+                return;
+            }
+            Action::FrameAll => {
+                self.frame(false);
+                return;
+            }
+            Action::ToggleFlying => {
+                self.toggle_flying();
+                return;
+            }
+            Action::StopFlying => {
+                self.stop_flying();
+                return;
+            }
+            Action::GoToBookmark(index) => {
+                self.go_to_bookmark(index);
+                return;
+            }
+            _ => return,
+        };
+        self.set_view_preset(preset);
+    }
+
+    fn action_released(&mut self, action: Action, _input_state: &InputState) {
+        if let Some(flight) = &mut self.flight {
+            flight.held.remove(&action);
         }
     }
 
+    fn set_bindings(&mut self, bindings: &ActionMap) {
+        self.bindings = bindings.clone();
+    }
+
     fn close_requested(&mut self) -> WindowCloseCallbackCommand {
         WindowCloseCallbackCommand::QuitProgram
     }
+
+    fn undo(&mut self) {
+        self.commit_properties_edit();
+        self.stop_flying();
+        self.camera_animation = None;
+        let mut target = SceneTarget {
+            scene: &mut self.scene_data,
+            rend3_renderer: &self.info.rend3_renderer,
+        };
+        if !self.history.undo(&mut target) {
+            log::info!("nothing to undo");
+        }
+    }
+
+    fn redo(&mut self) {
+        self.commit_properties_edit();
+        self.stop_flying();
+        self.camera_animation = None;
+        let mut target = SceneTarget {
+            scene: &mut self.scene_data,
+            rend3_renderer: &self.info.rend3_renderer,
+        };
+        if !self.history.redo(&mut target) {
+            log::info!("nothing to redo");
+        }
+    }
+
+    fn add_primitive(&mut self, primitive: Primitive) -> bool {
+        self.add_primitive_node(primitive);
+        true
+    }
+
+    fn plugin_scene(&self, width: u32, height: u32) -> Option<PluginScene> {
+        Some(PluginScene::new(&self.scene_data, width, height))
+    }
+
+    fn save_to_project(&self, project: &mut Project) {
+        if project.scene_owner == Some(self.info.window_id) {
+            project.scene = Some(self.scene_data.to_description());
+        }
+    }
+
+    fn load_from_project(&mut self, project: &mut Project) -> anyhow::Result<()> {
+        if !project.claim_scene(self.info.window_id) {
+            log::info!("the project's scene is in another 3D viewer, which saves it");
+            return Ok(());
+        }
+        if let Some(scene) = project.scene.take() {
+            self.stop_flying();
+            self.scene_data
+                .load_description(scene, &self.info.rend3_renderer);
+            // The history, and any edit in progress, refer to nodes of the scene that was
+            // replaced.
+            self.history.clear();
+            self.gizmo.end_drag();
+            self.selection_box = None;
+            self.camera_drag_start = None;
+            self.euler_angles = None;
+            self.properties_edit = None;
+            self.light_edit = None;
+            self.bookmarks_edit = None;
+            self.camera_edit = None;
+            self.active_light = None;
+            self.zoom_start = None;
+            self.camera_animation = None;
+
+            // The saved camera knows nothing about the size of this window.
+            self.scene_data
+                .camera
+                .handle_window_resize(self.info.resolution.x as f32, self.info.resolution.y as f32);
+            self.info
+                .rend3_renderer
+                .set_camera_data(self.scene_data.camera.to_rend3_camera());
+        }
+
+        Ok(())
+    }
 }